@@ -0,0 +1,455 @@
+//! The `ctmc.rs` file contains the tools needed to solve continuous time Markov chains
+//! numerically, to get exact references for the simulations of Markovian models.
+//!
+//! It also builds the CTMC of the farm of servers with setup of `policy.rs`, when the arrival,
+//! service, setup and idle timer laws are all Exponential, over a state space truncated at a
+//! maximal number of clients.
+use crate::policy::Policy;
+
+/// Solve the linear system `a x = b` with a Gaussian elimination (with partial pivoting).
+///
+/// The matrix is given as a list of rows, and must be square and invertible.
+pub fn solve_linear_system(a: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let n = b.len();
+    assert_eq!(a.len(), n);
+
+    let mut m: Vec<Vec<f64>> = a.to_vec();
+    let mut x: Vec<f64> = b.to_vec();
+
+    for col in 0..n {
+        // the row with the biggest pivot is put in place, for numerical stability
+        let pivot = (col..n)
+            .max_by(|&i, &j| m[i][col].abs().partial_cmp(&m[j][col].abs()).unwrap())
+            .unwrap();
+        m.swap(col, pivot);
+        x.swap(col, pivot);
+        assert!(m[col][col] != 0.0, "the matrix is singular");
+
+        for row in col + 1..n {
+            let factor = m[row][col] / m[col][col];
+            if factor == 0.0 {
+                continue;
+            }
+            let (upper, lower) = m.split_at_mut(row);
+            for (target, &p) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *target -= factor * p;
+            }
+            x[row] -= factor * x[col];
+        }
+    }
+
+    for col in (0..n).rev() {
+        let s = (col + 1..n).map(|k| m[col][k] * x[k]).sum::<f64>();
+        x[col] = (x[col] - s) / m[col][col];
+    }
+    x
+}
+
+/// Compute the stationary distribution `π` of an irreducible CTMC, that is the solution of
+/// `π Q = 0` with `∑ π = 1`.
+///
+/// Arguments:
+///
+/// * `q`: The generator matrix, as a list of rows. Every row must sum to `0`.
+///
+/// Returns:
+///
+/// The probability of every state, in the same order as the rows of `q`.
+pub fn stationary_distribution(q: &[Vec<f64>]) -> Vec<f64> {
+    let n = q.len();
+
+    // π Q = 0 is solved as Qᵀ πᵀ = 0, where the last equation is replaced by the normalisation.
+    let mut a = (0..n)
+        .map(|i| (0..n).map(|j| q[j][i]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    a[n - 1] = vec![1.0; n];
+    let mut b = vec![0.0; n];
+    b[n - 1] = 1.0;
+
+    solve_linear_system(&a, &b)
+}
+
+/// Compute the transient distribution `π(t) = π(0) e^(Qt)` of a CTMC, by uniformisation.
+///
+/// With `Λ` at least the biggest exit rate, `P = I + Q / Λ` is a stochastic matrix and
+/// `e^(Qt) = ∑_k e^(-Λt) (Λt)^k / k! P^k`: the chain jumps at the times of a Poisson process of rate
+/// `Λ`, following `P` (a jump may go nowhere). The time is cut in steps short enough for the
+/// Poisson weights not to underflow, and the sum of each step is stopped once the weights left
+/// are negligible.
+///
+/// Arguments:
+///
+/// * `q`: The generator matrix, as a list of rows. Every row must sum to `0`.
+/// * `initial`: The distribution `π(0)` at time `0`.
+/// * `t`: The time.
+///
+/// Returns:
+///
+/// The probability of every state at time `t`, in the same order as the rows of `q`.
+pub fn transient_distribution(q: &[Vec<f64>], initial: &[f64], t: f64) -> Vec<f64> {
+    let n = q.len();
+    assert_eq!(initial.len(), n);
+    let uniformisation_rate = (0..n).map(|i| -q[i][i]).fold(0.0, f64::max);
+    if uniformisation_rate == 0.0 || t <= 0.0 {
+        return initial.to_vec();
+    }
+
+    // the row vector `v` multiplied by `P`
+    let jump = |v: &[f64]| {
+        let mut next = v.to_vec();
+        for (i, &p) in v.iter().enumerate().filter(|(_, &p)| p != 0.0) {
+            for (target, rate) in next.iter_mut().zip(&q[i]) {
+                *target += p * rate / uniformisation_rate;
+            }
+        }
+        next
+    };
+
+    let steps = (uniformisation_rate * t / 50.0).ceil();
+    let jumps_by_step = uniformisation_rate * t / steps;
+    let mut pi = initial.to_vec();
+    for _ in 0..steps as usize {
+        let mut weight = (-jumps_by_step).exp();
+        let mut cumulative = weight;
+        let mut v = pi.clone();
+        let mut next = v.iter().map(|p| weight * p).collect::<Vec<_>>();
+        let mut k = 0.0;
+        while cumulative < 1.0 - 1e-14 {
+            k += 1.0;
+            v = jump(&v);
+            weight *= jumps_by_step / k;
+            cumulative += weight;
+            for (target, p) in next.iter_mut().zip(&v) {
+                *target += weight * p;
+            }
+        }
+        pi = next;
+    }
+    pi
+}
+
+/// A farm of servers with setup whose laws are all Exponential, managed by a power-down policy
+/// (see `policy_queue`), with a limited number of clients.
+///
+/// A state is the number `n` of clients in the system together with the number `on` of servers that
+/// are on (busy or idle): the clients are served by the servers that are on, and the number of
+/// servers warming up is then fixed by the policy (one for every `threshold` waiting clients, among
+/// the servers that are off). The state `(n, on)` is numbered `n (servers + 1) + on`.
+///
+/// With one server, no idle timer, a threshold of `1` and a big capacity, it is the M/M/1 queue with
+/// setup of `SetupQueue`.
+///
+/// Properties:
+///
+/// * `lambda`: The rate of the arrivals (Poisson process).
+/// * `mu`: The rate of the Exponential service times.
+/// * `theta`: The rate of the Exponential setup times.
+/// * `policy`: The power-down policy. The idle timer is Exponential, with the `idle_timer` of the
+///   policy as mean (`0.0` to turn off at once, `f64::INFINITY` to never turn off).
+/// * `capacity`: The maximal number of clients in the system, the arrivals that find it full are
+///   lost. For an infinite system, it is the truncation of the state space, which must be big
+///   enough for the blocking probability to be negligible.
+#[derive(Copy, Clone, Debug)]
+pub struct MarkovianFarm {
+    pub lambda: f64,
+    pub mu: f64,
+    pub theta: f64,
+    pub policy: Policy,
+    pub capacity: usize,
+}
+
+/// The exact metrics of a `MarkovianFarm`, the same ones as the simulations report.
+///
+/// Properties:
+///
+/// * `jobs_in_system_distribution`: The probability of every number of clients in the system, from
+///   `0` to the capacity.
+/// * `avg_servers_off`: The average number of servers that are off (with one server, the
+///   probability that it is off).
+/// * `avg_servers_setup`: The average number of servers that are warming up.
+/// * `avg_servers_idle`: The average number of servers that are on without client.
+/// * `avg_servers_busy`: The average number of servers that are serving a client.
+/// * `avg_jobs_in_system`: The average number of clients in the system.
+/// * `avg_jobs_in_queue`: The average number of clients waiting for a server.
+/// * `blocking_probability`: The probability that an arrival finds the system full.
+/// * `throughput`: The number of clients served by unit of time.
+/// * `avg_stay`: The average time spent in the system by a client (by Little's law).
+/// * `avg_waiting`: The average time spent waiting for a server by a client.
+/// * `switch_on_rate`: The number of setups started by unit of time (cancelled ones included).
+#[derive(Clone, Debug)]
+pub struct FarmMetrics {
+    pub jobs_in_system_distribution: Vec<f64>,
+    pub avg_servers_off: f64,
+    pub avg_servers_setup: f64,
+    pub avg_servers_idle: f64,
+    pub avg_servers_busy: f64,
+    pub avg_jobs_in_system: f64,
+    pub avg_jobs_in_queue: f64,
+    pub blocking_probability: f64,
+    pub throughput: f64,
+    pub avg_stay: f64,
+    pub avg_waiting: f64,
+    pub switch_on_rate: f64,
+}
+
+impl MarkovianFarm {
+    /// The number of the state `(n, on)`.
+    fn index(&self, n: usize, on: usize) -> usize {
+        n * (self.policy.servers + 1) + on
+    }
+
+    /// The number of busy servers, waiting clients and servers warming up in the state `(n, on)`.
+    fn occupation(&self, n: usize, on: usize) -> (usize, usize, usize) {
+        let busy = n.min(on);
+        let waiting = n - busy;
+        let setup = (waiting / self.policy.threshold).min(self.policy.servers - on);
+        (busy, waiting, setup)
+    }
+
+    /// The transitions out of the state `(n, on)`: the next state, the rate, and the number of setups
+    /// started by the transition (the policy may start some when a client arrives, or when a setup
+    /// ends and the queue is still long).
+    fn transitions(&self, n: usize, on: usize) -> Vec<(usize, usize, f64, usize)> {
+        let (busy, waiting, setup) = self.occupation(n, on);
+        let idle = on - busy;
+        let mut transitions = Vec::new();
+        if n < self.capacity {
+            let started = self.occupation(n + 1, on).2.saturating_sub(setup);
+            transitions.push((n + 1, on, self.lambda, started));
+        }
+        if setup > 0 {
+            let started = self.occupation(n, on + 1).2 + 1 - setup;
+            transitions.push((n, on + 1, setup as f64 * self.theta, started));
+        }
+        if busy > 0 {
+            // without waiting client, the server is idle or turns off at once
+            let on_after = if waiting == 0 && self.policy.idle_timer == 0.0 {
+                on - 1
+            } else {
+                on
+            };
+            transitions.push((n - 1, on_after, busy as f64 * self.mu, 0));
+        }
+        if idle > 0 && self.policy.idle_timer > 0.0 && self.policy.idle_timer.is_finite() {
+            transitions.push((n, on - 1, idle as f64 / self.policy.idle_timer, 0));
+        }
+        transitions
+    }
+
+    /// Build the generator matrix of the farm.
+    pub fn generator(&self) -> Vec<Vec<f64>> {
+        assert!(self.policy.servers > 0 && self.policy.threshold > 0);
+        let size = (self.capacity + 1) * (self.policy.servers + 1);
+        let mut q = vec![vec![0.0; size]; size];
+        for n in 0..=self.capacity {
+            for on in 0..=self.policy.servers {
+                for (next_n, next_on, rate, _) in self.transitions(n, on) {
+                    q[self.index(n, on)][self.index(next_n, next_on)] += rate;
+                }
+            }
+        }
+        for (i, row) in q.iter_mut().enumerate() {
+            row[i] = -row.iter().sum::<f64>();
+        }
+        q
+    }
+
+    /// Solve the farm: its stationary distribution, and the metrics derived from it.
+    pub fn solve(&self) -> FarmMetrics {
+        self.metrics(&stationary_distribution(&self.generator()))
+    }
+
+    /// The distribution of the states of the farm at every given time, starting empty with every
+    /// server off, by uniformisation.
+    pub fn transient(&self, times: &[f64]) -> Vec<Vec<f64>> {
+        let q = self.generator();
+        let mut initial = vec![0.0; q.len()];
+        initial[self.index(0, 0)] = 1.0;
+        // every distribution starts from the previous one
+        let mut previous = (0.0, initial);
+        times
+            .iter()
+            .map(|&t| {
+                assert!(t >= previous.0, "the times must be increasing");
+                previous = (t, transient_distribution(&q, &previous.1, t - previous.0));
+                previous.1.clone()
+            })
+            .collect()
+    }
+
+    /// The metrics of the farm in a given distribution of its states, the stationary one or a
+    /// transient one. In a transient distribution, `throughput` and `switch_on_rate` are the
+    /// rates at that time, while `avg_stay` and `avg_waiting` (given by Little's law) are only
+    /// meaningful at steady state.
+    pub fn metrics(&self, pi: &[f64]) -> FarmMetrics {
+        let c = self.policy.servers;
+        let states = || (0..=self.capacity).flat_map(move |n| (0..=c).map(move |on| (n, on)));
+        // the expectation of a function of the state
+        let expectation = |f: &dyn Fn(usize, usize) -> f64| {
+            states()
+                .map(|(n, on)| pi[self.index(n, on)] * f(n, on))
+                .sum::<f64>()
+        };
+
+        let jobs_in_system_distribution = (0..=self.capacity)
+            .map(|n| (0..=c).map(|on| pi[self.index(n, on)]).sum())
+            .collect::<Vec<f64>>();
+        let blocking_probability = jobs_in_system_distribution[self.capacity];
+        let throughput = self.lambda * (1.0 - blocking_probability);
+        let avg_jobs_in_system = expectation(&|n, _| n as f64);
+        let avg_jobs_in_queue = expectation(&|n, on| self.occupation(n, on).1 as f64);
+
+        FarmMetrics {
+            avg_servers_off: expectation(&|n, on| (c - on - self.occupation(n, on).2) as f64),
+            avg_servers_setup: expectation(&|n, on| self.occupation(n, on).2 as f64),
+            avg_servers_idle: expectation(&|n, on| (on - self.occupation(n, on).0) as f64),
+            avg_servers_busy: expectation(&|n, on| self.occupation(n, on).0 as f64),
+            avg_jobs_in_system,
+            avg_jobs_in_queue,
+            blocking_probability,
+            throughput,
+            avg_stay: avg_jobs_in_system / throughput,
+            avg_waiting: avg_jobs_in_queue / throughput,
+            switch_on_rate: expectation(&|n, on| {
+                self.transitions(n, on)
+                    .iter()
+                    .map(|&(_, _, rate, started)| rate * started as f64)
+                    .sum()
+            }),
+            jobs_in_system_distribution,
+        }
+    }
+
+    /// The average stay of a client arriving when the distribution of the states is `pi`, for the
+    /// single server that turns off at once (the M/M/1 queue with setup): the setup if the server
+    /// is not on (the remaining one if it is already warming up, as it is Exponential), then the
+    /// services of the clients already there and its own. The clients finding the system full are
+    /// not counted.
+    pub fn single_server_arrival_stay(&self, pi: &[f64]) -> f64 {
+        assert!(
+            self.policy.servers == 1 && self.policy.idle_timer == 0.0 && self.policy.threshold == 1
+        );
+        (0..self.capacity)
+            .flat_map(|n| (0..=1).map(move |on| (n, on)))
+            .map(|(n, on)| {
+                let setup = if on == 0 { 1.0 / self.theta } else { 0.0 };
+                pi[self.index(n, on)] * (setup + (n + 1) as f64 / self.mu)
+            })
+            .sum::<f64>()
+            / (1.0 - pi[self.index(self.capacity, 0)] - pi[self.index(self.capacity, 1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theory::SetupQueue;
+    use crate::variables::{ExponentialParameter, Parameter};
+
+    /// The M/M/1 queue with setup, as a farm of one server that turns off as soon as it is idle.
+    fn single_server(lambda: f64, mu: f64, theta: f64, capacity: usize) -> MarkovianFarm {
+        MarkovianFarm {
+            lambda,
+            mu,
+            theta,
+            policy: Policy {
+                servers: 1,
+                idle_timer: 0.0,
+                threshold: 1,
+            },
+            capacity,
+        }
+    }
+
+    #[test]
+    fn truncated_farm_matches_the_setup_queue() {
+        let (lambda, mu, theta) = (1.0, 1.0 / 0.7, 0.6);
+        let metrics = single_server(lambda, mu, theta, 500).solve();
+        let exact = SetupQueue {
+            lambda,
+            service: Parameter::Exponential(ExponentialParameter { lambda: mu }).moments(),
+            setup: Parameter::Exponential(ExponentialParameter { lambda: theta }).moments(),
+        };
+
+        assert!(metrics.blocking_probability < 1e-12);
+        assert!((metrics.avg_jobs_in_system - exact.avg_in_system()).abs() < 1e-9);
+        assert!((metrics.avg_stay - exact.avg_stay()).abs() < 1e-9);
+        assert!((metrics.avg_servers_off - exact.probability_p_off()).abs() < 1e-9);
+        assert!((metrics.avg_servers_setup - exact.probability_p_setup()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn transient_distribution_tends_to_the_stationary_one() {
+        let farm = single_server(1.0, 1.0 / 0.7, 0.6, 60);
+        let q = farm.generator();
+        let mut initial = vec![0.0; q.len()];
+        initial[farm.index(0, 0)] = 1.0;
+
+        let stationary = stationary_distribution(&q);
+        let transient = transient_distribution(&q, &initial, 500.0);
+        assert!((transient.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        for (p, s) in transient.iter().zip(&stationary) {
+            assert!((p - s).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn farm_that_never_turns_off_is_the_erlang_c_queue() {
+        // M/M/3 with a = λ / μ = 2: P(wait) = (a^c / c!) (c / (c - a)) / (∑_{k<c} a^k / k! + ...)
+        let (lambda, mu, c) = (2.0, 1.0, 3);
+        let a: f64 = lambda / mu;
+        let tail = a.powi(3) / 6.0 * 3.0 / (3.0 - a);
+        let waiting_probability = tail / (1.0 + a + a * a / 2.0 + tail);
+        let rho = a / c as f64;
+        let avg_jobs_in_queue = waiting_probability * rho / (1.0 - rho);
+
+        // once on, the servers stay on: the threshold only matters before, in transient states
+        for threshold in [1, 2] {
+            let metrics = MarkovianFarm {
+                lambda,
+                mu,
+                theta: 0.6,
+                policy: Policy {
+                    servers: c,
+                    idle_timer: f64::INFINITY,
+                    threshold,
+                },
+                capacity: 100,
+            }
+            .solve();
+            assert!(metrics.blocking_probability < 1e-12);
+            assert!(metrics.avg_servers_off.abs() < 1e-9);
+            assert!(metrics.avg_servers_setup.abs() < 1e-9);
+            assert!((metrics.avg_servers_busy - a).abs() < 1e-9);
+            assert!((metrics.avg_jobs_in_queue - avg_jobs_in_queue).abs() < 1e-9);
+            assert!((metrics.avg_jobs_in_system - avg_jobs_in_queue - a).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn small_capacity_farm_is_the_mm1k_queue() {
+        // M/M/1/K: P(N = n) = (1 - ρ) ρ^n / (1 - ρ^(K+1))
+        let (lambda, mu, capacity) = (1.0, 1.25, 5);
+        let rho: f64 = lambda / mu;
+        let probability = |n: i32| (1.0 - rho) * rho.powi(n) / (1.0 - rho.powi(capacity + 1));
+
+        let metrics = MarkovianFarm {
+            lambda,
+            mu,
+            theta: 0.6,
+            policy: Policy {
+                servers: 1,
+                idle_timer: f64::INFINITY,
+                threshold: 1,
+            },
+            capacity: capacity as usize,
+        }
+        .solve();
+        let blocking_probability = probability(capacity);
+        assert!((metrics.blocking_probability - blocking_probability).abs() < 1e-12);
+        assert!((metrics.throughput - lambda * (1.0 - blocking_probability)).abs() < 1e-12);
+        for (n, p) in metrics.jobs_in_system_distribution.iter().enumerate() {
+            assert!((p - probability(n as i32)).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! The `energy.rs` file contains the power model of the server: turning it off saves energy, at
+//! the price of the setups, which cost both time (the clients wait) and energy. The combined cost
+//! weighs the two, to compare the power-down policies.
+use crate::measures::{confidence_interval, ConfidenceInterval, Mean, DEFAULT_CONFIDENCE_LEVEL};
+use crate::policy::PolicySimulation;
+use crate::simulation::Simulation;
+use crate::theory::SetupQueue;
+
+/// The power drawn by the server in each of its states.
+///
+/// Properties:
+///
+/// * `off`: The power (in watts) when the server is off.
+/// * `setup`: The power when the server is warming up.
+/// * `idle`: The power when the server is on but has no client to serve.
+/// * `busy`: The power when the server is serving a client.
+/// * `switch_on_energy`: The energy (in joules) spent by every switch-on, on top of the power of
+///   the setup, if any.
+#[derive(Copy, Clone, Debug)]
+pub struct PowerModel {
+    pub off: f64,
+    pub setup: f64,
+    pub idle: f64,
+    pub busy: f64,
+    pub switch_on_energy: Option<f64>,
+}
+
+impl PowerModel {
+    /// The average power drawn by the server during a simulation.
+    pub fn avg_power(&self, simulation: &Simulation) -> f64 {
+        let on = simulation.probability_server_on();
+        let setup = simulation.probability_server_setup();
+        let off = simulation.probability_server_off();
+        let idle = (1.0 - on - setup - off).max(0.0);
+        self.off * off
+            + self.setup * setup
+            + self.idle * idle
+            + self.busy * on
+            + self.switch_on_energy.unwrap_or(0.0) * simulation.switch_on_rate()
+    }
+
+    /// The average power drawn by a farm of servers managed by a power-down policy, summed over the
+    /// servers.
+    pub fn avg_power_of_farm(&self, simulation: &PolicySimulation) -> f64 {
+        self.off * simulation.avg_servers_off()
+            + self.setup * simulation.avg_servers_setup()
+            + self.idle * simulation.avg_servers_idle()
+            + self.busy * simulation.avg_servers_busy()
+            + self.switch_on_energy.unwrap_or(0.0) * simulation.switch_on_rate()
+    }
+
+    /// The energy spent by client served during a simulation.
+    pub fn energy_per_job(&self, simulation: &Simulation) -> f64 {
+        self.avg_power(simulation) / simulation.throughput()
+    }
+
+    /// The theoretical average power of the M/G/1 queue with setup. The server is never idle, and
+    /// switches on once by cycle.
+    pub fn theoretical_avg_power(&self, queue: &SetupQueue) -> f64 {
+        self.off * queue.probability_p_off()
+            + self.setup * queue.probability_p_setup()
+            + self.busy * queue.probability_p_busy()
+            + self.switch_on_energy.unwrap_or(0.0) / queue.avg_cycle()
+    }
+
+    /// The theoretical energy by client, every client being served in the long run.
+    pub fn theoretical_energy_per_job(&self, queue: &SetupQueue) -> f64 {
+        self.theoretical_avg_power(queue) / queue.lambda
+    }
+}
+
+/// The weights of the combined cost `c_delay 𝔼[S] + c_power 𝔼[P]`.
+///
+/// Properties:
+///
+/// * `delay`: The cost of a unit of time spent in the system by a client.
+/// * `power`: The cost of a watt drawn by the server.
+#[derive(Copy, Clone, Debug)]
+pub struct CostWeights {
+    pub delay: f64,
+    pub power: f64,
+}
+
+impl CostWeights {
+    /// The combined cost of an average stay and an average power.
+    pub fn cost(&self, avg_stay: f64, avg_power: f64) -> f64 {
+        self.delay * avg_stay + self.power * avg_power
+    }
+}
+
+/// The energy measures of a set of simulations, in the spirit of `Data`.
+///
+/// Properties:
+///
+/// * `rho`: The load of the server.
+/// * `theta`: The parameter of the setup times (Exponential).
+/// * `avg_stay_time`: The average time spent in the system.
+/// * `avg_power`: The confidence interval of the average power.
+/// * `energy_per_job`: The confidence interval of the energy by client.
+/// * `cost`: The confidence interval of the combined cost.
+#[derive(Copy, Clone, Debug)]
+pub struct EnergyData {
+    pub rho: f64,
+    pub theta: f64,
+    pub avg_stay_time: f64,
+    pub avg_power: ConfidenceInterval,
+    pub energy_per_job: ConfidenceInterval,
+    pub cost: ConfidenceInterval,
+}
+
+impl EnergyData {
+    /// Compute the energy measures of some simulations, with the given power model and costs.
+    pub fn from_simulations(
+        rho: f64,
+        theta: f64,
+        simulations: &[Simulation],
+        power: &PowerModel,
+        weights: &CostWeights,
+    ) -> Self {
+        let interval = |samples: Vec<f64>| confidence_interval(&samples, DEFAULT_CONFIDENCE_LEVEL);
+        Self {
+            rho,
+            theta,
+            avg_stay_time: simulations.iter().map(|s| s.avg_stay()).calculate_mean(),
+            avg_power: interval(simulations.iter().map(|s| power.avg_power(s)).collect()),
+            energy_per_job: interval(
+                simulations
+                    .iter()
+                    .map(|s| power.energy_per_job(s))
+                    .collect(),
+            ),
+            cost: interval(
+                simulations
+                    .iter()
+                    .map(|s| weights.cost(s.avg_stay(), power.avg_power(s)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! The `finite_source.rs` file contains the closed-population model (machine-repairman style):
+//! `N` users think for a random time, submit a request to the server and wait for its answer
+//! before thinking again. The server follows the same off/setup/busy lifecycle as in `queue`.
+use crate::ctmc::stationary_distribution;
+use crate::simulation::Simulation;
+use crate::variables::{generator, Parameter};
+use std::error::Error;
+
+/// The `finite_source_queue` function simulates `n` requests submitted by `users` users.
+///
+/// Since a user can only have one request at a time in the system, the arrivals depend on the
+/// departures, so the arrivals can not be generated beforehand like in `queue`. Instead, every user
+/// knows the moment at which he will submit his next request, and the requests are served in the
+/// order they are submitted.
+///
+/// Arguments:
+///
+/// * `n`: The number of requests that will be served.
+/// * `users`: The size of the population.
+/// * `think_param`: The parameter of the law that will describe the think times.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+///
+/// Returns:
+///
+/// A `Simulation` containing all the useful information for further calculations.
+pub fn finite_source_queue(
+    n: usize,
+    users: usize,
+    think_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+) -> Result<Simulation, Box<dyn Error>> {
+    assert!(users > 0);
+
+    // every user starts by thinking
+    let mut next_submissions: Vec<f64> = generator(think_param, users);
+    let service_times: Vec<f64> = generator(service_param, n);
+
+    let mut arrivals: Vec<f64> = Vec::new();
+    let mut warmups: Vec<f64> = Vec::new();
+    let mut delays: Vec<f64> = Vec::new();
+    let mut nap_times: Vec<f64> = Vec::new();
+
+    let mut previous_client_departure = 0.0;
+    for &service_time in &service_times {
+        // the next request is the one of the user that stops thinking first
+        let (user, &client_arrival) = next_submissions
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .unwrap();
+
+        let (delay, nap_time, warmup) = if client_arrival > previous_client_departure {
+            (
+                0.0,
+                client_arrival - previous_client_departure,
+                generator(warming_up_param, 1)[0],
+            )
+        } else {
+            (previous_client_departure - client_arrival, 0.0, 0.0)
+        };
+        arrivals.push(client_arrival);
+        warmups.push(warmup);
+        delays.push(delay);
+        nap_times.push(nap_time);
+
+        previous_client_departure = client_arrival + delay + warmup + service_time;
+        // once served, the user thinks again before submitting his next request
+        next_submissions[user] = previous_client_departure + generator(think_param, 1)[0];
+    }
+
+    Ok(Simulation::new(
+        arrivals,
+        delays,
+        warmups,
+        service_times,
+        nap_times,
+    ))
+}
+
+/// The exact metrics of the M/M/1//N queue with setup.
+///
+/// Properties:
+///
+/// * `users`: The size of the population.
+/// * `throughput`: The number of requests served by unit of time.
+/// * `avg_response`: The average time between the submission of a request and its answer.
+/// * `avg_in_system`: The average number of requests in the system.
+/// * `probability_p_off`: The probability that the server is off.
+/// * `probability_p_setup`: The probability that the server is warming up.
+#[derive(Copy, Clone)]
+pub struct FiniteSourceMetrics {
+    pub users: usize,
+    pub throughput: f64,
+    pub avg_response: f64,
+    pub avg_in_system: f64,
+    pub probability_p_off: f64,
+    pub probability_p_setup: f64,
+}
+
+/// Solve the M/M/1//N queue with setup as a CTMC.
+///
+/// A state is the number `n` of requests in the system together with the state of the server. When
+/// the system is empty the server is off, otherwise it is either warming up or busy. The states are
+/// numbered as `0` for the empty system, `2n - 1` for `n` requests with the server warming up and
+/// `2n` for `n` requests with the server busy.
+///
+/// Arguments:
+///
+/// * `users`: The size of the population.
+/// * `think_rate`: The parameter of the Exponential think times.
+/// * `mu`: The parameter of the Exponential service times.
+/// * `theta`: The parameter of the Exponential warmup times.
+pub fn theoretic_finite_source(
+    users: usize,
+    think_rate: f64,
+    mu: f64,
+    theta: f64,
+) -> FiniteSourceMetrics {
+    let size = 2 * users + 1;
+    let setup = |n: usize| 2 * n - 1;
+    let busy = |n: usize| 2 * n;
+
+    let mut q = vec![vec![0.0; size]; size];
+    q[0][setup(1)] = users as f64 * think_rate;
+    for n in 1..=users {
+        let arrival_rate = (users - n) as f64 * think_rate;
+        if n < users {
+            q[setup(n)][setup(n + 1)] = arrival_rate;
+            q[busy(n)][busy(n + 1)] = arrival_rate;
+        }
+        q[setup(n)][busy(n)] = theta;
+        q[busy(n)][if n == 1 { 0 } else { busy(n - 1) }] = mu;
+    }
+    for (i, row) in q.iter_mut().enumerate() {
+        row[i] = -row.iter().sum::<f64>();
+    }
+
+    let pi = stationary_distribution(&q);
+
+    let probability_p_busy = (1..=users).map(|n| pi[busy(n)]).sum::<f64>();
+    let probability_p_setup = (1..=users).map(|n| pi[setup(n)]).sum::<f64>();
+    let avg_in_system = (1..=users)
+        .map(|n| n as f64 * (pi[busy(n)] + pi[setup(n)]))
+        .sum::<f64>();
+    let throughput = mu * probability_p_busy;
+
+    FiniteSourceMetrics {
+        users,
+        throughput,
+        // Little's law
+        avg_response: avg_in_system / throughput,
+        avg_in_system,
+        probability_p_off: pi[0],
+        probability_p_setup,
+    }
+}
@@ -0,0 +1,170 @@
+//! The `fork_join.rs` file contains the fork-join model: every job is split into `k` sub-tasks that
+//! are sent to `k` parallel servers, and the job only leaves the system when its last sub-task is
+//! done. Each of those servers follows the same off/setup/busy lifecycle as in `queue`.
+use crate::measures::Mean;
+use crate::simulation::{single_server, Simulation};
+use crate::variables::{generator, Parameter};
+use std::error::Error;
+
+/// The fork-join simulation struct groups the information of every branch server.
+///
+/// Properties:
+///
+/// * `arrivals`: List of the jobs arrivals, the exact time at which they entered the system.
+/// * `branches`: One `Simulation` by branch server, each one being fed with one sub-task of every
+///   job.
+/// * `departures`: List of the jobs departure, that is the moment when their last sub-task is done.
+/// * `total_time`: The total time, from the beginning to the end.
+pub struct ForkJoinSimulation {
+    arrivals: Vec<f64>,
+    branches: Vec<Simulation>,
+    // computed
+    departures: Vec<f64>,
+    total_time: Option<f64>,
+}
+
+/// Constructor & static functions
+impl ForkJoinSimulation {
+    /// Create a new `ForkJoinSimulation`, the departure of each job is computed automatically.
+    ///
+    /// Arguments:
+    ///
+    /// * `arrivals`: List of the jobs arrivals, the exact time at which they entered the system.
+    /// * `branches`: One `Simulation` by branch server, they must all have been fed with the same
+    ///   arrivals.
+    ///
+    /// Returns:
+    ///
+    /// A `ForkJoinSimulation` object (struct).
+    pub fn new(arrivals: Vec<f64>, branches: Vec<Simulation>) -> Self {
+        // sanity check
+        assert!(!branches.is_empty());
+        for branch in &branches {
+            assert_eq!(arrivals.len(), branch.departure_times().len());
+        }
+
+        let departures = (0..arrivals.len())
+            .map(|i| {
+                branches
+                    .iter()
+                    .map(|b| b.departure_times()[i])
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+            .collect::<Vec<_>>();
+        let total_time = departures.iter().cloned().reduce(f64::max);
+        Self {
+            arrivals,
+            branches,
+            departures,
+            total_time,
+        }
+    }
+}
+
+/// Methods of the ForkJoinSimulation struct
+impl ForkJoinSimulation {
+    /// The simulation of every branch server, to compute their own metrics.
+    pub fn branches(&self) -> &[Simulation] {
+        &self.branches
+    }
+
+    /// The response time of every job, from its arrival to the end of its last sub-task.
+    pub fn response_times(&self) -> Vec<f64> {
+        self.arrivals
+            .iter()
+            .zip(&self.departures)
+            .map(|(&a, &d)| d - a)
+            .collect()
+    }
+
+    /// The average response time of the jobs.
+    pub fn avg_response(&self) -> f64 {
+        self.response_times().iter().calculate_mean()
+    }
+
+    /// The time spent by every sub-task in the synchronisation queue, waiting for its siblings to
+    /// be done. A sub-task that is the last one of its job does not wait at all.
+    pub fn sync_times(&self) -> Vec<f64> {
+        self.branches
+            .iter()
+            .flat_map(|b| {
+                b.departure_times()
+                    .iter()
+                    .zip(&self.departures)
+                    .map(|(&branch_departure, &d)| d - branch_departure)
+            })
+            .collect()
+    }
+
+    /// The average time spent by a sub-task in the synchronisation queue.
+    pub fn avg_sync_time(&self) -> f64 {
+        self.sync_times().iter().calculate_mean()
+    }
+
+    /// The time average number of sub-tasks sitting in the synchronisation queue.
+    /// By Little's law it is the total time spent there divided by the total time.
+    pub fn avg_sync_queue_length(&self) -> f64 {
+        self.sync_times().iter().sum::<f64>() / self.total_time.unwrap()
+    }
+
+    /// The average, over the branch servers, of the probability of finding a server off.
+    pub fn probability_server_off(&self) -> f64 {
+        self.branches
+            .iter()
+            .map(|b| b.probability_server_off())
+            .calculate_mean()
+    }
+
+    /// The average, over the branch servers, of the probability of finding a server warming up.
+    pub fn probability_server_setup(&self) -> f64 {
+        self.branches
+            .iter()
+            .map(|b| b.probability_server_setup())
+            .calculate_mean()
+    }
+}
+
+/// The `fork_join_queue` function simulates `n` jobs that are each split into `k` sub-tasks.
+///
+/// All the branch servers see the same arrivals, but every sub-task draws its own service time and
+/// every branch server has its own warmups.
+///
+/// Arguments:
+///
+/// * `n`: The number of jobs that will enter the system.
+/// * `k`: The number of sub-tasks by job, which is also the number of servers.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the sub-tasks services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+///
+/// Returns:
+///
+/// A `ForkJoinSimulation` containing all the useful information for further calculations.
+pub fn fork_join_queue(
+    n: usize,
+    k: usize,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+) -> Result<ForkJoinSimulation, Box<dyn Error>> {
+    let mut accumulator = 0.0;
+    let incoming_jobs: Vec<f64> = generator(inter_arrival_param, n)
+        .iter()
+        .map(|x| {
+            accumulator += x;
+            accumulator
+        })
+        .collect();
+
+    let branches = (0..k)
+        .map(|_| {
+            single_server(
+                incoming_jobs.clone(),
+                generator(service_param, n),
+                warming_up_param,
+            )
+        })
+        .collect();
+
+    Ok(ForkJoinSimulation::new(incoming_jobs, branches))
+}
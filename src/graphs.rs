@@ -1,14 +1,13 @@
 //! The `graphs.rs` file is a dump of all the functions that render graphs.
-use crate::measures::{
-    is_inside_interval, test_statistic, theoretic_p_off_exp, theoretic_p_setup_exp,
-    theoretic_stay_avg_erlang, theoretic_stay_avg_exp, Data,
-};
+use crate::measures::{histogram, sweep_verdict, ConfidenceInterval, Correction, Data, Metric};
 use plotters::prelude::*;
 
-const HYPOTHESIS_INTERVAL: f64 = 1.96;
-
-pub fn print_avg_stay_graph_for_exp(values: &Vec<Data>) -> Result<(), Box<dyn std::error::Error>> {
-    let file_name: &'static str = "images/exp_avg_stay_by_rho.png";
+pub fn print_avg_stay_graph(
+    values: &[Data],
+    file_name: &'static str,
+    correction: Correction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let real_file_name = file_name.to_owned() + ".png";
     let title: &'static str = "Average stay time by rho";
 
     let width = 640;
@@ -16,12 +15,13 @@ pub fn print_avg_stay_graph_for_exp(values: &Vec<Data>) -> Result<(), Box<dyn st
 
     let theoretical_avg_stay = values
         .iter()
-        .map(|v| theoretic_stay_avg_exp(v.rho, v.mu.unwrap(), v.theta))
-        .collect::<Vec<_>>();
+        .map(|v| v.setup_queue().map(|q| q.avg_stay()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("the service law of the experiment is unknown")?;
 
     let rhos = values.iter().map(|v| v.rho).collect::<Vec<_>>();
 
-    let root = BitMapBackend::new(file_name, (width, height)).into_drawing_area();
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
@@ -39,7 +39,7 @@ pub fn print_avg_stay_graph_for_exp(values: &Vec<Data>) -> Result<(), Box<dyn st
             &MAGENTA,
         ))?
         .label("theoretical 𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     chart
         .draw_series(
@@ -48,19 +48,19 @@ pub fn print_avg_stay_graph_for_exp(values: &Vec<Data>) -> Result<(), Box<dyn st
                 .map(|v| Circle::new((v.rho, v.avg_stay_time), 2, BLUE.filled())),
         )?
         .label("𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
     // Hypotheses testing
-    let file_name: &'static str = "images/exp_avg_stay_by_rho_test.png";
+    let real_file_name = file_name.to_owned() + "_test.png";
 
-    let root = BitMapBackend::new(file_name, (width, height)).into_drawing_area();
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
@@ -78,71 +78,73 @@ pub fn print_avg_stay_graph_for_exp(values: &Vec<Data>) -> Result<(), Box<dyn st
             &MAGENTA,
         ))?
         .label("theoretical 𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     // Points that respect the hypotheses
-    let t_xs = values
-        .iter()
-        .zip(&theoretical_avg_stay)
-        .map(|(&d, &v)| {
-            test_statistic(
-                d.avg_stay_time,
-                v,
-                d.corrected_variance_avg_stay,
-                d.n_simulations,
-            )
-        })
-        .collect::<Vec<f64>>();
+    // the tests of all the rhos are corrected together, and summed up in a global verdict
+    let verdict = sweep_verdict(values, Metric::AvgStay, &theoretical_avg_stay, correction);
+    let verified = verdict.rejected.iter().map(|&r| !r).collect::<Vec<bool>>();
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| is_inside_interval(t, HYPOTHESIS_INTERVAL))
+                .zip(&verified)
+                .filter(|(_, &ok)| ok)
                 .map(|(&d, _)| Circle::new((d.rho, d.avg_stay_time), 2, GREEN.filled())),
         )?
         .label("𝔼[S] verifies H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
 
     // Points that do not respect the hypotheses
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| !is_inside_interval(t, HYPOTHESIS_INTERVAL))
+                .zip(&verified)
+                .filter(|(_, &ok)| !ok)
                 .map(|(&d, _)| Circle::new((d.rho, d.avg_stay_time), 2, RED.filled())),
         )?
         .label("𝔼[S] invalidates H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+
+    chart
+        .draw_series(std::iter::empty::<Circle<(f64, f64), i32>>())?
+        .label(format!(
+            "{} (global χ² p-value = {:.3})",
+            if verdict.passed { "pass" } else { "fail" },
+            verdict.global.p_value
+        ));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
     Ok(())
 }
 
-pub fn print_avg_stay_graph_for_erlang(
-    values: &Vec<Data>,
+pub fn print_p_setup_graph(
+    values: &[Data],
+    file_name: &'static str,
+    correction: Correction,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file_name: &'static str = "images/erlang_avg_stay_by_rho.png";
-    let title: &'static str = "Average stay time by rho";
+    let real_file_name = file_name.to_owned() + ".png";
+    let title: &'static str = "P(setup) time by rho";
 
     let width = 640;
     let height = 480;
 
-    let theoretical_avg_stay = values
+    let theoretical_p_setup = values
         .iter()
-        .map(|v| theoretic_stay_avg_erlang(v.lambda, v.theta, v.rho, v.k.unwrap(), v.beta.unwrap()))
-        .collect::<Vec<_>>();
+        .map(|v| v.setup_queue().map(|q| q.probability_p_setup()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("the service law of the experiment is unknown")?;
 
     let rhos = values.iter().map(|v| v.rho).collect::<Vec<_>>();
 
-    let root = BitMapBackend::new(file_name, (width, height)).into_drawing_area();
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
@@ -150,38 +152,37 @@ pub fn print_avg_stay_graph_for_erlang(
         .margin(5)
         .x_label_area_size(30)
         .y_label_area_size(30)
-        .build_cartesian_2d(0f64..1f64, 0f64..20f64)?;
+        .build_cartesian_2d(0f64..1f64, 0f64..1f64)?;
 
     chart.configure_mesh().draw()?;
 
     chart
         .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_avg_stay.clone()),
+            rhos.iter().cloned().zip(theoretical_p_setup.clone()),
             &MAGENTA,
         ))?
-        .label("theoretical 𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .label("theoretical P(setup)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     chart
         .draw_series(
             values
                 .iter()
-                .map(|v| Circle::new((v.rho, v.avg_stay_time), 2, BLUE.filled())),
+                .map(|v| Circle::new((v.rho, v.probability_p_setup), 2, BLUE.filled())),
         )?
-        .label("𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        .label("P(setup)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
-    // Hypothesis testing
-
-    let file_name: &'static str = "images/erlang_avg_stay_by_rho_test.png";
-    let root = BitMapBackend::new(file_name, (width, height)).into_drawing_area();
+    // Hypotheses testing
+    let real_file_name = file_name.to_owned() + "_test.png";
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
@@ -189,78 +190,81 @@ pub fn print_avg_stay_graph_for_erlang(
         .margin(5)
         .x_label_area_size(30)
         .y_label_area_size(30)
-        .build_cartesian_2d(0f64..1f64, 0f64..20f64)?;
+        .build_cartesian_2d(0f64..1f64, 0f64..1f64)?;
 
     chart.configure_mesh().draw()?;
 
     chart
         .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_avg_stay.clone()),
+            rhos.iter().cloned().zip(theoretical_p_setup.clone()),
             &MAGENTA,
         ))?
-        .label("theoretical 𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .label("theoretical P(setup)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
-    // Points that respect the hypotheses
-    let t_xs = values
-        .iter()
-        .zip(&theoretical_avg_stay)
-        .map(|(&d, &v)| {
-            test_statistic(
-                d.avg_stay_time,
-                v,
-                d.corrected_variance_avg_stay,
-                d.n_simulations,
-            )
-        })
-        .collect::<Vec<f64>>();
+    // the tests of all the rhos are corrected together, and summed up in a global verdict
+    let verdict = sweep_verdict(
+        values,
+        Metric::ProbabilitySetup,
+        &theoretical_p_setup,
+        correction,
+    );
+    let verified = verdict.rejected.iter().map(|&r| !r).collect::<Vec<bool>>();
 
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&d, _)| Circle::new((d.rho, d.avg_stay_time), 2, GREEN.filled())),
+                .zip(&verified)
+                .filter(|(_, &ok)| ok)
+                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_setup), 2, GREEN.filled())),
         )?
-        .label("𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
-
+        .label("P(setup) verifies H_0")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| !is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&d, _)| Circle::new((d.rho, d.avg_stay_time), 2, RED.filled())),
+                .zip(&verified)
+                .filter(|(_, &ok)| !ok)
+                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_setup), 2, RED.filled())),
         )?
-        .label("𝔼[S]")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        .label("P(setup) invalidates H_0")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+
+    chart
+        .draw_series(std::iter::empty::<Circle<(f64, f64), i32>>())?
+        .label(format!(
+            "{} (global χ² p-value = {:.3})",
+            if verdict.passed { "pass" } else { "fail" },
+            verdict.global.p_value
+        ));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
     Ok(())
 }
 
-pub fn print_p_setup_graph(
-    values: &Vec<Data>,
+pub fn print_p_off_graph(
+    values: &[Data],
     file_name: &'static str,
+    correction: Correction,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let title: &'static str = "P(off) time by rho";
     let real_file_name = file_name.to_owned() + ".png";
-    let title: &'static str = "P(setup) time by rho";
-
     let width = 640;
     let height = 480;
 
-    let theoretical_p_setup = values
+    let theoretical_p_off = values
         .iter()
-        .map(|v| theoretic_p_setup_exp(v.rho, v.lambda, v.theta))
-        .collect::<Vec<_>>();
+        .map(|v| v.setup_queue().map(|q| q.probability_p_off()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("the service law of the experiment is unknown")?;
 
     let rhos = values.iter().map(|v| v.rho).collect::<Vec<_>>();
 
@@ -278,30 +282,32 @@ pub fn print_p_setup_graph(
 
     chart
         .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_p_setup.clone()),
+            rhos.iter().cloned().zip(theoretical_p_off.clone()),
             &MAGENTA,
         ))?
-        .label("theoretical P(setup)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .label("theoretical P(off)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     chart
         .draw_series(
             values
                 .iter()
-                .map(|v| Circle::new((v.rho, v.probability_p_setup), 2, BLUE.filled())),
+                .map(|v| Circle::new((v.rho, v.probability_p_off), 2, BLUE.filled())),
         )?
-        .label("P(setup)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        .label("P(off)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
-    // Hypotheses testing
+    // Hypothesis testing
+
     let real_file_name = file_name.to_owned() + "_test.png";
+
     let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -316,172 +322,330 @@ pub fn print_p_setup_graph(
 
     chart
         .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_p_setup.clone()),
+            rhos.iter().cloned().zip(theoretical_p_off.clone()),
             &MAGENTA,
         ))?
-        .label("theoretical P(setup)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .label("theoretical P(off)")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
-    let t_xs = values
-        .iter()
-        .zip(&theoretical_p_setup)
-        .map(|(&d, &v)| {
-            test_statistic(
-                d.probability_p_setup,
-                v,
-                d.corrected_variance_p_setup,
-                d.n_simulations,
-            )
-        })
-        .collect::<Vec<f64>>();
+    // the tests of all the rhos are corrected together, and summed up in a global verdict
+    let verdict = sweep_verdict(
+        values,
+        Metric::ProbabilityOff,
+        &theoretical_p_off,
+        correction,
+    );
+    let verified = verdict.rejected.iter().map(|&r| !r).collect::<Vec<bool>>();
 
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_setup), 2, GREEN.filled())),
+                .zip(&verified)
+                .filter(|(_, &ok)| ok)
+                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_off), 2, GREEN.filled())),
         )?
-        .label("P(setup) verifies H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
+        .label("P(off) satisfies H_0")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
+
     chart
         .draw_series(
             values
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| !is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_setup), 2, RED.filled())),
+                .zip(&verified)
+                .filter(|(_, &ok)| !ok)
+                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_off), 2, RED.filled())),
         )?
-        .label("P(setup) invalidates H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        .label("P(off) invalidates H_0")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+
+    chart
+        .draw_series(std::iter::empty::<Circle<(f64, f64), i32>>())?
+        .label(format!(
+            "{} (global χ² p-value = {:.3})",
+            if verdict.passed { "pass" } else { "fail" },
+            verdict.global.p_value
+        ));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
-
     Ok(())
 }
 
-pub fn print_p_off_graph(
-    values: &Vec<Data>,
-    file_name: &'static str,
+/// Draw the histogram of some samples (e.g. response times), as the proportion of samples by bin.
+pub fn print_distribution_graph(
+    samples: &[f64],
+    file_name: &str,
+    title: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let title: &'static str = "P(off) time by rho";
     let real_file_name = file_name.to_owned() + ".png";
     let width = 640;
     let height = 480;
 
-    let theoretical_p_off = values
-        .iter()
-        .map(|v| theoretic_p_off_exp(v.rho, v.lambda, v.theta))
-        .collect::<Vec<_>>();
+    let bins = histogram(samples, 50);
+    let bin_width = if bins.len() > 1 {
+        bins[1].0 - bins[0].0
+    } else {
+        1.0
+    };
+    let x_max = bins.last().map(|&(x, _)| x + bin_width).unwrap_or(1.0);
+    let y_max = bins.iter().map(|&(_, p)| p).fold(0.0, f64::max) * 1.1;
 
-    let rhos = values.iter().map(|v| v.rho).collect::<Vec<_>>();
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 40).into_font())
+        .margin(5)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0f64..x_max, 0f64..y_max.max(f64::EPSILON))?;
+
+    chart.configure_mesh().draw()?;
+
+    chart
+        .draw_series(bins.iter().map(|&(x, p)| {
+            Rectangle::new([(x, 0.0), (x + bin_width, p)], BLUE.mix(0.6).filled())
+        }))?;
+
+    Ok(())
+}
+
+/// Draw simulated points against a theoretical curve, both given as `(x, y)` couples.
+/// The ranges of the graph are deduced from the values.
+pub fn print_comparison_graph(
+    simulated: &[(f64, f64)],
+    theoretical: &[(f64, f64)],
+    file_name: &str,
+    title: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let real_file_name = file_name.to_owned() + ".png";
+    let width = 640;
+    let height = 480;
+
+    let all = simulated.iter().chain(theoretical);
+    let x_max = all.clone().map(|&(x, _)| x).fold(0.0, f64::max) * 1.05;
+    let y_max = all.map(|&(_, y)| y).fold(0.0, f64::max) * 1.1;
 
     let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 50).into_font())
+        .caption(title, ("sans-serif", 40).into_font())
         .margin(5)
         .x_label_area_size(30)
-        .y_label_area_size(30)
-        .build_cartesian_2d(0f64..1f64, 0f64..1f64)?;
+        .y_label_area_size(40)
+        .build_cartesian_2d(0f64..x_max.max(1.0), 0f64..y_max.max(f64::EPSILON))?;
 
     chart.configure_mesh().draw()?;
 
     chart
-        .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_p_off.clone()),
-            &MAGENTA,
-        ))?
-        .label("theoretical P(off)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
+        .draw_series(LineSeries::new(theoretical.iter().cloned(), &MAGENTA))?
+        .label("theoretical")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     chart
         .draw_series(
-            values
+            simulated
                 .iter()
-                .map(|v| Circle::new((v.rho, v.probability_p_off), 2, BLUE.filled())),
+                .map(|&(x, y)| Circle::new((x, y), 2, BLUE.filled())),
         )?
-        .label("P(off)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        .label("simulated")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
-    // Hypothesis testing
+    Ok(())
+}
 
-    let real_file_name = file_name.to_owned() + "_test.png";
+/// Draw the empirical CDF of some samples against a theoretical CDF, given as `(x, F(x))` couples.
+pub fn print_cdf_graph(
+    samples: &[f64],
+    theoretical: &[(f64, f64)],
+    file_name: &str,
+    title: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let real_file_name = file_name.to_owned() + ".png";
+    let width = 640;
+    let height = 480;
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = sorted.len() as f64;
+    let x_max = theoretical
+        .iter()
+        .map(|&(x, _)| x)
+        .chain(sorted.last().cloned())
+        .fold(0.0, f64::max);
 
     let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
     root.fill(&WHITE)?;
 
     let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 50).into_font())
+        .caption(title, ("sans-serif", 40).into_font())
         .margin(5)
         .x_label_area_size(30)
         .y_label_area_size(30)
-        .build_cartesian_2d(0f64..1f64, 0f64..1f64)?;
+        .build_cartesian_2d(0f64..x_max.max(1.0), 0f64..1f64)?;
 
     chart.configure_mesh().draw()?;
 
     chart
         .draw_series(LineSeries::new(
-            rhos.iter().cloned().zip(theoretical_p_off.clone()),
-            &MAGENTA,
+            sorted
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| (x, (i + 1) as f64 / n)),
+            &BLUE,
         ))?
-        .label("theoretical P(off)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &MAGENTA));
-
-    let t_xs = values
-        .iter()
-        .zip(&theoretical_p_off)
-        .map(|(&d, &v)| {
-            test_statistic(
-                d.probability_p_off,
-                v,
-                d.corrected_variance_p_off,
-                d.n_simulations,
-            )
-        })
-        .collect::<Vec<f64>>();
+        .label("empirical CDF")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
 
     chart
-        .draw_series(
-            values
-                .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_off), 2, GREEN.filled())),
-        )?
-        .label("P(off) satisfies H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
+        .draw_series(LineSeries::new(theoretical.iter().cloned(), &MAGENTA))?
+        .label("theoretical CDF")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
 
     chart
-        .draw_series(
-            values
+        .configure_series_labels()
+        .position(SeriesLabelPosition::LowerRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+/// Draw several named series of `(x, y)` couples, each one as a line with its points, for
+/// instance one curve by value of a parameter. The ranges of the graph are deduced from the values.
+pub fn print_series_graph(
+    series: &[(String, Vec<(f64, f64)>)],
+    file_name: &str,
+    title: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let real_file_name = file_name.to_owned() + ".png";
+    let width = 640;
+    let height = 480;
+
+    let all = series.iter().flat_map(|(_, points)| points);
+    let x_max = all.clone().map(|&(x, _)| x).fold(0.0, f64::max) * 1.05;
+    let y_max = all.map(|&(_, y)| y).fold(0.0, f64::max) * 1.1;
+
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 40).into_font())
+        .margin(5)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0f64..x_max.max(f64::EPSILON), 0f64..y_max.max(f64::EPSILON))?;
+
+    chart.configure_mesh().draw()?;
+
+    for (i, (name, points)) in series.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(points.iter().cloned(), &color))?
+            .label(name.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        chart.draw_series(
+            points
                 .iter()
-                .zip(&t_xs)
-                .filter(|(_, &t)| !is_inside_interval(t, HYPOTHESIS_INTERVAL))
-                .map(|(&v, _)| Circle::new((v.rho, v.probability_p_off), 2, RED.filled())),
-        )?
-        .label("P(off) invalidates H_0")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+                .map(|&(x, y)| Circle::new((x, y), 2, color.filled())),
+        )?;
+    }
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+/// Draw the simulated values with their confidence intervals against the exact curve (when one
+/// is known) and against some named approximations, all given as `(x, y)` couples, to see where
+/// each one can be trusted. The ranges of the graph are deduced from the values.
+pub fn print_approximation_graph(
+    simulated: &[(f64, ConfidenceInterval)],
+    exact: Option<&[(f64, f64)]>,
+    approximations: &[(String, Vec<(f64, f64)>)],
+    file_name: &str,
+    title: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let real_file_name = file_name.to_owned() + ".png";
+    let width = 640;
+    let height = 480;
+
+    let all = simulated
+        .iter()
+        .map(|&(x, interval)| (x, interval.upper))
+        .chain(exact.into_iter().flatten().cloned())
+        .chain(approximations.iter().flat_map(|(_, points)| points.clone()))
+        .collect::<Vec<_>>();
+    let x_max = all.iter().map(|&(x, _)| x).fold(0.0, f64::max) * 1.05;
+    let y_max = all.iter().map(|&(_, y)| y).fold(0.0, f64::max) * 1.1;
+
+    let root = BitMapBackend::new(&real_file_name, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 40).into_font())
+        .margin(5)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0f64..x_max.max(f64::EPSILON), 0f64..y_max.max(f64::EPSILON))?;
+
+    chart.configure_mesh().draw()?;
+
+    if let Some(exact) = exact {
+        chart
+            .draw_series(LineSeries::new(exact.iter().cloned(), &MAGENTA))?
+            .label("exact")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], MAGENTA));
+    }
+
+    for (i, (name, points)) in approximations.iter().enumerate() {
+        // the first colours of the palette are left to the exact curve and to the simulations
+        let color = Palette99::pick(i + 2).to_rgba();
+        chart
+            .draw_series(LineSeries::new(points.iter().cloned(), &color))?
+            .label(name.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    chart
+        .draw_series(simulated.iter().map(|&(x, interval)| {
+            ErrorBar::new_vertical(
+                x,
+                interval.lower,
+                interval.mean,
+                interval.upper,
+                BLUE.filled(),
+                6,
+            )
+        }))?
+        .label("simulated")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
+
     Ok(())
 }
@@ -3,234 +3,2656 @@
 //! The project consist of a study of a queue simulation
 
 use std::error::Error;
+pub mod ctmc;
+pub mod energy;
+pub mod finite_source;
+pub mod fork_join;
 pub mod graphs;
 pub mod measures;
+pub mod optimiser;
+pub mod policy;
+pub mod qbd;
+pub mod rare_event;
 pub mod simulation;
+pub mod theory;
+pub mod transform;
 pub mod variables;
+use crate::ctmc::MarkovianFarm;
+use crate::energy::{CostWeights, EnergyData, PowerModel};
+use crate::finite_source::{finite_source_queue, theoretic_finite_source};
+use crate::fork_join::{fork_join_queue, ForkJoinSimulation};
 use crate::graphs::{
-    print_avg_stay_graph_for_erlang, print_avg_stay_graph_for_exp, print_p_off_graph,
-    print_p_setup_graph,
+    print_approximation_graph, print_avg_stay_graph, print_cdf_graph, print_comparison_graph,
+    print_distribution_graph, print_p_off_graph, print_p_setup_graph, print_series_graph,
 };
-use crate::measures::{corrected_standard_deviation, Data};
-use crate::simulation::Simulation;
-use crate::variables::{generator, ErlangParameter, ExponentialParameter, PoissonParameter};
+use crate::measures::{
+    anderson_darling_test, antithetic_estimate, chi_square_goodness_of_fit,
+    common_random_numbers_estimate, confidence_interval, control_variates_estimate,
+    corrected_standard_deviation, crude_rare_event_estimate, ensemble_curve,
+    kolmogorov_smirnov_test, quantile, rare_event_estimate, regenerative, sweep_verdict,
+    truncation_points, Approximation, ConfidenceInterval, Correction, Data, Metric,
+    SetupApproximations, StoppingRule, TransientDeletion, DEFAULT_CONFIDENCE_LEVEL,
+    LITTLES_LAW_TOLERANCE, PERCENTILES,
+};
+use crate::optimiser::{select_best, SelectionSettings};
+use crate::policy::{policy_grid, policy_queue, Policy, PolicySimulation};
+use crate::qbd::{PhaseType, PhaseTypeSetupFarm, PhaseTypeSetupQueue};
+use crate::rare_event::{cramer_root, restart_queue, tilted_queue, Splitting};
+use crate::simulation::{single_server, single_server_with_warmups, Cycle, Simulation};
+use crate::theory::{SetupQueue, SetupQueueLaws};
+use crate::variables::{antithetic_generator, generator, ErlangParameter, ExponentialParameter};
 use indicatif::ProgressIterator;
 use measures::Mean;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use rayon::prelude::{
+    IntoParallelIterator, IntoParallelRefIterator, ParallelExtend, ParallelIterator,
+};
 use variables::Parameter;
 
-/// Entry point of the program
-fn main() {
-    let simulations_by_batch = 300; // number of simulations
-    let arrivals_number = 400; // number of arrivals in our system
-    let rhos = itertools_num::linspace(0.05, 0.95, 50).collect::<Vec<f64>>();
+/// Entry point of the program
+fn main() {
+    let simulations_by_batch = 300; // number of simulations
+    let arrivals_number = 400; // number of arrivals in our system
+    let rhos = itertools_num::linspace(0.05, 0.95, 50).collect::<Vec<f64>>();
+
+    let theta = 0.6; // parameter for the warmup (setup) random distribution (exp)
+
+    // replications are added until every metric is known within 2% (or ±0.005 for the
+    // probabilities close to 0), with at most 3000 replications by rho. The same confidence level
+    // is used by the hypothesis tests of the graphs.
+    let stopping = StoppingRule {
+        metrics: vec![
+            Metric::AvgStay,
+            Metric::ProbabilityOff,
+            Metric::ProbabilitySetup,
+        ],
+        relative_precision: Some(0.02),
+        absolute_precision: Some(0.005),
+        initial_simulations: 50,
+        simulations_by_step: 50,
+        max_simulations: 3000,
+        confidence_level: DEFAULT_CONFIDENCE_LEVEL,
+    };
+
+    launch_exp(
+        &stopping,
+        arrivals_number,
+        theta,
+        &rhos,
+        Some(TransientDeletion::Mser5),
+    );
+    // the Erlang sweep deletes a common transient for all the replications of a rho, where the
+    // stays are averaged over 51 clients and must stay within 5% of their final level
+    launch_erlang(
+        &stopping,
+        arrivals_number,
+        theta,
+        &rhos,
+        Some(TransientDeletion::Welch {
+            window: 25,
+            tolerance: 0.05,
+        }),
+    );
+    launch_fork_join(simulations_by_batch, arrivals_number, theta, 4, 0.7);
+    launch_finite_source(simulations_by_batch, arrivals_number, theta, 20);
+    launch_horizon(simulations_by_batch, 400.0, theta, &rhos);
+    launch_transient(4000, 60.0, theta, 0.8, 60, 1.0);
+    launch_long_run(200_000, theta, 0.8);
+    launch_queue_length_distribution(simulations_by_batch, arrivals_number, theta, 0.8, 30);
+    launch_distribution_fit(2000, arrivals_number, theta, 0.7);
+    launch_stay_distribution(2000, arrivals_number, theta, 0.7);
+    launch_cycles(simulations_by_batch, arrivals_number, theta, 0.7);
+
+    // a server drawing 150 W when working, 100 W when idle and 5 W when off, whose switch-on
+    // costs an extra 50 J; a unit of stay costs as much as 20 W.
+    let power = PowerModel {
+        off: 5.0,
+        setup: 150.0,
+        idle: 100.0,
+        busy: 150.0,
+        switch_on_energy: Some(50.0),
+    };
+    let weights = CostWeights {
+        delay: 1.0,
+        power: 0.05,
+    };
+    launch_energy(
+        simulations_by_batch,
+        arrivals_number,
+        &[0.1, 0.3, 0.6, 1.0, 3.0],
+        &itertools_num::linspace(0.1, 0.9, 9).collect::<Vec<f64>>(),
+        &power,
+        &weights,
+    );
+    launch_gradients(2000, arrivals_number, theta, 0.7);
+    launch_variance_reduction(
+        simulations_by_batch / 2,
+        arrivals_number,
+        theta,
+        0.7,
+        &[
+            Policy {
+                servers: 1,
+                idle_timer: 0.0,
+                threshold: 1,
+            },
+            Policy {
+                servers: 1,
+                idle_timer: 1.0,
+                threshold: 1,
+            },
+        ],
+        &power,
+        &weights,
+    );
+    launch_rare_events(
+        8,
+        arrivals_number * 50,
+        1_000_000,
+        20_000,
+        theta,
+        0.7,
+        &[5.0, 10.0, 20.0, 50.0, 100.0],
+    );
+    launch_markov_models(
+        simulations_by_batch,
+        arrivals_number * 10,
+        theta,
+        &[0.1, 0.3, 0.5, 0.7, 0.9, 0.95],
+        1.5,
+        &[
+            Policy {
+                servers: 2,
+                idle_timer: 0.0,
+                threshold: 1,
+            },
+            Policy {
+                servers: 2,
+                idle_timer: f64::INFINITY,
+                threshold: 1,
+            },
+            Policy {
+                servers: 3,
+                idle_timer: 0.0,
+                threshold: 2,
+            },
+            Policy {
+                servers: 3,
+                idle_timer: 1.0,
+                threshold: 1,
+            },
+        ],
+    );
+    launch_heavy_traffic(
+        50,
+        arrivals_number * 50,
+        theta,
+        &[0.5, 0.6, 0.7, 0.8, 0.85, 0.9, 0.95, 0.98],
+    );
+    launch_arrival_variability(
+        50,
+        arrivals_number * 20,
+        theta,
+        &[0.3, 0.5, 0.7, 0.9],
+        &[0.25, 0.5, 1.0, 2.0, 4.0],
+    );
+    launch_qbd(
+        simulations_by_batch,
+        arrivals_number * 20,
+        theta,
+        &[0.5, 0.9, 0.99, 0.999],
+        &[2, 4],
+        0.95,
+    );
+    launch_policy_optimisation(
+        arrivals_number,
+        theta,
+        0.7,
+        &policy_grid(&[1, 2, 3], &[0.0, 0.5, 1.0, 2.0, 5.0], &[1, 2, 4]),
+        &power,
+        &weights,
+    );
+}
+
+/// This function will launch the experiments linked to the queue having Exponential law describing
+/// the service times.
+///
+/// Arguments:
+///  
+/// * `stopping`: The rule deciding how many simulations are launched for each rho value.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The values of rho for which we want results
+/// * `deletion`: The method used to detect and delete the initial transient of every simulation,
+///   if any. The average truncation point is kept in the `Data`.
+///
+/// Return:
+///
+/// Nothing BUT this function will generate 6 graphs: the average stay in the queue (by rho), the
+/// probability of arriving with the server warming up (by rho) and the probability of arriving
+/// with the server offline (by rho).
+/// Those 3 kind of graphs are declined with the hypothesis testing that the computed point is equal
+/// to the theoretical value.
+fn launch_exp(
+    stopping: &StoppingRule,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+    deletion: Option<TransientDeletion>,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+
+    let mut values: Vec<Data> = Vec::new();
+    for &rho in rhos.iter().progress() {
+        let mu = lambda / rho; // value for the service parameter (Exponential)
+
+        let (data, _) = run_until_precision(
+            stopping,
+            || exp_service_time(arrivals_number, lambda, mu, theta),
+            |simulations| Data {
+                mu: Some(mu),
+                ..steady_state_data(simulations, deletion, rho, lambda, theta)
+            },
+        );
+
+        values.push(data);
+    }
+    for v in values.iter() {
+        // the service of the experiment is Exponential
+        let theory = v.setup_queue().unwrap();
+        let theoretical = [
+            ("𝔼[S]", Metric::AvgStay, theory.avg_stay()),
+            ("P(off)", Metric::ProbabilityOff, theory.probability_p_off()),
+            (
+                "P(setup)",
+                Metric::ProbabilitySetup,
+                theory.probability_p_setup(),
+            ),
+            (
+                "fraction of clients paying the warmup",
+                Metric::FractionWithWarmup,
+                theory.probability_warmup(),
+            ),
+            (
+                "𝔼[S | warmup]",
+                Metric::AvgStayWithWarmup,
+                theory.avg_stay_with_warmup(),
+            ),
+            ("𝔼[N]", Metric::AvgJobsInSystem, theory.avg_in_system()),
+            ("𝔼[N_q]", Metric::AvgJobsInQueue, theory.avg_in_queue()),
+        ];
+        for (name, metric, expected) in theoretical {
+            let interval = v.confidence_interval_of(metric);
+            println!(
+                "rho = {}: {} = {} ; {}% CI = [{}, {}] ({} simulations) ; theoretical = {} ; p-value = {}",
+                v.rho,
+                name,
+                interval.mean,
+                v.confidence_level * 100.0,
+                interval.lower,
+                interval.upper,
+                v.n_simulations,
+                expected,
+                v.t_test(metric, expected).p_value
+            );
+        }
+        for (i, p) in PERCENTILES.iter().enumerate() {
+            let stay = v.confidence_interval_of(Metric::StayPercentile(i));
+            let waiting = v.confidence_interval_of(Metric::WaitingPercentile(i));
+            println!(
+                "rho = {}: p{} of S = {} ± {} ; p{} of W = {} ± {}",
+                v.rho,
+                p * 100.0,
+                stay.mean,
+                stay.half_width(),
+                p * 100.0,
+                waiting.mean,
+                waiting.half_width()
+            );
+        }
+        if let Some(truncation_point) = v.truncation_point {
+            println!(
+                "rho = {}: {} jobs deleted on average as initial transient",
+                v.rho, truncation_point
+            );
+        }
+    }
+    print_verdicts(
+        "exp",
+        &values,
+        &[
+            (
+                "𝔼[S]",
+                Metric::AvgStay,
+                values
+                    .iter()
+                    .map(|v| v.setup_queue().unwrap().avg_stay())
+                    .collect(),
+            ),
+            (
+                "P(off)",
+                Metric::ProbabilityOff,
+                values
+                    .iter()
+                    .map(|v| v.setup_queue().unwrap().probability_p_off())
+                    .collect(),
+            ),
+            (
+                "P(setup)",
+                Metric::ProbabilitySetup,
+                values
+                    .iter()
+                    .map(|v| v.setup_queue().unwrap().probability_p_setup())
+                    .collect(),
+            ),
+        ],
+        Correction::Holm,
+    );
+    let _ = print_avg_stay_graph(&values, "images/exp_avg_stay_by_rho", Correction::Holm);
+    let _ = print_p_setup_graph(&values, "images/exp_p_setup_by_rho", Correction::Holm);
+    let _ = print_p_off_graph(&values, "images/exp_p_off_by_rho", Correction::Holm);
+}
+
+/// This function will launch the experiments linked to the queue having Exponential law describing
+/// the service times.
+///
+/// Arguments:
+///  
+/// * `stopping`: The rule deciding how many simulations are launched for each rho value.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The values of rho for which we want results
+/// * `deletion`: The method used to detect and delete the initial transient of every simulation,
+///   if any. The average truncation point is kept in the `Data`.
+///
+/// Return:
+///
+/// Nothing BUT this function will generate 6 graphs: the average stay in the queue (by rho), the
+/// probability of arriving with the server warming up (by rho) and the probability of arriving
+/// with the server offline (by rho).
+/// Those 3 kind of graphs are declined with the hypothesis testing that the computed point is equal
+/// to the theoretical value.
+fn launch_erlang(
+    stopping: &StoppingRule,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+    deletion: Option<TransientDeletion>,
+) {
+    let mut values: Vec<Data> = Vec::new();
+    let mut d: Option<f64> = None; // used to compute 𝔼[W²]
+
+    let k: usize = 5; // Erlang shape
+    let lambda = 1.0; // Poisson param
+    for &rho in rhos.iter().progress() {
+        let beta = rho / lambda / k as f64; // Erlang scale -> /!\ rate = 1/beta
+
+        let (data, simulations) = run_until_precision(
+            stopping,
+            || erlang_service_time(arrivals_number, lambda, theta, k, beta),
+            |simulations| Data {
+                k: Some(k),
+                beta: Some(beta),
+                ..steady_state_data(simulations, deletion, rho, lambda, theta)
+            },
+        );
+
+        if rho > 0.5 && d.is_none() {
+            d = Some(
+                simulations
+                    .iter()
+                    .map(|s: &Simulation| s.second_order_moment_waiting_delay())
+                    .calculate_mean(),
+            );
+            print!(
+                "E[W²] = sum( (W_i - E[W])² ) = {} ; for rho = {}",
+                d.unwrap(),
+                rho
+            );
+        }
+
+        if let Some(truncation_point) = data.truncation_point {
+            println!(
+                "rho = {}: {} jobs deleted on average as initial transient",
+                rho, truncation_point
+            );
+        }
+        values.push(data);
+    }
+    print_verdicts(
+        "erlang",
+        &values,
+        &[(
+            "𝔼[S]",
+            Metric::AvgStay,
+            values
+                .iter()
+                .map(|v| v.setup_queue().unwrap().avg_stay())
+                .collect(),
+        )],
+        Correction::Holm,
+    );
+    let _ = print_avg_stay_graph(&values, "images/erlang_avg_stay_by_rho", Correction::Holm);
+    let _ = print_p_setup_graph(&values, "images/erlang_p_setup_by_rho", Correction::Holm);
+    let _ = print_p_off_graph(&values, "images/erlang_p_off_by_rho", Correction::Holm);
+}
+
+/// Compute the `Data` of some simulations over their steady state: every replication starts from
+/// an empty system with the server off, so the first jobs may be deleted beforehand.
+///
+/// Arguments:
+///
+/// * `simulations`: The simulations, all of the same experiment.
+/// * `deletion`: The method used to detect and delete the initial transient of every simulation,
+///   if any. The average truncation point is then kept in the `Data`.
+/// * `rho`, `lambda`, `theta`: The parameters of the experiment (see `Data::from_simulations`).
+fn steady_state_data(
+    simulations: &[Simulation],
+    deletion: Option<TransientDeletion>,
+    rho: f64,
+    lambda: f64,
+    theta: f64,
+) -> Data {
+    match deletion {
+        Some(method) => {
+            let truncation_points = truncation_points(simulations, method);
+            let truncated = simulations
+                .iter()
+                .zip(&truncation_points)
+                .map(|(s, &d)| s.truncated(d))
+                .collect::<Vec<_>>();
+            Data {
+                truncation_point: Some(
+                    truncation_points.iter().map(|&d| d as f64).calculate_mean(),
+                ),
+                ..Data::from_simulations(rho, lambda, theta, &truncated)
+            }
+        }
+        None => Data::from_simulations(rho, lambda, theta, simulations),
+    }
+}
+
+/// Print the single pass/fail verdict of an experiment for every metric, once the tests of all the
+/// rhos are corrected for the multiple testing, then the Little's law sanity check.
+///
+/// Arguments:
+///
+/// * `experiment`: The name of the experiment.
+/// * `values`: The data of every rho of the sweep.
+/// * `theoretical`: For every metric, its name, the metric and its theoretical value for every rho.
+/// * `correction`: The correction applied on the tests of the sweep.
+fn print_verdicts(
+    experiment: &str,
+    values: &[Data],
+    theoretical: &[(&str, Metric, Vec<f64>)],
+    correction: Correction,
+) {
+    for (name, metric, expected) in theoretical {
+        let verdict = sweep_verdict(values, *metric, expected, correction);
+        println!(
+            "{} {}: {} ; {} rejected rhos out of {} ({:?}) ; global χ² = {} ({} degrees of freedom, p-value = {})",
+            experiment,
+            name,
+            if verdict.passed { "pass" } else { "fail" },
+            verdict.rejected.iter().filter(|&&r| r).count(),
+            verdict.rejected.len(),
+            correction,
+            verdict.global.statistic,
+            verdict.global.degrees_of_freedom,
+            verdict.global.p_value
+        );
+    }
+
+    // Little's law holds on every sample path up to the edges of the window, so a gap outside of
+    // the bounds given by the edges means that the simulation is wrong
+    let discrepancy = values
+        .iter()
+        .map(|v| v.littles_law_discrepancy)
+        .fold(0.0, f64::max);
+    println!(
+        "{} Little's law: {} ; biggest distance between L - λW and its edge bounds = {}",
+        experiment,
+        if discrepancy < LITTLES_LAW_TOLERANCE {
+            "pass"
+        } else {
+            "fail"
+        },
+        discrepancy
+    );
+}
+
+/// Launch simulations in parallel, by steps, until the `stopping` rule is satisfied.
+///
+/// Arguments:
+///
+/// * `stopping`: The rule deciding when enough simulations were launched.
+/// * `simulate`: The function launching one simulation.
+/// * `summarize`: The function computing the metrics of a set of simulations, which are checked
+///   against the stopping rule after every step.
+///
+/// Returns:
+///
+/// The metrics of the last step (with the confidence level of the stopping rule), together with all
+/// the simulations that were launched.
+fn run_until_precision<F, G>(
+    stopping: &StoppingRule,
+    simulate: F,
+    summarize: G,
+) -> (Data, Vec<Simulation>)
+where
+    F: Fn() -> Simulation + Sync + Send,
+    G: Fn(&[Simulation]) -> Data,
+{
+    // sanity check : a confidence interval needs two simulations, and every step must add some
+    assert!(stopping.initial_simulations >= 2 && stopping.max_simulations >= 2);
+    assert!(stopping.simulations_by_step >= 1);
+
+    let mut simulations: Vec<Simulation> = Vec::new();
+    let mut step = stopping.initial_simulations.min(stopping.max_simulations);
+    loop {
+        // simulations are computed in parallel
+        simulations.par_extend((0..step).into_par_iter().map(|_| simulate()));
+
+        let data = summarize(&simulations).with_confidence_level(stopping.confidence_level);
+        if stopping.is_satisfied(&data) || simulations.len() >= stopping.max_simulations {
+            return (data, simulations);
+        }
+        step = stopping
+            .simulations_by_step
+            .min(stopping.max_simulations - simulations.len());
+    }
+}
+
+/// This function will launch the fork-join experiment: every job is split into `k` sub-tasks, each
+/// one served by its own server with Exponential service times, and the job is done when the last
+/// sub-task is done.
+///
+/// Arguments:
+///
+/// * `simulations_by_batch`: The number of simulations that will be launched.
+/// * `arrivals_number`: The number of jobs that will enter the system.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `k`: The number of sub-tasks by job (and thus of servers).
+/// * `rho`: The load of each branch server.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the averages of the experiment and generate 2 graphs: the
+/// distribution of the response time and the distribution of the time spent in the
+/// synchronisation queue.
+fn launch_fork_join(
+    simulations_by_batch: usize,
+    arrivals_number: usize,
+    theta: f64,
+    k: usize,
+    rho: f64,
+) {
+    let lambda = 1.0;
+    let mu = lambda / rho;
+
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+
+    let simulations = (0..=simulations_by_batch)
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|_| {
+            fork_join_queue(
+                arrivals_number,
+                k,
+                &inter_arrival_param,
+                &service_param,
+                &warming_up_param,
+            )
+            .unwrap()
+        })
+        .collect::<Vec<ForkJoinSimulation>>();
+
+    let response_times = simulations
+        .iter()
+        .flat_map(|s| s.response_times())
+        .collect::<Vec<_>>();
+    let sync_times = simulations
+        .iter()
+        .flat_map(|s| s.sync_times())
+        .collect::<Vec<_>>();
+
+    println!(
+        "fork-join (k = {}, rho = {}): 𝔼[R] = {} ; p95(R) = {} ; 𝔼[sync] = {} ; 𝔼[N_sync] = {} ; P(off) = {}",
+        k,
+        rho,
+        response_times.iter().calculate_mean(),
+        quantile(&response_times, 0.95),
+        sync_times.iter().calculate_mean(),
+        simulations
+            .iter()
+            .map(|s| s.avg_sync_queue_length())
+            .calculate_mean(),
+        simulations
+            .iter()
+            .map(|s| s.probability_server_off())
+            .calculate_mean(),
+    );
+
+    let _ = print_distribution_graph(
+        &response_times,
+        "images/fork_join_response_time",
+        "Fork-join response time",
+    );
+    let _ = print_distribution_graph(
+        &sync_times,
+        "images/fork_join_sync_time",
+        "Fork-join synchronisation time",
+    );
+}
+
+/// This function will launch the experiments linked to the closed-population queue: `N` users think
+/// for an Exponential time before submitting a request, served with an Exponential law.
+///
+/// Arguments:
+///
+/// * `simulations_by_batch`: The number of simulations that will be launched for each `N`.
+/// * `requests_number`: The number of requests that will be served.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `max_users`: The biggest population studied, every `N` from `1` to `max_users` is studied.
+///
+/// Return:
+///
+/// Nothing BUT this function will generate 3 graphs: the throughput, the average response time and
+/// the probability of finding the server off, all by `N`, compared with the exact M/M/1//N with
+/// setup values.
+fn launch_finite_source(
+    simulations_by_batch: usize,
+    requests_number: usize,
+    theta: f64,
+    max_users: usize,
+) {
+    let think_rate = 0.1; // users think for 10 units of time on average
+    let mu = 1.0; // the value for the service parameter (Exponential)
+
+    let think_param = Parameter::Exponential(ExponentialParameter { lambda: think_rate });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+
+    let mut throughputs = Vec::new();
+    let mut responses = Vec::new();
+    let mut p_offs = Vec::new();
+    let mut exact_throughputs = Vec::new();
+    let mut exact_responses = Vec::new();
+    let mut exact_p_offs = Vec::new();
+    for users in (1..max_users + 1).progress() {
+        let simulations = (0..=simulations_by_batch)
+            .collect::<Vec<_>>()
+            .par_iter()
+            .map(|_| {
+                finite_source_queue(
+                    requests_number,
+                    users,
+                    &think_param,
+                    &service_param,
+                    &warming_up_param,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<Simulation>>();
+
+        let x = users as f64;
+        throughputs.push((
+            x,
+            simulations.iter().map(|s| s.throughput()).calculate_mean(),
+        ));
+        responses.push((x, simulations.iter().map(|s| s.avg_stay()).calculate_mean()));
+        p_offs.push((
+            x,
+            simulations
+                .iter()
+                .map(|s| s.probability_server_off())
+                .calculate_mean(),
+        ));
+
+        let exact = theoretic_finite_source(users, think_rate, mu, theta);
+        exact_throughputs.push((x, exact.throughput));
+        exact_responses.push((x, exact.avg_response));
+        exact_p_offs.push((x, exact.probability_p_off));
+    }
+
+    let _ = print_comparison_graph(
+        &throughputs,
+        &exact_throughputs,
+        "images/finite_source_throughput_by_n",
+        "Throughput by N",
+    );
+    let _ = print_comparison_graph(
+        &responses,
+        &exact_responses,
+        "images/finite_source_response_by_n",
+        "Response time by N",
+    );
+    let _ = print_comparison_graph(
+        &p_offs,
+        &exact_p_offs,
+        "images/finite_source_p_off_by_n",
+        "P(off) by N",
+    );
+}
+
+/// This function will launch the experiments linked to the queue having Exponential law describing
+/// the service times, simulated over a fixed time horizon instead of a fixed number of arrivals.
+///
+/// Arguments:
+///
+/// * `simulations_by_batch`: The number of simulations that will be launched for each rho value.
+/// * `horizon`: The time at which every simulation ends.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The values of rho for which we want results
+///
+/// Return:
+///
+/// Nothing BUT this function will generate 4 graphs: the probability of arriving with the server
+/// warming up (by rho) and the probability of arriving with the server offline (by rho), with
+/// their hypothesis testing. The average number of jobs still in the system at the horizon is
+/// printed for every rho.
+fn launch_horizon(simulations_by_batch: usize, horizon: f64, theta: f64, rhos: &[f64]) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+
+    let mut values: Vec<Data> = Vec::new();
+    for &rho in rhos.iter().progress() {
+        let mu = lambda / rho; // value for the service parameter (Exponential)
+        let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+
+        let simulations = (0..=simulations_by_batch)
+            .collect::<Vec<_>>()
+            .par_iter()
+            .map(|_| {
+                queue_until(
+                    horizon,
+                    &inter_arrival_param,
+                    &service_param,
+                    &warming_up_param,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<Simulation>>();
+
+        println!(
+            "horizon {} (rho = {}): {} jobs in the system at the end on average",
+            horizon,
+            rho,
+            simulations
+                .iter()
+                .map(|s| s.jobs_in_system_at_end() as f64)
+                .calculate_mean()
+        );
+
+        values.push(Data {
+            mu: Some(mu),
+            ..Data::from_simulations(rho, lambda, theta, &simulations)
+        });
+    }
+    let _ = print_p_setup_graph(
+        &values,
+        "images/horizon_exp_p_setup_by_rho",
+        Correction::Holm,
+    );
+    let _ = print_p_off_graph(&values, "images/horizon_exp_p_off_by_rho", Correction::Holm);
+}
+
+/// This function will launch the transient experiment: the queue having Exponential laws starts
+/// empty with the server off, and its behaviour over time is estimated by ensemble averaging
+/// across replications at fixed times, then compared to the exact transient solution of the
+/// CTMC (by uniformisation) and to the steady state.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of replications.
+/// * `horizon`: The end of the time grid.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+/// * `points`: The number of intervals of the time grid.
+/// * `window`: The width of the interval around `t` in which the clients count as arriving near
+///   `t`, for their mean stay (cut at `0`). The exact stay is averaged over the same interval.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the curves of `𝔼[N(t)]`, `P(off at t)` and of the mean stay
+/// of the clients arriving near `t`, with their confidence intervals next to the exact values,
+/// and generate their graphs.
+fn launch_transient(
+    simulations_number: usize,
+    horizon: f64,
+    theta: f64,
+    rho: f64,
+    points: usize,
+    window: f64,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    let times = (0..=points)
+        .map(|i| horizon * i as f64 / points as f64)
+        .collect::<Vec<_>>();
+
+    // the clients arriving near the end of the grid must be in the simulations
+    let simulations = (0..simulations_number)
+        .into_par_iter()
+        .map(|_| {
+            queue_until(
+                horizon + window,
+                &inter_arrival_param,
+                &service_param,
+                &warming_up_param,
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let jobs = ensemble_curve(
+        &simulations,
+        &times,
+        |simulation, t| simulation.jobs_in_system_at(t) as f64,
+        DEFAULT_CONFIDENCE_LEVEL,
+    );
+    let p_offs = ensemble_curve(
+        &simulations,
+        &times,
+        |simulation, t| simulation.is_server_off_at(t) as usize as f64,
+        DEFAULT_CONFIDENCE_LEVEL,
+    );
+    // the mean stay of the clients arriving near `t`, over all the replications (a ratio, as the
+    // number of such clients varies)
+    let stays = times
+        .iter()
+        .map(|&t| {
+            let (sums, counts): (Vec<f64>, Vec<f64>) = simulations
+                .iter()
+                .map(|simulation| {
+                    let stays =
+                        simulation.stays_of_arrivals_between(t - window / 2.0, t + window / 2.0);
+                    (stays.iter().sum::<f64>(), stays.len() as f64)
+                })
+                .unzip();
+            regenerative(&sums, &counts)
+        })
+        .collect::<Vec<_>>();
+
+    let farm = MarkovianFarm {
+        lambda,
+        mu,
+        theta,
+        policy: Policy {
+            servers: 1,
+            idle_timer: 0.0,
+            threshold: 1,
+        },
+        capacity: 200,
+    };
+    let distributions = farm.transient(&times);
+    let exact_jobs = distributions
+        .iter()
+        .map(|pi| farm.metrics(pi).avg_jobs_in_system)
+        .collect::<Vec<_>>();
+    let exact_p_offs = distributions
+        .iter()
+        .map(|pi| farm.metrics(pi).avg_servers_off)
+        .collect::<Vec<_>>();
+    // the simulated stay is averaged over the arrivals of a window (cut at `0`), and the arrivals
+    // are Poisson, so the exact stay of an arrival is averaged over the same window (midpoint rule)
+    let samples = 20;
+    let window_times = |t: f64| {
+        let start = (t - window / 2.0).max(0.0);
+        let width = t + window / 2.0 - start;
+        (0..samples).map(move |j| start + width * (j as f64 + 0.5) / samples as f64)
+    };
+    let mut sub_times = times
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &t)| window_times(t).map(move |s| (s, i)))
+        .collect::<Vec<_>>();
+    sub_times.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let mut exact_stays = vec![0.0; times.len()];
+    let sub_distributions = farm.transient(&sub_times.iter().map(|&(s, _)| s).collect::<Vec<_>>());
+    for (&(_, i), pi) in sub_times.iter().zip(&sub_distributions) {
+        exact_stays[i] += farm.single_server_arrival_stay(pi) / samples as f64;
+    }
+
+    let steady_state = SetupQueue {
+        lambda,
+        service: service_param.moments(),
+        setup: warming_up_param.moments(),
+    };
+    println!(
+        "transient (rho = {}): steady state 𝔼[N] = {} ; P(off) = {} ; 𝔼[S] = {}",
+        rho,
+        steady_state.avg_in_system(),
+        steady_state.probability_p_off(),
+        steady_state.avg_stay()
+    );
+    for (i, &t) in times.iter().enumerate().step_by((points / 12).max(1)) {
+        println!(
+            "t = {}: 𝔼[N(t)] = {} ; CI = [{}, {}] ; exact = {} || P(off at t) = {} ; CI = [{}, {}] ; exact = {} || stay near t = {} ; CI = [{}, {}] ; exact = {}",
+            t,
+            jobs[i].mean,
+            jobs[i].lower,
+            jobs[i].upper,
+            exact_jobs[i],
+            p_offs[i].mean,
+            p_offs[i].lower,
+            p_offs[i].upper,
+            exact_p_offs[i],
+            stays[i].point,
+            stays[i].lower,
+            stays[i].upper,
+            exact_stays[i]
+        );
+    }
+    let covered = |inside: &dyn Fn(usize) -> bool| (0..=points).filter(|&i| inside(i)).count();
+    println!(
+        "transient (rho = {}): exact values inside the confidence intervals: 𝔼[N(t)] {}/{} ; P(off at t) {}/{} ; stay near t {}/{}",
+        rho,
+        covered(&|i| jobs[i].contains(exact_jobs[i])),
+        points + 1,
+        covered(&|i| p_offs[i].contains(exact_p_offs[i])),
+        points + 1,
+        covered(&|i| stays[i].lower <= exact_stays[i] && exact_stays[i] <= stays[i].upper),
+        points + 1
+    );
+
+    let curve = |values: &[f64]| {
+        times
+            .iter()
+            .zip(values)
+            .map(|(&t, &v)| (t, v))
+            .collect::<Vec<_>>()
+    };
+    let means =
+        |intervals: &[ConfidenceInterval]| intervals.iter().map(|i| i.mean).collect::<Vec<_>>();
+    let _ = print_comparison_graph(
+        &curve(&means(&jobs)),
+        &curve(&exact_jobs),
+        "images/transient_jobs_in_system",
+        "E[N(t)] from an empty system",
+    );
+    let _ = print_comparison_graph(
+        &curve(&means(&p_offs)),
+        &curve(&exact_p_offs),
+        "images/transient_p_off",
+        "P(off at t) from an empty system",
+    );
+    let _ = print_comparison_graph(
+        &curve(&stays.iter().map(|e| e.point).collect::<Vec<_>>()),
+        &curve(&exact_stays),
+        "images/transient_stay",
+        "Stay of the clients arriving at t",
+    );
+}
+
+/// This function will launch a single long simulation of the queue having Exponential law describing
+/// the service times, and estimate its steady state with the batch means and the regenerative
+/// methods instead of independent replications.
+///
+/// Arguments:
+///
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+///
+/// Return:
+///
+/// Nothing BUT this function will print every estimation, with its confidence interval, next to
+/// the theoretical value.
+fn launch_long_run(arrivals_number: usize, theta: f64, rho: f64) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+
+    let simulation = exp_service_time(arrivals_number, lambda, mu, theta);
+    let theory = SetupQueue {
+        lambda,
+        service: Parameter::Exponential(ExponentialParameter { lambda: mu }).moments(),
+        setup: Parameter::Exponential(ExponentialParameter { lambda: theta }).moments(),
+    };
+
+    println!(
+        "long run (rho = {}): theoretical 𝔼[S] = {} ; theoretical P(off) = {}",
+        rho,
+        theory.avg_stay(),
+        theory.probability_p_off()
+    );
+    for (name, estimate) in [
+        ("batch means 𝔼[S]", simulation.batch_means_avg_stay(30)),
+        (
+            "overlapping batch means 𝔼[S]",
+            simulation.overlapping_batch_means_avg_stay(arrivals_number / 30),
+        ),
+        ("regenerative 𝔼[S]", simulation.regenerative_avg_stay()),
+        (
+            "regenerative P(off)",
+            simulation.regenerative_probability_server_off(),
+        ),
+    ] {
+        println!(
+            "{}: {} ; variance = {} ; CI = [{}, {}]",
+            name, estimate.point, estimate.variance, estimate.lower, estimate.upper
+        );
+    }
+    println!(
+        "time average 𝔼[N] = {} (theoretical = {}) ; time average 𝔼[N_q] = {} (theoretical = {}) ; distance between L - λW and its edge bounds = {}",
+        simulation.avg_jobs_in_system(),
+        theory.avg_in_system(),
+        simulation.avg_jobs_in_queue(),
+        theory.avg_in_queue(),
+        simulation.littles_law_discrepancy()
+    );
+    for (n, p) in simulation
+        .jobs_in_system_distribution()
+        .iter()
+        .enumerate()
+        .take(10)
+    {
+        println!("P(N = {}) = {}", n, p);
+    }
+}
+
+/// This function will launch the queue-length experiment: the time-average distribution of the
+/// number of clients in the system, given by the Markov chain embedded at the departures, is
+/// compared to the one of the simulations, for Exponential and Erlang services and setups.
+///
+/// The arrivals of the simulations are Poisson (Exponential inter-arrival times), as assumed by
+/// the embedded chain.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations of every queue.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The rate of the setup/warmup times (the Erlang setups have the same mean).
+/// * `rho`: The load of the server.
+/// * `max_jobs`: The largest number of clients whose probability is compared.
+///
+/// Return:
+///
+/// Nothing BUT this function will print both distributions with their total variation distance,
+/// the CTMC distribution when everything is Exponential, and generate the graph of both
+/// distributions for every queue.
+fn launch_queue_length_distribution(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rho: f64,
+    max_jobs: usize,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
+    let mu = lambda / rho;
+    let k = 3; // the number of phases of the Erlang laws
+
+    // the Exponential law, or the Erlang law with the same mean
+    let law = |erlang: bool, rate: f64| {
+        if erlang {
+            Parameter::Erlang(ErlangParameter {
+                k,
+                beta: 1.0 / (k as f64 * rate),
+            })
+        } else {
+            Parameter::Exponential(ExponentialParameter { lambda: rate })
+        }
+    };
+
+    for (name, erlang_service, erlang_setup) in [
+        ("exp_exp", false, false),
+        ("erlang_exp", true, false),
+        ("exp_erlang", false, true),
+        ("erlang_erlang", true, true),
+    ] {
+        let (service_param, warming_up_param) = (law(erlang_service, mu), law(erlang_setup, theta));
+        let laws = SetupQueueLaws {
+            lambda,
+            service: &service_param,
+            setup: &warming_up_param,
+        };
+        let exact = laws.jobs_in_system_distribution(max_jobs);
+
+        let distributions = (0..simulations_number)
+            .into_par_iter()
+            .map(|_| {
+                queue(
+                    arrivals_number,
+                    law(false, lambda),
+                    law(erlang_service, mu),
+                    law(erlang_setup, theta),
+                )
+                .unwrap()
+                .jobs_in_system_distribution()
+            })
+            .collect::<Vec<_>>();
+        let simulated = (0..=max_jobs)
+            .map(|n| {
+                distributions
+                    .iter()
+                    .map(|d| d.get(n).copied().unwrap_or(0.0))
+                    .calculate_mean()
+            })
+            .collect::<Vec<_>>();
+
+        // the mass beyond `max_jobs` counts in the distance as well
+        let tail_gap = (exact.iter().sum::<f64>() - simulated.iter().sum::<f64>()).abs();
+        let total_variation = (exact
+            .iter()
+            .zip(&simulated)
+            .map(|(p, q)| (p - q).abs())
+            .sum::<f64>()
+            + tail_gap)
+            / 2.0;
+        println!(
+            "{} (rho = {}): embedded chain mean = {} (theoretical 𝔼[N] = {}) ; total variation distance to the simulations = {}",
+            name,
+            rho,
+            exact.iter().enumerate().map(|(n, p)| n as f64 * p).sum::<f64>(),
+            laws.moments().avg_in_system(),
+            total_variation
+        );
+        for (n, (p, q)) in exact.iter().zip(&simulated).enumerate().take(10) {
+            println!(
+                "{}: P(N = {}) = {} (embedded chain) / {} (simulated)",
+                name, n, p, q
+            );
+        }
+
+        if !erlang_service && !erlang_setup {
+            let ctmc = MarkovianFarm {
+                lambda,
+                mu,
+                theta,
+                policy: Policy {
+                    servers: 1,
+                    idle_timer: 0.0,
+                    threshold: 1,
+                },
+                capacity: 500,
+            }
+            .solve();
+            let gap = exact
+                .iter()
+                .zip(&ctmc.jobs_in_system_distribution)
+                .map(|(p, q)| (p - q).abs())
+                .fold(0.0, f64::max);
+            println!(
+                "{} (rho = {}): largest gap between the embedded chain and the CTMC = {}",
+                name, rho, gap
+            );
+        }
+
+        let points = |distribution: &[f64]| {
+            distribution
+                .iter()
+                .enumerate()
+                .map(|(n, &p)| (n as f64, p))
+                .collect::<Vec<_>>()
+        };
+        let _ = print_comparison_graph(
+            &points(&simulated),
+            &points(&exact),
+            &format!("images/queue_length_{}", name),
+            "P(N = n)",
+        );
+    }
+}
+
+/// This function will launch the goodness-of-fit experiment: the distribution of the stay time of the
+/// queue having Exponential law describing the service times is compared to the theoretical one
+/// (see `SetupQueueLaws::stay_cdf`).
+///
+/// The stay times of the clients of a same simulation are correlated, while the tests need
+/// independent samples: only the last client of every simulation is kept.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations, and thus of samples.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the Kolmogorov-Smirnov, Anderson-Darling and chi-square
+/// tests, and generate the graph of the empirical CDF against the theoretical one.
+fn launch_distribution_fit(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rho: f64,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+
+    let stays = (0..simulations_number)
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|_| {
+            *exp_service_time(arrivals_number, lambda, mu, theta)
+                .stay_times()
+                .last()
+                .unwrap()
+        })
+        .collect::<Vec<f64>>();
+
+    let service = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let setup = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    let laws = SetupQueueLaws {
+        lambda,
+        service: &service,
+        setup: &setup,
+    };
+    let cdf = |t: f64| laws.stay_cdf(t);
+    let kolmogorov_smirnov = kolmogorov_smirnov_test(&stays, cdf);
+    let anderson_darling = anderson_darling_test(&stays, cdf);
+    let chi_square = chi_square_goodness_of_fit(&stays, cdf, (0.0, f64::INFINITY), 20).unwrap();
+
+    let alpha = 1.0 - DEFAULT_CONFIDENCE_LEVEL;
+    let verdict = |accepted: bool| if accepted { "pass" } else { "fail" };
+    println!(
+        "stay time distribution (rho = {}): KS D = {} (p-value = {}, {}) ; AD A² = {} (p-value = {}, {}) ; χ² = {} (p-value = {}, {})",
+        rho,
+        kolmogorov_smirnov.statistic,
+        kolmogorov_smirnov.p_value,
+        verdict(kolmogorov_smirnov.is_accepted(alpha)),
+        anderson_darling.statistic,
+        anderson_darling.p_value,
+        verdict(anderson_darling.is_accepted(alpha)),
+        chi_square.statistic,
+        chi_square.p_value,
+        verdict(chi_square.is_accepted(alpha)),
+    );
+
+    let x_max = quantile(&stays, 0.99);
+    let theoretical = itertools_num::linspace(0.0, x_max, 200)
+        .map(|t| (t, cdf(t)))
+        .collect::<Vec<_>>();
+    let _ = print_cdf_graph(&stays, &theoretical, "images/exp_stay_cdf", "Stay time CDF");
+}
+
+/// Compare the theoretical distribution of the stay time, obtained by inverting numerically its
+/// Laplace-Stieltjes transform, to the empirical one, for Exponential and Erlang services.
+///
+/// As for the goodness-of-fit tests, only the last client of every simulation is kept.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations, and thus of samples.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the theoretical and empirical quantiles and tail
+/// probabilities, and generate the graphs of the empirical CDF against the theoretical one.
+fn launch_stay_distribution(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rho: f64,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let k: usize = 5; // Erlang shape
+    let beta = rho / lambda / k as f64; // Erlang scale
+
+    let setup = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    let exp_service = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let erlang_service = Parameter::Erlang(ErlangParameter { k, beta });
+
+    // the inversion is checked against the closed form known for Exponential services: the stay
+    // is then the sum of two independent Exponential laws, of rates `mu - lambda` and `theta`
+    let exp_laws = SetupQueueLaws {
+        lambda,
+        service: &exp_service,
+        setup: &setup,
+    };
+    let closed_form = |t: f64| {
+        let a = mu - lambda;
+        if (a - theta).abs() < 1e-12 {
+            // both rates are equal: the stay follows an Erlang law of shape 2
+            1.0 - (1.0 + a * t) * (-a * t).exp()
+        } else {
+            1.0 - (theta * (-a * t).exp() - a * (-theta * t).exp()) / (theta - a)
+        }
+    };
+    let inversion_error = itertools_num::linspace(0.1, 10.0 * exp_laws.moments().avg_stay(), 100)
+        .map(|t| (exp_laws.stay_cdf(t) - closed_form(t)).abs())
+        .fold(0.0, f64::max);
+    println!(
+        "stay time CDF (rho = {}): biggest inversion error for exponential services = {}",
+        rho, inversion_error
+    );
+
+    let cases: [(&str, &Parameter, &(dyn Fn() -> Simulation + Sync)); 2] = [
+        ("exp", &exp_service, &|| {
+            exp_service_time(arrivals_number, lambda, mu, theta)
+        }),
+        ("erlang", &erlang_service, &|| {
+            erlang_service_time(arrivals_number, lambda, theta, k, beta)
+        }),
+    ];
+    for (name, service, simulate) in cases.iter() {
+        let laws = SetupQueueLaws {
+            lambda,
+            service,
+            setup: &setup,
+        };
+        let stays = (0..simulations_number)
+            .collect::<Vec<_>>()
+            .par_iter()
+            .map(|_| *simulate().stay_times().last().unwrap())
+            .collect::<Vec<f64>>();
+
+        for &p in [0.5, 0.9, 0.95, 0.99].iter() {
+            let t = laws.stay_quantile(p);
+            let empirical_tail =
+                stays.iter().filter(|&&s| s > t).count() as f64 / simulations_number as f64;
+            println!(
+                "{} (rho = {}): {}-quantile of S = {} (empirical = {}) ; P(S > {}) = {} (empirical = {})",
+                name,
+                rho,
+                p,
+                t,
+                quantile(&stays, p),
+                t,
+                laws.stay_tail(t),
+                empirical_tail
+            );
+        }
+
+        let x_max = laws.stay_quantile(0.99);
+        let theoretical = itertools_num::linspace(0.0, x_max, 200)
+            .map(|t| (t, laws.stay_cdf(t)))
+            .collect::<Vec<_>>();
+        let file_name = format!("images/{}_stay_cdf_by_inversion", name);
+        let _ = print_cdf_graph(&stays, &theoretical, &file_name, "Stay time CDF");
+    }
+}
+
+/// Compare the cycles of the server (nap, setup, busy period and number of clients served during
+/// the busy period) to their theoretical moments, for Exponential and Erlang services.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations whose cycles are gathered.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the mean and variance of every part of the cycles against
+/// their theoretical values, and generate the histograms of the busy periods and of the number of
+/// clients they serve.
+fn launch_cycles(simulations_number: usize, arrivals_number: usize, theta: f64, rho: f64) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let k: usize = 5; // Erlang shape
+    let beta = rho / lambda / k as f64; // Erlang scale
+
+    let setup = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    let cases: [(&str, Parameter, &(dyn Fn() -> Simulation + Sync)); 2] = [
+        (
+            "exp",
+            Parameter::Exponential(ExponentialParameter { lambda: mu }),
+            &|| exp_service_time(arrivals_number, lambda, mu, theta),
+        ),
+        (
+            "erlang",
+            Parameter::Erlang(ErlangParameter { k, beta }),
+            &|| erlang_service_time(arrivals_number, lambda, theta, k, beta),
+        ),
+    ];
+    for (name, service, simulate) in cases.iter() {
+        let theory = SetupQueue {
+            lambda,
+            service: service.moments(),
+            setup: setup.moments(),
+        };
+        let cycles = (0..simulations_number)
+            .collect::<Vec<_>>()
+            .par_iter()
+            .flat_map(|_| simulate().cycles())
+            .collect::<Vec<Cycle>>();
+
+        let naps = cycles.iter().map(|c| c.nap).collect::<Vec<_>>();
+        let setups = cycles.iter().map(|c| c.setup).collect::<Vec<_>>();
+        let busy_periods = cycles.iter().map(|c| c.busy).collect::<Vec<_>>();
+        let jobs = cycles.iter().map(|c| c.jobs as f64).collect::<Vec<_>>();
+        for (part, samples, expected) in [
+            ("nap", &naps, theory.nap()),
+            ("setup", &setups, theory.setup),
+            ("busy period", &busy_periods, theory.busy_period()),
+            (
+                "clients by busy period",
+                &jobs,
+                theory.jobs_per_busy_period(),
+            ),
+        ] {
+            let mean = samples.iter().calculate_mean();
+            let deviation = corrected_standard_deviation(mean, samples);
+            println!(
+                "{} (rho = {}): {} mean = {} (theoretical = {}) ; variance = {} (theoretical = {}) ; {} cycles",
+                name,
+                rho,
+                part,
+                mean,
+                expected.first,
+                deviation * deviation,
+                expected.variance(),
+                samples.len()
+            );
+        }
+
+        let _ = print_distribution_graph(
+            &busy_periods,
+            &format!("images/{}_busy_periods", name),
+            "Busy period",
+        );
+        let _ = print_distribution_graph(
+            &jobs,
+            &format!("images/{}_clients_by_busy_period", name),
+            "Clients by busy period",
+        );
+    }
+}
+
+/// This function will launch the energy experiment: the queue having Exponential law describing
+/// the service times is simulated for several setup rates, to see how much energy is saved by
+/// turning the server off, and how much delay it costs.
+///
+/// Arguments:
+///
+/// * `simulations_by_batch`: The number of simulations that will be launched for each couple of
+///   theta and rho.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `thetas`: The parameters of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The values of rho for which we want results
+/// * `power`: The power drawn by the server in each of its states.
+/// * `weights`: The weights of the delay and of the power in the combined cost.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the average power against its theoretical value, and the
+/// cheapest theta for every rho. It will generate 2 graphs: the energy by client against the
+/// average stay (the energy-delay trade-off), and the combined cost by rho, with one curve by
+/// theta.
+fn launch_energy(
+    simulations_by_batch: usize,
+    arrivals_number: usize,
+    thetas: &[f64],
+    rhos: &[f64],
+    power: &PowerModel,
+    weights: &CostWeights,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+
+    let mut values: Vec<Vec<EnergyData>> = Vec::new();
+    for &theta in thetas.iter().progress() {
+        let mut by_rho = Vec::new();
+        for &rho in rhos {
+            let mu = lambda / rho; // value for the service parameter (Exponential)
+            let simulations = (0..simulations_by_batch)
+                .into_par_iter()
+                .map(|_| exp_service_time(arrivals_number, lambda, mu, theta))
+                .collect::<Vec<_>>();
+            let data = EnergyData::from_simulations(rho, theta, &simulations, power, weights);
+
+            let theory = SetupQueue {
+                lambda,
+                service: Parameter::Exponential(ExponentialParameter { lambda: mu }).moments(),
+                setup: Parameter::Exponential(ExponentialParameter { lambda: theta }).moments(),
+            };
+            println!(
+                "theta = {}, rho = {}: 𝔼[P] = {} W ± {} (theoretical = {}) ; energy by client = {} J (theoretical = {}) ; cost = {} (theoretical = {})",
+                theta,
+                rho,
+                data.avg_power.mean,
+                data.avg_power.half_width(),
+                power.theoretical_avg_power(&theory),
+                data.energy_per_job.mean,
+                power.theoretical_energy_per_job(&theory),
+                data.cost.mean,
+                weights.cost(theory.avg_stay(), power.theoretical_avg_power(&theory))
+            );
+            by_rho.push(data);
+        }
+        values.push(by_rho);
+    }
+
+    for (i, &rho) in rhos.iter().enumerate() {
+        let best = values
+            .iter()
+            .map(|by_rho| &by_rho[i])
+            .min_by(|a, b| a.cost.mean.partial_cmp(&b.cost.mean).unwrap())
+            .unwrap();
+        println!(
+            "rho = {}: cheapest theta = {} (cost = {})",
+            rho, best.theta, best.cost.mean
+        );
+    }
+
+    let series = |point: &dyn Fn(&EnergyData) -> (f64, f64)| {
+        values
+            .iter()
+            .map(|by_rho| {
+                (
+                    format!("theta = {}", by_rho[0].theta),
+                    by_rho.iter().map(point).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let _ = print_series_graph(
+        &series(&|d| (d.avg_stay_time, d.energy_per_job.mean)),
+        "images/energy_delay_tradeoff",
+        "Energy by client against 𝔼[S]",
+    );
+    let _ = print_series_graph(
+        &series(&|d| (d.rho, d.cost.mean)),
+        "images/cost_by_rho",
+        "Combined cost by rho",
+    );
+}
+
+/// This function will launch the search of the best power-down policy: every policy is simulated
+/// (with common random numbers), and the one with the smallest combined cost is selected by
+/// ranking and selection.
+///
+/// Arguments:
+///
+/// * `arrivals_number`: The number of clients that will enter the farm.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load `λ / μ` brought to the farm, shared between its servers.
+/// * `policies`: The policies that are compared.
+/// * `power`: The power drawn by every server in each of its states.
+/// * `weights`: The weights of the delay and of the power in the combined cost.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the selected policy, the confidence interval of its cost
+/// and of its advantage over the runner-up.
+fn launch_policy_optimisation(
+    arrivals_number: usize,
+    theta: f64,
+    rho: f64,
+    policies: &[Policy],
+    power: &PowerModel,
+    weights: &CostWeights,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+
+    let settings = SelectionSettings {
+        initial_replications: 20,
+        max_replications: 500,
+        indifference_zone: 0.1,
+        confidence_level: DEFAULT_CONFIDENCE_LEVEL,
+    };
+    let selection = select_best(
+        policies,
+        |policy, seed| {
+            let simulation = policy_queue(
+                arrivals_number,
+                policy,
+                &inter_arrival_param,
+                &service_param,
+                &warming_up_param,
+                seed,
+            );
+            weights.cost(simulation.avg_stay(), power.avg_power_of_farm(&simulation))
+        },
+        &settings,
+    );
+
+    println!(
+        "policy optimisation (rho = {}): best policy = {:?} ; cost = {} ; {}% CI = [{}, {}] ; {} replications in total for {} policies",
+        rho,
+        policies[selection.best],
+        selection.cost.mean,
+        settings.confidence_level * 100.0,
+        selection.cost.lower,
+        selection.cost.upper,
+        selection.replications.iter().sum::<usize>(),
+        policies.len()
+    );
+    if let Some(gap) = selection.gap_to_runner_up {
+        println!(
+            "policy optimisation (rho = {}): the runner-up costs {} more ; {}% CI = [{}, {}]",
+            rho,
+            gap.mean,
+            settings.confidence_level * 100.0,
+            gap.lower,
+            gap.upper
+        );
+    }
+}
+
+/// This function will launch the exact resolution of the Markovian farms (every law Exponential)
+/// as CTMCs truncated at a maximal number of clients, and compare it with the closed forms of the
+/// M/M/1 queue with setup and with the simulations of the farm.
+///
+/// The simulations use Exponential inter-arrival times (unlike the other experiments), so that
+/// the farm is really Markovian. Their idle timers are deterministic, so only the policies whose
+/// servers turn off at once or never are comparable.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations of every policy.
+/// * `arrivals_number`: The number of clients of every simulation.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The loads `λ / μ` of the single server queue.
+/// * `rho`: The load `λ / μ` brought to the farms, shared between their servers.
+/// * `policies`: The policies of the farms.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the exact metrics next to the closed forms or to the
+/// simulated ones, and the effect of a finite capacity.
+fn launch_markov_models(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+    rho: f64,
+    policies: &[Policy],
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
 
-    let theta = 0.6; // parameter for the warmup (setup) random distribution (exp)
+    // the truncation for which the blocking probability is about `1e-12` or less: the number of
+    // clients decreases geometrically, with the load of a server or with the ratio of the clients
+    // arriving during a setup
+    let capacity = |load: f64| {
+        let ratio = load.max(lambda / (lambda + theta));
+        (-12.0 * 10f64.ln() / ratio.ln()).ceil() as usize + 10
+    };
+    let single_server = Policy {
+        servers: 1,
+        idle_timer: 0.0,
+        threshold: 1,
+    };
+
+    for &rho in rhos.iter() {
+        let mu = lambda / rho;
+        let exact = MarkovianFarm {
+            lambda,
+            mu,
+            theta,
+            policy: single_server,
+            capacity: capacity(rho),
+        }
+        .solve();
+        let closed_form = SetupQueue {
+            lambda,
+            service: Parameter::Exponential(ExponentialParameter { lambda: mu }).moments(),
+            setup: Parameter::Exponential(ExponentialParameter { lambda: theta }).moments(),
+        };
+        println!(
+            "rho = {}: CTMC vs closed form: 𝔼[S] = {} / {} ; P(off) = {} / {} ; P(setup) = {} / {} ; 𝔼[N] = {} / {} ; 𝔼[N_q] = {} / {} ; switch-on rate = {} / {}",
+            rho,
+            exact.avg_stay,
+            closed_form.avg_stay(),
+            exact.avg_servers_off,
+            closed_form.probability_p_off(),
+            exact.avg_servers_setup,
+            closed_form.probability_p_setup(),
+            exact.avg_jobs_in_system,
+            closed_form.avg_in_system(),
+            exact.avg_jobs_in_queue,
+            closed_form.avg_in_queue(),
+            exact.switch_on_rate,
+            1.0 / closed_form.avg_cycle()
+        );
+    }
+
+    let mu = lambda / rho;
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    for policy in policies.iter() {
+        let exact = MarkovianFarm {
+            lambda,
+            mu,
+            theta,
+            policy: *policy,
+            capacity: capacity(rho / policy.servers as f64),
+        }
+        .solve();
+        println!(
+            "{:?} (rho = {}): CTMC: 𝔼[S] = {} ; servers off = {} ; setup = {} ; idle = {} ; busy = {} ; switch-on rate = {}",
+            policy,
+            rho,
+            exact.avg_stay,
+            exact.avg_servers_off,
+            exact.avg_servers_setup,
+            exact.avg_servers_idle,
+            exact.avg_servers_busy,
+            exact.switch_on_rate
+        );
+        if policy.idle_timer == 0.0 || policy.idle_timer.is_infinite() {
+            let simulations = (0..simulations_number as u64)
+                .into_par_iter()
+                .map(|seed| {
+                    policy_queue(
+                        arrivals_number,
+                        policy,
+                        &inter_arrival_param,
+                        &service_param,
+                        &warming_up_param,
+                        seed,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let mean = |metric: fn(&PolicySimulation) -> f64| {
+                simulations.iter().map(metric).calculate_mean()
+            };
+            println!(
+                "{:?} (rho = {}): simulation: 𝔼[S] = {} ; servers off = {} ; setup = {} ; idle = {} ; busy = {} ; switch-on rate = {}",
+                policy,
+                rho,
+                mean(PolicySimulation::avg_stay),
+                mean(PolicySimulation::avg_servers_off),
+                mean(PolicySimulation::avg_servers_setup),
+                mean(PolicySimulation::avg_servers_idle),
+                mean(PolicySimulation::avg_servers_busy),
+                mean(PolicySimulation::switch_on_rate)
+            );
+        }
+    }
 
-    launch_exp(simulations_by_batch, arrivals_number, theta, &rhos);
-    launch_erlang(simulations_by_batch, arrivals_number, theta, &rhos);
+    // a small waiting room loses clients, but bounds their stay
+    for capacity in [1, 2, 5, 10, 20] {
+        let exact = MarkovianFarm {
+            lambda,
+            mu,
+            theta,
+            policy: single_server,
+            capacity,
+        }
+        .solve();
+        println!(
+            "M/M/1/{} with setup (rho = {}): blocking probability = {} ; 𝔼[S] = {} ; P(off) = {}",
+            capacity, rho, exact.blocking_probability, exact.avg_stay, exact.avg_servers_off
+        );
+    }
 }
 
-/// This function will launch the experiments linked to the queue having Exponential law describing
-/// the service times.
+/// This function will launch the heavy-traffic experiment: the average stay of the queue with
+/// setup is approximated (Kingman, Marchal and the heavy-traffic diffusion limit, setup included)
+/// up to a load close to one, and compared to long simulations and to the exact value when there
+/// is one (with Poisson arrivals).
 ///
 /// Arguments:
-///  
-/// * `simulations_by_batch`: The number of simulations that will be launched for each rho value.
-/// * `arrivals_number`: The number of clients that will enter the queue.
+///
+/// * `simulations_number`: The number of simulations by rho.
+/// * `arrivals_number`: The number of clients that will enter every simulation, enough for the
+///   queue to forget its empty start even at a high load.
 /// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
-/// * `rhos`: The values of rho for which we want results
+/// * `rhos`: The values of rho for which we want results.
 ///
 /// Return:
 ///
-/// Nothing BUT this function will generate 6 graphs: the average stay in the queue (by rho), the
-/// probability of arriving with the server warming up (by rho) and the probability of arriving
-/// with the server offline (by rho).
-/// Those 3 kind of graphs are declined with the hypothesis testing that the computed point is equal
-/// to the theoretical value.
-fn launch_exp(simulations_by_batch: usize, arrivals_number: usize, theta: f64, rhos: &[f64]) {
-    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+/// Nothing BUT this function will print the relative error of every approximation, and generate
+/// the graph of the approximations against the simulations (and the exact value) for Exponential
+/// and Erlang inter-arrival times.
+fn launch_heavy_traffic(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
+    let k = 2; // the shape of the Erlang inter-arrival and service times
+    let approximations = [
+        ("Kingman", Approximation::Kingman),
+        ("Marchal", Approximation::Marchal),
+        ("heavy traffic", Approximation::HeavyTraffic),
+        ("Whitt", Approximation::Whitt),
+    ];
 
-    let mut values: Vec<Data> = Vec::new();
-    for &rho in rhos.iter().progress() {
-        let mu = lambda / rho; // value for the service parameter (Exponential)
+    for (name, erlang_arrivals) in [("M", false), ("E2", true)] {
+        let inter_arrival_param = || {
+            if erlang_arrivals {
+                Parameter::Erlang(ErlangParameter {
+                    k,
+                    beta: 1.0 / (k as f64 * lambda),
+                })
+            } else {
+                Parameter::Exponential(ExponentialParameter { lambda })
+            }
+        };
+        let service_param = |rho: f64| {
+            Parameter::Erlang(ErlangParameter {
+                k,
+                beta: rho / (k as f64 * lambda),
+            })
+        };
+        let warming_up_param = || Parameter::Exponential(ExponentialParameter { lambda: theta });
 
-        // simulations are computed in parallel
-        let simulations = (0..=simulations_by_batch)
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map(|_| exp_service_time(arrivals_number, lambda, mu, theta))
-            .collect::<Vec<Simulation>>();
+        let mut simulated = Vec::new();
+        let mut exact = Vec::new();
+        let mut approximated = vec![Vec::new(); approximations.len()];
+        for &rho in rhos.iter() {
+            let stays = (0..simulations_number)
+                .into_par_iter()
+                .map(|_| {
+                    queue(
+                        arrivals_number,
+                        inter_arrival_param(),
+                        service_param(rho),
+                        warming_up_param(),
+                    )
+                    .unwrap()
+                    .avg_stay()
+                })
+                .collect::<Vec<_>>();
+            let interval = confidence_interval(&stays, DEFAULT_CONFIDENCE_LEVEL);
+            simulated.push((rho, interval));
 
-        let avg_stay_time = simulations
-            .iter()
-            .map(|s: &Simulation| s.avg_stay())
-            .calculate_mean();
+            let laws = SetupApproximations::from_parameters(
+                &inter_arrival_param(),
+                &service_param(rho),
+                &warming_up_param(),
+            );
+            // the exact value is only known with Poisson arrivals
+            let reference = if erlang_arrivals {
+                interval.mean
+            } else {
+                let value = SetupQueue {
+                    lambda,
+                    service: laws.service,
+                    setup: laws.setup,
+                }
+                .avg_stay();
+                exact.push((rho, value));
+                value
+            };
 
-        let probability_p_off = simulations
-            .iter()
-            .map(|s: &Simulation| s.probability_server_off())
-            .calculate_mean();
+            let errors = approximations
+                .iter()
+                .zip(approximated.iter_mut())
+                .map(|(&(approximation_name, approximation), points)| {
+                    let value = laws.avg_stay(approximation);
+                    points.push((rho, value));
+                    format!(
+                        "{} = {} ({:+.2}%)",
+                        approximation_name,
+                        value,
+                        100.0 * (value - reference) / reference
+                    )
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "{}/E2/1 with setup (rho = {}): simulated 𝔼[S] = {} ; CI = [{}, {}] ; {} = {} ; {}",
+                name,
+                rho,
+                interval.mean,
+                interval.lower,
+                interval.upper,
+                if erlang_arrivals {
+                    "reference (simulated)"
+                } else {
+                    "exact"
+                },
+                reference,
+                errors.join(" ; ")
+            );
+        }
 
-        let probability_p_setup = simulations
+        let named = approximations
             .iter()
-            .map(|s: &Simulation| s.probability_server_setup())
-            .calculate_mean();
+            .zip(approximated)
+            .map(|(&(approximation_name, _), points)| (approximation_name.to_owned(), points))
+            .collect::<Vec<_>>();
+        let _ = print_approximation_graph(
+            &simulated,
+            if erlang_arrivals { None } else { Some(&exact) },
+            &named,
+            &format!("images/heavy_traffic_{}_e2_1", name.to_lowercase()),
+            &format!("{}/E2/1 with setup: E[S] by rho", name),
+        );
+    }
+}
+
+/// This function will launch the G/G/1 experiment: the queue with setup is fed by renewal arrivals
+/// of various squared coefficients of variation (Erlang below one, Exponential at one and
+/// hyper-exponential above), and its average stay is compared to the approximations (Kingman, or
+/// Allen-Cunneen, Marchal, Whitt and the heavy-traffic limit), setup correction included, both by
+/// rho and by arrival SCV.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations by pair of rho and SCV.
+/// * `arrivals_number`: The number of clients that will enter every simulation.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rhos`: The values of rho for which we want results.
+/// * `scvs`: The squared coefficients of variation of the inter-arrival times (the ones below one
+///   must be of the form `1 / k`).
+///
+/// Return:
+///
+/// Nothing BUT this function will print the relative error of every approximation (and the exact
+/// M/E2/1 value when the arrivals are Poisson), and generate the graphs of the approximations
+/// against the simulations, by rho for every SCV and by SCV for every rho.
+fn launch_arrival_variability(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+    scvs: &[f64],
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
+    let k = 2; // the shape of the Erlang service times
+    let approximations = [
+        ("Kingman", Approximation::Kingman),
+        ("Marchal", Approximation::Marchal),
+        ("Whitt", Approximation::Whitt),
+        ("heavy traffic", Approximation::HeavyTraffic),
+    ];
+    let inter_arrival_param = |scv: f64| Parameter::with_scv(1.0 / lambda, scv);
+    let service_param = |rho: f64| {
+        Parameter::Erlang(ErlangParameter {
+            k,
+            beta: rho / (k as f64 * lambda),
+        })
+    };
+    let warming_up_param = || Parameter::Exponential(ExponentialParameter { lambda: theta });
 
-        // the way to calculate a corrected standard deviation:
-        // sqrt( 1 / (n-1) * sum( (µ - µ̂)² ) )
+    // for every SCV then every rho: the simulated interval and the approximated values
+    let mut results = Vec::new();
+    for &scv in scvs.iter() {
+        let mut by_rho = Vec::new();
+        for &rho in rhos.iter() {
+            let stays = (0..simulations_number)
+                .into_par_iter()
+                .map(|_| {
+                    queue(
+                        arrivals_number,
+                        inter_arrival_param(scv),
+                        service_param(rho),
+                        warming_up_param(),
+                    )
+                    .unwrap()
+                    .avg_stay()
+                })
+                .collect::<Vec<_>>();
+            let interval = confidence_interval(&stays, DEFAULT_CONFIDENCE_LEVEL);
 
-        let corrected_standard_deviation_avg_stay = f64::sqrt(
-            1.0 / (simulations_by_batch - 1) as f64
-                * simulations
-                    .iter()
-                    .map(|s: &Simulation| (s.avg_stay() - avg_stay_time).powi(2))
-                    .sum::<f64>(),
-        );
-        let corrected_standard_deviation_p_off = f64::sqrt(
-            1.0 / (simulations_by_batch - 1) as f64
-                * simulations
+            let laws = SetupApproximations::from_parameters(
+                &inter_arrival_param(scv),
+                &service_param(rho),
+                &warming_up_param(),
+            );
+            let values = approximations
+                .iter()
+                .map(|&(_, approximation)| laws.avg_stay(approximation))
+                .collect::<Vec<_>>();
+            let errors = approximations
+                .iter()
+                .zip(values.iter())
+                .map(|(&(approximation_name, _), value)| {
+                    format!(
+                        "{} = {} ({:+.2}%)",
+                        approximation_name,
+                        value,
+                        100.0 * (value - interval.mean) / interval.mean
+                    )
+                })
+                .collect::<Vec<_>>();
+            // the exact value is only known with Poisson arrivals
+            let exact = if scv == 1.0 {
+                Some(
+                    SetupQueue {
+                        lambda,
+                        service: laws.service,
+                        setup: laws.setup,
+                    }
+                    .avg_stay(),
+                )
+            } else {
+                None
+            };
+            println!(
+                "G/E2/1 with setup (c_a² = {}, rho = {}): simulated 𝔼[S] = {} ; CI = [{}, {}] ; {}{}",
+                scv,
+                rho,
+                interval.mean,
+                interval.lower,
+                interval.upper,
+                exact.map_or(String::new(), |value| format!("exact = {} ; ", value)),
+                errors.join(" ; ")
+            );
+            by_rho.push((interval, values, exact));
+        }
+        results.push(by_rho);
+    }
+
+    // the graphs by rho, one by SCV
+    for (&scv, by_rho) in scvs.iter().zip(results.iter()) {
+        let simulated = rhos
+            .iter()
+            .zip(by_rho.iter())
+            .map(|(&rho, (interval, _, _))| (rho, *interval))
+            .collect::<Vec<_>>();
+        let exact = rhos
+            .iter()
+            .zip(by_rho.iter())
+            .map(|(&rho, (_, _, exact))| exact.map(|value| (rho, value)))
+            .collect::<Option<Vec<_>>>();
+        let named = approximations
+            .iter()
+            .enumerate()
+            .map(|(i, &(approximation_name, _))| {
+                let points = rhos
                     .iter()
-                    .map(|s: &Simulation| (s.probability_server_off() - probability_p_off).powi(2))
-                    .sum::<f64>(),
+                    .zip(by_rho.iter())
+                    .map(|(&rho, (_, values, _))| (rho, values[i]))
+                    .collect();
+                (approximation_name.to_owned(), points)
+            })
+            .collect::<Vec<_>>();
+        let _ = print_approximation_graph(
+            &simulated,
+            exact.as_deref(),
+            &named,
+            &format!("images/arrival_variability_scv_{}", scv),
+            &format!("G/E2/1 with setup (c_a² = {}): E[S] by rho", scv),
         );
+    }
 
-        let corrected_standard_deviation_p_setup = f64::sqrt(
-            1.0 / (simulations_by_batch - 1) as f64
-                * simulations
+    // the graphs by SCV, one by rho
+    for (j, &rho) in rhos.iter().enumerate() {
+        let simulated = scvs
+            .iter()
+            .zip(results.iter())
+            .map(|(&scv, by_rho)| (scv, by_rho[j].0))
+            .collect::<Vec<_>>();
+        let named = approximations
+            .iter()
+            .enumerate()
+            .map(|(i, &(approximation_name, _))| {
+                let points = scvs
                     .iter()
-                    .map(|s: &Simulation| {
-                        (s.probability_server_setup() - probability_p_setup).powi(2)
-                    })
-                    .sum::<f64>(),
+                    .zip(results.iter())
+                    .map(|(&scv, by_rho)| (scv, by_rho[j].1[i]))
+                    .collect();
+                (approximation_name.to_owned(), points)
+            })
+            .collect::<Vec<_>>();
+        let _ = print_approximation_graph(
+            &simulated,
+            None,
+            &named,
+            &format!("images/arrival_variability_rho_{}", rho),
+            &format!("G/E2/1 with setup (rho = {}): E[S] by c_a²", rho),
         );
+    }
+}
 
-        values.push(Data {
-            rho,
+/// This function will launch the quasi-birth-death experiment: the queues with phase-type services
+/// or setups, and the farms of servers with phase-type setups, are solved exactly (without
+/// truncating the number of clients), even at a load close to one.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of simulations of every farm.
+/// * `arrivals_number`: The number of clients that will enter every farm.
+/// * `theta`: The rate of the setup/warmup times (the Erlang setups have the same mean).
+/// * `rhos`: The loads at which the single server queues are solved.
+/// * `servers`: The numbers of servers of the farms.
+/// * `rho`: The load of a server of the farms.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the QBD metrics next to the closed forms of the M/G/1
+/// queue with setup, next to the truncated CTMC (whose truncation is too small at high load), and
+/// next to the simulations of the farms.
+fn launch_qbd(
+    simulations_number: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rhos: &[f64],
+    servers: &[usize],
+    rho: f64,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival
+    let k = 3; // the number of phases of the Erlang laws
+
+    for &rho in rhos.iter() {
+        let mu = lambda / rho;
+        // the services with a coefficient of variation of 0, 1/√3 and 2 (balanced means)
+        let services = [
+            ("Exponential", PhaseType::exponential(mu)),
+            ("Erlang", PhaseType::erlang(k, k as f64 * mu)),
+            (
+                "hyper-exponential",
+                PhaseType::hyperexponential(&[0.1, 0.9], &[2.0 * 0.1 * mu, 2.0 * 0.9 * mu]),
+            ),
+        ];
+        for (name, service) in services.iter() {
+            for setup in [
+                PhaseType::exponential(theta),
+                PhaseType::erlang(k, k as f64 * theta),
+            ] {
+                let (_, exact) = PhaseTypeSetupQueue {
+                    lambda,
+                    service: service.clone(),
+                    setup: setup.clone(),
+                }
+                .solve();
+                let closed_form = SetupQueue {
+                    lambda,
+                    service: service.moments(),
+                    setup: setup.moments(),
+                };
+                println!(
+                    "M/PH/1 with {} services and {} setup phase(s) (rho = {}): QBD vs closed form: 𝔼[S] = {} / {} ; P(off) = {} / {} ; P(setup) = {} / {} ; 𝔼[N] = {} / {}",
+                    name,
+                    setup.phases(),
+                    rho,
+                    exact.avg_stay,
+                    closed_form.avg_stay(),
+                    exact.avg_servers_off,
+                    closed_form.probability_p_off(),
+                    exact.avg_servers_setup,
+                    closed_form.probability_p_setup(),
+                    exact.avg_jobs_in_system,
+                    closed_form.avg_in_system()
+                );
+            }
+        }
+
+        // the truncation of the CTMC that is enough at a moderate load
+        let truncated = MarkovianFarm {
             lambda,
-            mu: Some(mu),
-            k: None,
-            beta: None,
+            mu,
             theta,
-            avg_stay_time,
-            corrected_variance_avg_stay: corrected_standard_deviation_avg_stay,
-            probability_p_off,
-            corrected_variance_p_off: corrected_standard_deviation_p_off,
-            probability_p_setup,
-            corrected_variance_p_setup: corrected_standard_deviation_p_setup,
-            n_simulations: simulations_by_batch,
-        });
+            policy: Policy {
+                servers: 1,
+                idle_timer: 0.0,
+                threshold: 1,
+            },
+            capacity: 100,
+        }
+        .solve();
+        let (_, exact) = PhaseTypeSetupQueue {
+            lambda,
+            service: PhaseType::exponential(mu),
+            setup: PhaseType::exponential(theta),
+        }
+        .solve();
+        println!(
+            "M/M/1 with setup (rho = {}): QBD vs CTMC truncated at 100 clients: 𝔼[S] = {} / {} (blocking probability = {})",
+            rho, exact.avg_stay, truncated.avg_stay, truncated.blocking_probability
+        );
+    }
+
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let setups = [
+        (
+            "Exponential",
+            Parameter::Exponential(ExponentialParameter { lambda: theta }),
+        ),
+        (
+            "Erlang",
+            Parameter::Erlang(ErlangParameter {
+                k,
+                beta: 1.0 / (k as f64 * theta),
+            }),
+        ),
+    ];
+    for &c in servers.iter() {
+        let mu = lambda / (rho * c as f64);
+        let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+        let policy = Policy {
+            servers: c,
+            idle_timer: 0.0,
+            threshold: 1,
+        };
+        for (name, warming_up_param) in setups.iter() {
+            let (solution, exact) = PhaseTypeSetupFarm {
+                lambda,
+                mu,
+                servers: c,
+                setup: PhaseType::from_parameter(warming_up_param),
+            }
+            .solve();
+            println!(
+                "M/M/{} with {} setups (rho = {}): QBD: 𝔼[S] = {} ; servers off = {} ; setup = {} ; busy = {} ; switch-on rate = {} ; P(N > 100) = {}",
+                c,
+                name,
+                rho,
+                exact.avg_stay,
+                exact.avg_servers_off,
+                exact.avg_servers_setup,
+                exact.avg_servers_busy,
+                exact.switch_on_rate,
+                1.0 - solution.level_distribution(100).iter().sum::<f64>()
+            );
+            if let Parameter::Exponential(_) = warming_up_param {
+                let ctmc = MarkovianFarm {
+                    lambda,
+                    mu,
+                    theta,
+                    policy,
+                    capacity: 100,
+                }
+                .solve();
+                println!(
+                    "M/M/{} with {} setups (rho = {}): CTMC truncated at 100 clients: 𝔼[S] = {} ; servers off = {} ; setup = {} ; busy = {} ; switch-on rate = {}",
+                    c,
+                    name,
+                    rho,
+                    ctmc.avg_stay,
+                    ctmc.avg_servers_off,
+                    ctmc.avg_servers_setup,
+                    ctmc.avg_servers_busy,
+                    ctmc.switch_on_rate
+                );
+            }
+
+            let simulations = (0..simulations_number as u64)
+                .into_par_iter()
+                .map(|seed| {
+                    policy_queue(
+                        arrivals_number,
+                        &policy,
+                        &inter_arrival_param,
+                        &service_param,
+                        warming_up_param,
+                        seed,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let mean = |metric: fn(&PolicySimulation) -> f64| {
+                simulations.iter().map(metric).calculate_mean()
+            };
+            println!(
+                "M/M/{} with {} setups (rho = {}): simulation: 𝔼[S] = {} ; servers off = {} ; setup = {} ; busy = {} ; switch-on rate = {}",
+                c,
+                name,
+                rho,
+                mean(PolicySimulation::avg_stay),
+                mean(PolicySimulation::avg_servers_off),
+                mean(PolicySimulation::avg_servers_setup),
+                mean(PolicySimulation::avg_servers_busy),
+                mean(PolicySimulation::switch_on_rate)
+            );
+        }
     }
-    let _ = print_avg_stay_graph_for_exp(&values);
-    let _ = print_p_setup_graph(&values, "images/exp_p_setup_by_rho");
-    let _ = print_p_off_graph(&values, "images/exp_p_off_by_rho");
 }
 
-/// This function will launch the experiments linked to the queue having Exponential law describing
-/// the service times.
+/// This function will launch the sensitivity experiment: the derivatives of the average stay and of
+/// the probability of finding the server off with respect to the arrival, service and setup rates
+/// are estimated from the simulations of the queue having Exponential law describing the service
+/// times, both by infinitesimal perturbation analysis and by likelihood ratio.
 ///
 /// Arguments:
-///  
-/// * `simulations_by_batch`: The number of simulations that will be launched for each rho value.
+///
+/// * `simulations_number`: The number of simulations.
 /// * `arrivals_number`: The number of clients that will enter the queue.
 /// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
-/// * `rhos`: The values of rho for which we want results
+/// * `rho`: The load of the server.
 ///
 /// Return:
 ///
-/// Nothing BUT this function will generate 6 graphs: the average stay in the queue (by rho), the
-/// probability of arriving with the server warming up (by rho) and the probability of arriving
-/// with the server offline (by rho).
-/// Those 3 kind of graphs are declined with the hypothesis testing that the computed point is equal
-/// to the theoretical value.
-fn launch_erlang(simulations_by_batch: usize, arrivals_number: usize, theta: f64, rhos: &[f64]) {
-    let mut values: Vec<Data> = Vec::new();
-    let mut d: Option<f64> = None; // used to compute 𝔼[W²]
+/// Nothing BUT this function will print both estimations of every derivative, with their
+/// confidence intervals, next to the derivative of the theoretical value.
+fn launch_gradients(simulations_number: usize, arrivals_number: usize, theta: f64, rho: f64) {
+    let lambda = 1.0; // the rate of the arrival (Poisson process, Exponential inter-arrival times)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
 
-    let k: usize = 5; // Erlang shape
-    let lambda = 1.0; // Poisson param
-    for &rho in rhos.iter().progress() {
-        let beta = rho / lambda / k as f64; // Erlang scale -> /!\ rate = 1/beta
+    let simulations = (0..simulations_number)
+        .into_par_iter()
+        .map(|_| exp_service_time(arrivals_number, lambda, mu, theta))
+        .collect::<Vec<_>>();
+    let data = Data {
+        mu: Some(mu),
+        ..Data::from_simulations(rho, lambda, theta, &simulations)
+    }
+    .with_gradients(
+        &simulations,
+        [&inter_arrival_param, &service_param, &warming_up_param],
+    );
+    let gradients = data.gradients.unwrap();
 
-        // simulations are computed in parallel
-        let simulations = (0..=simulations_by_batch)
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map(|_| erlang_service_time(arrivals_number, lambda, theta, k, beta))
-            .collect::<Vec<Simulation>>();
+    // the theoretical derivatives, by central differences of the closed forms
+    let theory = |rates: [f64; 3]| SetupQueue {
+        lambda: rates[0],
+        service: Parameter::Exponential(ExponentialParameter { lambda: rates[1] }).moments(),
+        setup: Parameter::Exponential(ExponentialParameter { lambda: rates[2] }).moments(),
+    };
+    let rates = [lambda, mu, theta];
+    let derivative = |k: usize, metric: fn(&SetupQueue) -> f64| {
+        let h = 1e-6 * rates[k];
+        let (mut up, mut down) = (rates, rates);
+        up[k] += h;
+        down[k] -= h;
+        (metric(&theory(up)) - metric(&theory(down))) / (2.0 * h)
+    };
 
-        let avg_stay_time = simulations
-            .iter()
-            .map(|s: &Simulation| s.avg_stay())
-            .calculate_mean();
+    let format = |interval: Option<ConfidenceInterval>| match interval {
+        Some(i) => format!("{} ; CI = [{}, {}]", i.mean, i.lower, i.upper),
+        None => "not available".to_owned(),
+    };
+    for (k, rate) in ["lambda", "mu", "theta"].iter().enumerate() {
+        println!(
+            "rho = {}: ∂𝔼[S]/∂{}: IPA = {} ; LR = {} ; theoretical = {}",
+            rho,
+            rate,
+            format(gradients.avg_stay_ipa[k]),
+            format(Some(gradients.avg_stay_lr[k])),
+            derivative(k, SetupQueue::avg_stay)
+        );
+        println!(
+            "rho = {}: ∂P(off)/∂{}: IPA = {} ; LR = {} ; theoretical = {}",
+            rho,
+            rate,
+            format(gradients.p_off_ipa[k]),
+            format(Some(gradients.p_off_lr[k])),
+            derivative(k, SetupQueue::probability_p_off)
+        );
+    }
+}
 
-        let probability_p_off = simulations
-            .iter()
-            .map(|s: &Simulation| s.probability_server_off())
-            .calculate_mean();
+/// This function will launch the variance reduction experiment on the queue having Exponential law
+/// describing the service times, and report how much each technique saves on every metric:
+///
+/// * antithetic variates: the simulations are run by antithetic pairs (see `antithetic_queue`);
+/// * control variates: the metrics of independent simulations are corrected with the average
+///   inter-arrival and service times, whose expectations are known;
+/// * common random numbers: two power-down policies are compared with the same seeds (see
+///   `policy_queue`), instead of independent ones.
+///
+/// Arguments:
+///
+/// * `pairs`: The number of antithetic pairs, the other techniques use twice as many simulations.
+/// * `arrivals_number`: The number of clients that will enter the queue.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+/// * `policies`: The two policies whose difference of cost is estimated.
+/// * `power`: The power drawn by a server in each of its states.
+/// * `weights`: The weights of the average stay and of the average power in the cost.
+///
+/// Return:
+///
+/// Nothing BUT this function will print, for every metric and technique, the plain and the reduced
+/// confidence intervals with the variance reduction factor.
+fn launch_variance_reduction(
+    pairs: usize,
+    arrivals_number: usize,
+    theta: f64,
+    rho: f64,
+    policies: &[Policy; 2],
+    power: &PowerModel,
+    weights: &CostWeights,
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
 
-        let probability_p_setup = simulations
-            .iter()
-            .map(|s: &Simulation| s.probability_server_setup())
-            .calculate_mean();
+    let antithetic_simulations = (0..pairs)
+        .into_par_iter()
+        .map(|_| {
+            antithetic_queue(
+                arrivals_number,
+                &inter_arrival_param,
+                &service_param,
+                &warming_up_param,
+            )
+        })
+        .collect::<Vec<_>>();
+    let simulations = (0..2 * pairs)
+        .into_par_iter()
+        .map(|_| exp_service_time(arrivals_number, lambda, mu, theta))
+        .collect::<Vec<_>>();
+    let controls = [
+        simulations.iter().map(|s| s.avg_inter_arrival()).collect(),
+        simulations.iter().map(|s| s.avg_service()).collect(),
+    ];
+    let control_means = [
+        inter_arrival_param.moments().first,
+        service_param.moments().first,
+    ];
 
-        let corrected_standard_deviation_avg_stay = corrected_standard_deviation(
-            avg_stay_time,
-            &simulations.iter().map(|s| s.avg_stay()).collect::<Vec<_>>(),
+    let format = |interval: &ConfidenceInterval| {
+        format!(
+            "{} ; CI = [{}, {}]",
+            interval.mean, interval.lower, interval.upper
+        )
+    };
+    let metrics = [
+        ("𝔼[S]", Metric::AvgStay),
+        ("P(off)", Metric::ProbabilityOff),
+        ("P(setup)", Metric::ProbabilitySetup),
+        (
+            "fraction of clients paying the warmup",
+            Metric::FractionWithWarmup,
+        ),
+        ("𝔼[N]", Metric::AvgJobsInSystem),
+        ("𝔼[N_q]", Metric::AvgJobsInQueue),
+        ("95th percentile of S", Metric::StayPercentile(2)),
+    ];
+    for (name, metric) in metrics.iter() {
+        let antithetic = antithetic_estimate(
+            &antithetic_simulations
+                .iter()
+                .filter_map(|(a, b)| Some((metric.value(a)?, metric.value(b)?)))
+                .collect::<Vec<_>>(),
+            DEFAULT_CONFIDENCE_LEVEL,
         );
-
-        let corrected_standard_deviation_p_off = corrected_standard_deviation(
-            probability_p_off,
+        let control_variates = control_variates_estimate(
             &simulations
                 .iter()
-                .map(|s| s.probability_server_off())
+                .map(|s| metric.value(s).unwrap())
                 .collect::<Vec<_>>(),
+            &controls,
+            &control_means,
+            DEFAULT_CONFIDENCE_LEVEL,
+        );
+        println!(
+            "rho = {}: {}: antithetic = {} (plain = {}) ; factor = {}",
+            rho,
+            name,
+            format(&antithetic.reduced),
+            format(&antithetic.plain),
+            antithetic.factor
+        );
+        println!(
+            "rho = {}: {}: control variates = {} (plain = {}) ; factor = {}",
+            rho,
+            name,
+            format(&control_variates.reduced),
+            format(&control_variates.plain),
+            control_variates.factor
+        );
+    }
+
+    // every seed gives the same clients to both policies
+    let runs = |policy: &Policy| {
+        (0..2 * pairs as u64)
+            .into_par_iter()
+            .map(|seed| {
+                let simulation = policy_queue(
+                    arrivals_number,
+                    policy,
+                    &inter_arrival_param,
+                    &service_param,
+                    &warming_up_param,
+                    seed,
+                );
+                let avg_power = power.avg_power_of_farm(&simulation);
+                [
+                    simulation.avg_stay(),
+                    avg_power,
+                    weights.cost(simulation.avg_stay(), avg_power),
+                ]
+            })
+            .collect::<Vec<_>>()
+    };
+    let (first, second) = (runs(&policies[0]), runs(&policies[1]));
+    for (k, name) in ["𝔼[S]", "average power", "cost"].iter().enumerate() {
+        let crn = common_random_numbers_estimate(
+            &first.iter().map(|r| r[k]).collect::<Vec<_>>(),
+            &second.iter().map(|r| r[k]).collect::<Vec<_>>(),
+            DEFAULT_CONFIDENCE_LEVEL,
         );
-        let corrected_standard_deviation_p_setup = corrected_standard_deviation(
-            probability_p_setup,
+        println!(
+            "rho = {}: {} of {:?} minus {:?}: common random numbers = {} (independent = {}) ; factor = {}",
+            rho,
+            name,
+            policies[0],
+            policies[1],
+            format(&crn.reduced),
+            format(&crn.plain),
+            crn.factor
+        );
+    }
+}
+
+/// This function will launch the rare event experiment: the probability that a client of the queue
+/// having Exponential law describing the service times stays longer than a multiple of the average
+/// stay, estimated by crude Monte Carlo, by importance sampling and by splitting.
+///
+/// The importance sampling uses the Cramér root as tilt. The splitting levels are spaced so that
+/// each one is reached from the previous one with a probability of about `1 / retrials`, the tail
+/// decreasing like `e^(-γx)`.
+///
+/// Arguments:
+///
+/// * `simulations_number`: The number of crude and of importance sampling simulations.
+/// * `arrivals_number`: The number of clients of every crude simulation.
+/// * `tilted_arrivals_number`: The number of clients of every importance sampling simulation,
+///   which must cover many long cycles.
+/// * `cycles`: The number of regeneration cycles of the splitting.
+/// * `theta`: The parameter of the law describing the setup/warmup times (Exponential)
+/// * `rho`: The load of the server.
+/// * `multiples`: The values of `x / 𝔼[S]` for which `P(S > x)` is estimated.
+///
+/// Return:
+///
+/// Nothing BUT this function will print the three estimations of every probability, with their
+/// relative errors.
+fn launch_rare_events(
+    simulations_number: usize,
+    arrivals_number: usize,
+    tilted_arrivals_number: usize,
+    cycles: usize,
+    theta: f64,
+    rho: f64,
+    multiples: &[f64],
+) {
+    let lambda = 1.0; // the value for the parameter of the arrival (Poisson)
+    let mu = lambda / rho; // value for the service parameter (Exponential)
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
+    let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
+    let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
+    let gamma = cramer_root(&inter_arrival_param, &service_param);
+    let retrials = 4;
+
+    let simulations = (0..simulations_number)
+        .into_par_iter()
+        .map(|_| exp_service_time(arrivals_number, lambda, mu, theta))
+        .collect::<Vec<_>>();
+    let avg_stay = simulations.iter().map(|s| s.avg_stay()).calculate_mean();
+    // the number of clients of every cycle, but the last one (as for the rewards)
+    let jobs_by_cycle = simulations
+        .iter()
+        .flat_map(|s| {
+            let cycles = s.regeneration_cycles();
+            cycles[..cycles.len() - 1]
+                .iter()
+                .map(|c| c.len() as f64)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    println!(
+        "rho = {}: 𝔼[S] = {} ; Cramér root γ = {}",
+        rho, avg_stay, gamma
+    );
+    for &multiple in multiples.iter() {
+        let x = multiple * avg_stay;
+
+        let crude = crude_rare_event_estimate(
             &simulations
                 .iter()
-                .map(|s| s.probability_server_setup())
+                .flat_map(|s| s.weighted_stay_tail_by_cycle(x))
                 .collect::<Vec<_>>(),
+            &jobs_by_cycle,
         );
-        if rho > 0.5 && d == None {
-            d = Some(
-                simulations
-                    .iter()
-                    .map(|s: &Simulation| s.second_order_moment_waiting_delay())
-                    .calculate_mean(),
-            );
-            print!(
-                "E[W²] = sum( (W_i - E[W])² ) = {} ; for rho = {}",
-                d.unwrap(),
-                rho
+        let importance_sampling = rare_event_estimate(
+            &(0..simulations_number)
+                .into_par_iter()
+                .flat_map_iter(|_| {
+                    tilted_queue(
+                        tilted_arrivals_number,
+                        &inter_arrival_param,
+                        &service_param,
+                        &warming_up_param,
+                        gamma,
+                        x,
+                    )
+                    .weighted_stay_tail_by_cycle(x)
+                })
+                .collect::<Vec<_>>(),
+            &jobs_by_cycle,
+        );
+        let levels = (x * gamma / (retrials as f64).ln()).ceil() as usize;
+        let splitting = Splitting::evenly_spaced(x, levels, retrials);
+        let restart = rare_event_estimate(
+            &(0..simulations_number)
+                .into_par_iter()
+                .flat_map_iter(|_| {
+                    restart_queue(
+                        cycles / simulations_number,
+                        &inter_arrival_param,
+                        &service_param,
+                        &warming_up_param,
+                        &splitting,
+                        x,
+                    )
+                })
+                .collect::<Vec<_>>(),
+            &jobs_by_cycle,
+        );
+
+        for (name, estimate) in [
+            ("crude Monte Carlo", crude),
+            ("importance sampling", importance_sampling),
+            (&format!("RESTART ({} levels)", levels), restart),
+        ] {
+            println!(
+                "rho = {}: P(S > {} 𝔼[S]) by {} = {:e} ; CI = [{:e}, {:e}] ; relative error = {} ; normalised = {} ; {} hits in {} cycles",
+                rho,
+                multiple,
+                name,
+                estimate.estimate.point,
+                estimate.estimate.lower,
+                estimate.estimate.upper,
+                estimate.relative_error,
+                estimate.normalised_relative_error(),
+                estimate.hits,
+                estimate.cycles
             );
         }
-
-        values.push(Data {
-            rho,
-            lambda,
-            mu: None,
-            k: Some(k),
-            beta: Some(beta),
-            theta,
-            avg_stay_time,
-            corrected_variance_avg_stay: corrected_standard_deviation_avg_stay,
-            probability_p_off,
-            corrected_variance_p_off: corrected_standard_deviation_p_off,
-            probability_p_setup,
-            corrected_variance_p_setup: corrected_standard_deviation_p_setup,
-            n_simulations: simulations_by_batch,
-        });
     }
-    let _ = print_avg_stay_graph_for_erlang(&values);
-    let _ = print_p_setup_graph(&values, "images/erlang_p_setup_by_rho");
-    let _ = print_p_off_graph(&values, "images/erlang_p_off_by_rho");
 }
 
 /// Wrapper for the queue function, M/G/1 with service time distributed as exponential
 ///
-/// entry: poisson of parameter lambda
+/// entry: poisson process of rate lambda (exponential inter-arrival times)
 /// service: exponential of parameter mu
 /// warmup: exponential of parameter theta
 /// n people are allowed to enter the queue
@@ -238,7 +2660,8 @@ fn launch_erlang(simulations_by_batch: usize, arrivals_number: usize, theta: f64
 /// Arguments:
 ///
 /// * `n`: The number of people that will enter the queue
-/// * `lambda`: The parameter used for the Poisson distribution (arrival)
+/// * `lambda`: The rate of the Poisson process (arrival), i.e. of the Exponential inter-arrival
+///   times
 /// * `mu`: The parameter used for the Exponential distribution (service)
 /// * `theta`: The parameter used for the Exponential distribution (warmup)
 ///
@@ -249,7 +2672,7 @@ fn exp_service_time(n: usize, lambda: f64, mu: f64, theta: f64) -> Simulation {
     // sanity check : rho must always be less than one
     assert!(lambda / mu < 1.0);
 
-    let inter_arrival_param = Parameter::Poisson(PoissonParameter { lambda });
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
     let service_param = Parameter::Exponential(ExponentialParameter { lambda: mu });
     let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
 
@@ -258,7 +2681,7 @@ fn exp_service_time(n: usize, lambda: f64, mu: f64, theta: f64) -> Simulation {
 
 /// Wrapper for the queue function, M/G/1 with service time distributed as erlang.
 ///
-/// entry: poisson of parameter lambda
+/// entry: poisson process of rate lambda (exponential inter-arrival times)
 /// service: erlang of parameters k, beta (beta is scale)
 /// warmup: exponential of parameter theta
 /// n people are allowed to enter the queue
@@ -266,7 +2689,8 @@ fn exp_service_time(n: usize, lambda: f64, mu: f64, theta: f64) -> Simulation {
 /// Arguments:
 ///
 /// * `n`: The number of people that will enter the queue
-/// * `lambda`: The parameter used for the Poisson distribution (arrival)
+/// * `lambda`: The rate of the Poisson process (arrival), i.e. of the Exponential inter-arrival
+///   times
 /// * `theta`: The parameter used for the Exponential distribution (warmup)
 /// * `k`: One of the two parameters (shape) used for the Erlang distribution (service)
 /// * `beta`: The other parameter (scale) used for the Erlang distribution (service)
@@ -277,7 +2701,7 @@ fn exp_service_time(n: usize, lambda: f64, mu: f64, theta: f64) -> Simulation {
 fn erlang_service_time(n: usize, lambda: f64, theta: f64, k: usize, beta: f64) -> Simulation {
     assert!(lambda * k as f64 * beta < 1.0);
 
-    let inter_arrival_param = Parameter::Poisson(PoissonParameter { lambda });
+    let inter_arrival_param = Parameter::Exponential(ExponentialParameter { lambda });
     let service_param = Parameter::Erlang(ErlangParameter { k, beta });
     let warming_up_param = Parameter::Exponential(ExponentialParameter { lambda: theta });
 
@@ -322,42 +2746,90 @@ fn queue(
     // we can calculate them all here.
     let service_times: Vec<_> = generator(&service_param, n);
 
-    let mut warmups: Vec<f64> = Vec::new();
-    let mut delays: Vec<f64> = Vec::new();
-    let mut nap_times: Vec<f64> = Vec::new();
-
-    let mut previous_client_departure = 0.0;
-    for (&client_arrival, &service_time) in incoming_clients.iter().zip(&service_times) {
-        let delay: f64;
-        let nap_time: f64;
-        let warmup: f64;
-
-        // If the new client arrive *after* the departure of the last one present in the node, the
-        // server has taken a break and needs to be warmed up.
-        // If the new client arrive *before* the departure of the last one present in the node, this
-        // client has to wait for the server. The server will not take a break.
-        if client_arrival > previous_client_departure {
-            delay = 0.0;
-            nap_time = client_arrival - previous_client_departure; // the nap time is the time that the server spent OFF
-            warmup = generator(&warming_up_param, 1)[0]; // asking for only one draw so take the first value of the vector.
-        } else {
-            delay = previous_client_departure - client_arrival;
-            nap_time = 0.0;
-            warmup = 0.0;
-        }
-        warmups.push(warmup);
-        delays.push(delay); // warning, the real time waited by clients are `delays.iter().zip(warmups).map(|(d, w)| d+w).collect()`
-        nap_times.push(nap_time);
-
-        // the nap time is only relevant to the server, so it's not counted in the sojourn time
-        previous_client_departure = client_arrival + delay + warmup + service_time;
-    }
-
-    Ok(Simulation::new(
+    Ok(single_server(
         incoming_clients,
-        delays,
-        warmups,
         service_times,
-        nap_times,
+        &warming_up_param,
     ))
 }
+
+/// The `antithetic_queue` function simulates two antithetic runs of `queue`: the inter-arrival,
+/// service and warmup times of the second run are drawn from the complements of the uniform
+/// numbers of the first one, client by client.
+///
+/// Each run alone behaves exactly like `queue`, but their metrics are negatively correlated, so
+/// their average is more precise than the average of two independent runs.
+///
+/// Arguments:
+///
+/// * `n`: The number of jobs that will enter the queue.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+///
+/// Returns:
+///
+/// The two `Simulation`s of the pair.
+fn antithetic_queue(
+    n: usize,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+) -> (Simulation, Simulation) {
+    let arrival_times = |inter_arrival_times: Vec<f64>| {
+        let mut accumulator = 0.0;
+        inter_arrival_times
+            .iter()
+            .map(|x| {
+                accumulator += x;
+                accumulator
+            })
+            .collect::<Vec<_>>()
+    };
+    let (inter_arrivals, antithetic_inter_arrivals) = antithetic_generator(inter_arrival_param, n);
+    let (services, antithetic_services) = antithetic_generator(service_param, n);
+    let (warmups, antithetic_warmups) = antithetic_generator(warming_up_param, n);
+
+    (
+        single_server_with_warmups(arrival_times(inter_arrivals), services, &warmups),
+        single_server_with_warmups(
+            arrival_times(antithetic_inter_arrivals),
+            antithetic_services,
+            &antithetic_warmups,
+        ),
+    )
+}
+
+/// The `queue_until` function is the fixed time horizon version of `queue`.
+///
+/// Instead of stopping after `n` arrivals, the clients keep coming until the end time `horizon`, and
+/// everything that would happen after it is cut. This way the total time is not random anymore and
+/// the time averages are all computed over the same horizon.
+///
+/// Arguments:
+///
+/// * `horizon`: The time at which the simulation ends.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+///
+/// Returns:
+///
+/// A `Simulation` "object" (it is a struct really) cut at the horizon.
+fn queue_until(
+    horizon: f64,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+) -> Result<Simulation, Box<dyn Error>> {
+    let mut incoming_clients: Vec<f64> = Vec::new();
+    let mut accumulator = generator(inter_arrival_param, 1)[0];
+    while accumulator <= horizon {
+        incoming_clients.push(accumulator);
+        accumulator += generator(inter_arrival_param, 1)[0];
+    }
+
+    let service_times: Vec<_> = generator(service_param, incoming_clients.len());
+
+    Ok(single_server(incoming_clients, service_times, warming_up_param).with_horizon(horizon))
+}
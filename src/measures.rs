@@ -1,6 +1,12 @@
 //! The `measures.rs` file contains the `Data` struct, dump of all metrics calculated for a given
 //! rho. It also contains the `Mean` trait, where is defined the `calculate_mean()` function.
-//! Finally it's also here that can be found all the function that compute theoretical values & graphs
+//! Finally it's also here that can be found all the statistical tools used to compare the simulations
+//! with the theoretical values of `theory.rs`.
+use crate::ctmc::solve_linear_system;
+use crate::simulation::{Input, Simulation};
+use crate::theory::{Moments, SetupQueue};
+use crate::variables::{ErlangParameter, ExponentialParameter, Parameter};
+use std::error::Error;
 
 /// The `Data` struct is a big dump of all the calculated measures from the simulation
 /// that may be useful during the print of the graph.
@@ -18,12 +24,40 @@
 /// * `theta`: Exponential parameter, for warmup time.
 /// * `avg_stay_time`: The average time spent in the system.
 /// * `corrected_variance_avg_stay`: The calculated and corrected variance of the time spent in the
-/// system.
+///   system.
 /// * `probability_p_off`: Probability that a client finds a server off on arrival.
 /// * `corrected_variance_p_off`: The calculated and corrected variance of the probability p_off.
 /// * `probability_p_setup`: Probability that a client finds a server warming up on arrival.
 /// * `corrected_variance_p_setup`: The calculated and corrected variance of the probability p_setup.
 /// * `n_simulations`: The number of simulations made to obtain all the previous data.
+/// * `half_width_avg_stay`: The half-width of the confidence interval of the average stay time, that
+///   is the precision achieved on it.
+/// * `half_width_p_off`: The half-width of the confidence interval of the probability p_off.
+/// * `half_width_p_setup`: The half-width of the confidence interval of the probability p_setup.
+/// * `confidence_level`: The level of the confidence intervals (and thus of the hypothesis tests,
+///   at the significance level `1 - confidence_level`).
+/// * `truncation_point`: When the initial transient was deleted, the average number of jobs that
+///   were deleted from the beginning of each simulation.
+/// * `stay_percentiles`: For every level of `PERCENTILES`, the confidence interval of the
+///   percentile of the stay times of a simulation.
+/// * `waiting_percentiles`: For every level of `PERCENTILES`, the confidence interval of the
+///   percentile of the waiting times of a simulation.
+/// * `fraction_with_warmup`: The confidence interval of the fraction of the clients that paid for
+///   the warmup of the server.
+/// * `avg_stay_with_warmup`: The confidence interval of the average stay time of those clients.
+///
+///   The percentiles and this average are only known for the simulations with a completed client
+///   (with a warmup), their intervals are `ConfidenceInterval::undefined` when less than two
+///   simulations are left.
+/// * `avg_jobs_in_system`: The confidence interval of the time average of the number of clients in
+///   the system.
+/// * `avg_jobs_in_queue`: The confidence interval of the time average of the number of clients
+///   waiting in the queue.
+/// * `littles_law_discrepancy`: The biggest distance between the gap of Little's law and the
+///   bounds given by the edges of the window over the simulations, a sanity check that should be
+///   `0` up to rounding errors.
+/// * `gradients`: The sensitivities of the average stay and of p_off to the rates of the laws, when
+///   they were asked for (see `with_gradients`).
 #[derive(Copy, Clone)]
 pub struct Data {
     pub rho: f64,
@@ -39,6 +73,376 @@ pub struct Data {
     pub probability_p_setup: f64,
     pub corrected_variance_p_setup: f64,
     pub n_simulations: usize,
+    pub half_width_avg_stay: f64,
+    pub half_width_p_off: f64,
+    pub half_width_p_setup: f64,
+    pub confidence_level: f64,
+    pub truncation_point: Option<f64>,
+    pub stay_percentiles: [ConfidenceInterval; 5],
+    pub waiting_percentiles: [ConfidenceInterval; 5],
+    pub fraction_with_warmup: ConfidenceInterval,
+    pub avg_stay_with_warmup: ConfidenceInterval,
+    pub avg_jobs_in_system: ConfidenceInterval,
+    pub avg_jobs_in_queue: ConfidenceInterval,
+    pub littles_law_discrepancy: f64,
+    pub gradients: Option<Gradients>,
+}
+
+/// The levels of the percentiles of the stay and waiting times kept in `Data`.
+pub const PERCENTILES: [f64; 5] = [0.5, 0.9, 0.95, 0.99, 0.999];
+
+impl Data {
+    /// Compute the averages and corrected standard deviations of the metrics over some simulations.
+    ///
+    /// The parameters that are specific to one experiment (`mu`, `k` and `beta`) are left to `None`,
+    /// they can be filled with the struct update syntax:
+    /// `Data { mu: Some(mu), ..Data::from_simulations(rho, lambda, theta, &simulations) }`.
+    pub fn from_simulations(rho: f64, lambda: f64, theta: f64, simulations: &[Simulation]) -> Self {
+        let avg_stays = simulations.iter().map(|s| s.avg_stay()).collect::<Vec<_>>();
+        let p_offs = simulations
+            .iter()
+            .map(|s| s.probability_server_off())
+            .collect::<Vec<_>>();
+        let p_setups = simulations
+            .iter()
+            .map(|s| s.probability_server_setup())
+            .collect::<Vec<_>>();
+
+        let avg_stay_time = avg_stays.iter().calculate_mean();
+        let probability_p_off = p_offs.iter().calculate_mean();
+        let probability_p_setup = p_setups.iter().calculate_mean();
+
+        let deviation_avg_stay = corrected_standard_deviation(avg_stay_time, &avg_stays);
+        let deviation_p_off = corrected_standard_deviation(probability_p_off, &p_offs);
+        let deviation_p_setup = corrected_standard_deviation(probability_p_setup, &p_setups);
+        let n = simulations.len();
+        let half_width = |deviation: f64| {
+            ConfidenceInterval::new(0.0, deviation, n, DEFAULT_CONFIDENCE_LEVEL).half_width()
+        };
+        // some metrics are only known for some simulations (e.g. the ones with a completed client)
+        let interval = |samples: Vec<f64>| {
+            if samples.len() < 2 {
+                ConfidenceInterval::undefined(samples.len(), DEFAULT_CONFIDENCE_LEVEL)
+            } else {
+                confidence_interval(&samples, DEFAULT_CONFIDENCE_LEVEL)
+            }
+        };
+
+        Self {
+            rho,
+            lambda,
+            mu: None,
+            k: None,
+            beta: None,
+            theta,
+            avg_stay_time,
+            corrected_variance_avg_stay: deviation_avg_stay,
+            probability_p_off,
+            corrected_variance_p_off: deviation_p_off,
+            probability_p_setup,
+            corrected_variance_p_setup: deviation_p_setup,
+            n_simulations: simulations.len(),
+            half_width_avg_stay: half_width(deviation_avg_stay),
+            half_width_p_off: half_width(deviation_p_off),
+            half_width_p_setup: half_width(deviation_p_setup),
+            confidence_level: DEFAULT_CONFIDENCE_LEVEL,
+            truncation_point: None,
+            stay_percentiles: PERCENTILES.map(|p| {
+                interval(
+                    simulations
+                        .iter()
+                        .filter_map(|s| s.stay_percentile(p))
+                        .collect(),
+                )
+            }),
+            waiting_percentiles: PERCENTILES.map(|p| {
+                interval(
+                    simulations
+                        .iter()
+                        .filter_map(|s| s.waiting_percentile(p))
+                        .collect(),
+                )
+            }),
+            fraction_with_warmup: interval(
+                simulations
+                    .iter()
+                    .map(|s| s.fraction_with_warmup())
+                    .collect(),
+            ),
+            avg_stay_with_warmup: interval(
+                simulations
+                    .iter()
+                    .filter_map(|s| s.avg_stay_with_warmup())
+                    .collect(),
+            ),
+            avg_jobs_in_system: interval(
+                simulations.iter().map(|s| s.avg_jobs_in_system()).collect(),
+            ),
+            avg_jobs_in_queue: interval(
+                simulations.iter().map(|s| s.avg_jobs_in_queue()).collect(),
+            ),
+            littles_law_discrepancy: simulations
+                .iter()
+                .map(|s| s.littles_law_discrepancy())
+                .fold(0.0, f64::max),
+            gradients: None,
+        }
+    }
+
+    /// The theoretical M/G/1 queue with setup matching the parameters of the experiment: the
+    /// service is Exponential when `mu` is known, Erlang when `k` and `beta` are, and the setup is
+    /// Exponential of parameter `theta`.
+    ///
+    /// Returns:
+    ///
+    /// `None` when neither `mu` nor `k` and `beta` were filled, the service law being unknown.
+    pub fn setup_queue(&self) -> Option<SetupQueue> {
+        let service = match (self.mu, self.k, self.beta) {
+            (Some(mu), _, _) => Parameter::Exponential(ExponentialParameter { lambda: mu }),
+            (None, Some(k), Some(beta)) => Parameter::Erlang(ErlangParameter { k, beta }),
+            _ => return None,
+        };
+        Some(SetupQueue {
+            lambda: self.lambda,
+            service: service.moments(),
+            setup: Parameter::Exponential(ExponentialParameter { lambda: self.theta }).moments(),
+        })
+    }
+
+    /// The same data, with the confidence intervals (and the hypothesis tests) at another level.
+    pub fn with_confidence_level(self, level: f64) -> Self {
+        let half_width = |deviation: f64| {
+            ConfidenceInterval::new(0.0, deviation, self.n_simulations, level).half_width()
+        };
+        Self {
+            half_width_avg_stay: half_width(self.corrected_variance_avg_stay),
+            half_width_p_off: half_width(self.corrected_variance_p_off),
+            half_width_p_setup: half_width(self.corrected_variance_p_setup),
+            confidence_level: level,
+            stay_percentiles: self.stay_percentiles.map(|i| i.at_level(level)),
+            waiting_percentiles: self.waiting_percentiles.map(|i| i.at_level(level)),
+            fraction_with_warmup: self.fraction_with_warmup.at_level(level),
+            avg_stay_with_warmup: self.avg_stay_with_warmup.at_level(level),
+            avg_jobs_in_system: self.avg_jobs_in_system.at_level(level),
+            avg_jobs_in_queue: self.avg_jobs_in_queue.at_level(level),
+            gradients: self.gradients.map(|g| g.at_level(level)),
+            ..self
+        }
+    }
+}
+
+/// The sensitivities of the average stay and of the probability p_off to the rates of the three
+/// laws (arrival, service and warmup, in this order), each one estimated by infinitesimal
+/// perturbation analysis and by likelihood ratio.
+///
+/// Properties:
+///
+/// * `avg_stay_ipa`: The IPA confidence intervals of `∂𝔼[S]/∂r`, `None` when the law is not a scale
+///   family.
+/// * `avg_stay_lr`: The likelihood ratio confidence intervals of `∂𝔼[S]/∂r`.
+/// * `p_off_ipa`: The IPA confidence intervals of `∂P(off)/∂r`, `None` when the law is not a scale
+///   family.
+/// * `p_off_lr`: The likelihood ratio confidence intervals of `∂P(off)/∂r`.
+#[derive(Copy, Clone, Debug)]
+pub struct Gradients {
+    pub avg_stay_ipa: [Option<ConfidenceInterval>; 3],
+    pub avg_stay_lr: [ConfidenceInterval; 3],
+    pub p_off_ipa: [Option<ConfidenceInterval>; 3],
+    pub p_off_lr: [ConfidenceInterval; 3],
+}
+
+/// The inputs of the simulations, in the order of the rates of `Gradients`.
+pub const GRADIENT_INPUTS: [Input; 3] = [Input::InterArrival, Input::Service, Input::Warmup];
+
+impl Gradients {
+    /// The same gradients, with the confidence intervals at another level.
+    pub fn at_level(self, level: f64) -> Self {
+        Self {
+            avg_stay_ipa: self.avg_stay_ipa.map(|i| i.map(|i| i.at_level(level))),
+            avg_stay_lr: self.avg_stay_lr.map(|i| i.at_level(level)),
+            p_off_ipa: self.p_off_ipa.map(|i| i.map(|i| i.at_level(level))),
+            p_off_lr: self.p_off_lr.map(|i| i.at_level(level)),
+        }
+    }
+}
+
+impl Data {
+    /// The same data, with the sensitivities of the average stay and of p_off to the rates of the
+    /// laws used by the simulations.
+    ///
+    /// Arguments:
+    ///
+    /// * `simulations`: The simulations the data was computed from.
+    /// * `laws`: The laws of the inter-arrival, service and warmup times.
+    pub fn with_gradients(self, simulations: &[Simulation], laws: [&Parameter; 3]) -> Self {
+        let interval = |samples: Vec<f64>| confidence_interval(&samples, self.confidence_level);
+        let ipa = |k: usize, metric: fn((f64, f64)) -> f64| {
+            let law = laws[k];
+            if law.is_scale_family() {
+                Some(interval(
+                    simulations
+                        .iter()
+                        .map(|s| metric(s.ipa_derivatives(GRADIENT_INPUTS[k], law.rate())))
+                        .collect(),
+                ))
+            } else {
+                None
+            }
+        };
+        let lr = |k: usize, metric: fn(&Simulation) -> f64| {
+            interval(
+                simulations
+                    .iter()
+                    .map(|s| metric(s) * s.score(GRADIENT_INPUTS[k], laws[k]))
+                    .collect(),
+            )
+        };
+        Self {
+            gradients: Some(Gradients {
+                avg_stay_ipa: [0, 1, 2].map(|k| ipa(k, |(stay, _)| stay)),
+                avg_stay_lr: [0, 1, 2].map(|k| lr(k, Simulation::avg_stay)),
+                p_off_ipa: [0, 1, 2].map(|k| ipa(k, |(_, p_off)| p_off)),
+                p_off_lr: [0, 1, 2].map(|k| lr(k, Simulation::probability_server_off)),
+            }),
+            ..self
+        }
+    }
+}
+
+/// The metrics of `Data` that can be asked to reach a given precision.
+#[derive(Copy, Clone)]
+pub enum Metric {
+    AvgStay,
+    ProbabilityOff,
+    ProbabilitySetup,
+    /// The percentile of the stay times, given by its index in `PERCENTILES`.
+    StayPercentile(usize),
+    /// The percentile of the waiting times, given by its index in `PERCENTILES`.
+    WaitingPercentile(usize),
+    FractionWithWarmup,
+    AvgStayWithWarmup,
+    AvgJobsInSystem,
+    AvgJobsInQueue,
+}
+
+impl Data {
+    /// The estimated value of a metric, together with the half-width of its confidence interval.
+    pub fn estimate_of(&self, metric: Metric) -> (f64, f64) {
+        match metric {
+            Metric::AvgStay => (self.avg_stay_time, self.half_width_avg_stay),
+            Metric::ProbabilityOff => (self.probability_p_off, self.half_width_p_off),
+            Metric::ProbabilitySetup => (self.probability_p_setup, self.half_width_p_setup),
+            _ => {
+                let interval = self.confidence_interval_of(metric);
+                (interval.mean, interval.half_width())
+            }
+        }
+    }
+
+    /// The confidence interval of a metric, with its explicit bounds.
+    pub fn confidence_interval_of(&self, metric: Metric) -> ConfidenceInterval {
+        match metric {
+            Metric::StayPercentile(i) => self.stay_percentiles[i],
+            Metric::WaitingPercentile(i) => self.waiting_percentiles[i],
+            Metric::FractionWithWarmup => self.fraction_with_warmup,
+            Metric::AvgStayWithWarmup => self.avg_stay_with_warmup,
+            Metric::AvgJobsInSystem => self.avg_jobs_in_system,
+            Metric::AvgJobsInQueue => self.avg_jobs_in_queue,
+            _ => {
+                let (value, _) = self.estimate_of(metric);
+                ConfidenceInterval::new(
+                    value,
+                    self.standard_deviation_of(metric),
+                    self.n_simulations,
+                    self.confidence_level,
+                )
+            }
+        }
+    }
+
+    /// The corrected standard deviation of a metric over the simulations.
+    pub fn standard_deviation_of(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::AvgStay => self.corrected_variance_avg_stay,
+            Metric::ProbabilityOff => self.corrected_variance_p_off,
+            Metric::ProbabilitySetup => self.corrected_variance_p_setup,
+            _ => self.confidence_interval_of(metric).standard_deviation,
+        }
+    }
+
+    /// Test the hypothesis that a metric is equal to its theoretical value.
+    pub fn t_test(&self, metric: Metric, theoretical: f64) -> TTest {
+        let interval = self.confidence_interval_of(metric);
+        t_test(
+            interval.mean,
+            theoretical,
+            interval.standard_deviation,
+            interval.n,
+        )
+    }
+
+    /// Check if a metric is compatible with its theoretical value, at the significance level
+    /// `1 - confidence_level`.
+    pub fn verifies(&self, metric: Metric, theoretical: f64) -> bool {
+        self.t_test(metric, theoretical)
+            .is_accepted(1.0 - self.confidence_level)
+    }
+}
+
+impl Metric {
+    /// The value of the metric for a single simulation, `None` when the simulation has none (no
+    /// client paid for the warmup).
+    pub fn value(&self, simulation: &Simulation) -> Option<f64> {
+        match self {
+            Metric::AvgStay => Some(simulation.avg_stay()),
+            Metric::ProbabilityOff => Some(simulation.probability_server_off()),
+            Metric::ProbabilitySetup => Some(simulation.probability_server_setup()),
+            Metric::StayPercentile(i) => simulation.stay_percentile(PERCENTILES[*i]),
+            Metric::WaitingPercentile(i) => simulation.waiting_percentile(PERCENTILES[*i]),
+            Metric::FractionWithWarmup => Some(simulation.fraction_with_warmup()),
+            Metric::AvgStayWithWarmup => simulation.avg_stay_with_warmup(),
+            Metric::AvgJobsInSystem => Some(simulation.avg_jobs_in_system()),
+            Metric::AvgJobsInQueue => Some(simulation.avg_jobs_in_queue()),
+        }
+    }
+}
+
+/// The sequential stopping rule: simulations are added by steps until every chosen metric is
+/// precise enough, or until the budget is spent.
+///
+/// Properties:
+///
+/// * `metrics`: The metrics that must reach the precision.
+/// * `relative_precision`: A metric is precise enough when the half-width of its confidence
+///   interval is below this fraction of its value.
+/// * `absolute_precision`: A metric is also precise enough when the half-width of its confidence
+///   interval is below this value (useful for the probabilities close to `0`).
+/// * `initial_simulations`: The number of simulations launched before the first check (at least
+///   `2`, for the confidence intervals).
+/// * `simulations_by_step`: The number of simulations added between two checks (at least `1`).
+/// * `max_simulations`: The budget, no more simulations are launched once it is reached (at least
+///   `2`).
+/// * `confidence_level`: The level of the confidence intervals whose half-widths are checked.
+pub struct StoppingRule {
+    pub metrics: Vec<Metric>,
+    pub relative_precision: Option<f64>,
+    pub absolute_precision: Option<f64>,
+    pub initial_simulations: usize,
+    pub simulations_by_step: usize,
+    pub max_simulations: usize,
+    pub confidence_level: f64,
+}
+
+impl StoppingRule {
+    /// Check if every chosen metric reached one of the targeted precisions.
+    pub fn is_satisfied(&self, data: &Data) -> bool {
+        self.metrics.iter().all(|&metric| {
+            let (value, half_width) = data.estimate_of(metric);
+            self.relative_precision
+                .is_some_and(|r| half_width <= r * value.abs())
+                || self.absolute_precision.is_some_and(|a| half_width <= a)
+        })
+    }
 }
 
 /// I created this trait because I had enough of calculating the mean by hand (sum / length)
@@ -59,65 +463,1483 @@ where
     }
 }
 
-pub fn theoretic_stay_avg_exp(rho: f64, mu: f64, theta: f64) -> f64 {
-    (1.0 / mu) / (1.0 - rho) + 1.0 / theta
+pub fn corrected_standard_deviation(avg: f64, data: &[f64]) -> f64 {
+    f64::sqrt(1.0 / (data.len() - 1) as f64 * data.iter().map(|d| (d - avg).powi(2)).sum::<f64>())
 }
 
-pub fn theoretic_p_off_exp(rho: f64, lambda: f64, theta: f64) -> f64 {
-    (1.0 - rho) * (1.0 / lambda) / (1.0 / lambda + 1.0 / theta)
+/// The Student statistic of the hypothesis "the mean of the `n` samples is `theoretical_avg`".
+/// The standard error of the mean is `standard_deviation / √n`.
+pub fn test_statistic(avg: f64, theoretical_avg: f64, standard_deviation: f64, n: usize) -> f64 {
+    (avg - theoretical_avg) / (standard_deviation / f64::sqrt(n as f64))
 }
 
-pub fn theoretic_p_setup_exp(rho: f64, lambda: f64, theta: f64) -> f64 {
-    (1.0 - rho) * (1.0 / theta) / (1.0 / lambda + 1.0 / theta)
+/// The confidence level used when none is chosen.
+pub const DEFAULT_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// The biggest distance between the gap of Little's law and its bounds accepted for a simulation,
+/// in clients (see `Simulation::littles_law_discrepancy`). The gap is always within its bounds,
+/// so only the rounding errors are tolerated: both sides are sums of one term by client or event,
+/// each of them of the order of `N(t)` and exact up to `f64::EPSILON` relatively, so the errors of
+/// runs of up to millions of clients stay far below `1e-6`.
+pub const LITTLES_LAW_TOLERANCE: f64 = 1e-6;
+
+/// The logarithm of the gamma function, with the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| {
+            acc + c / (x + i as f64 + 1.0)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
 }
 
-pub fn theoretic_p_off_gen(rho: f64, lambda: f64, expectation_start: f64) -> f64 {
-    (1.0 - rho) * (1.0 - lambda) / (1.0 / lambda + expectation_start)
+/// The continued fraction used by the regularized incomplete beta function (modified Lentz).
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < tiny {
+        d = tiny;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        d = if d.abs() < tiny { 1.0 / tiny } else { 1.0 / d };
+        c = 1.0 + aa / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        h *= d * c;
+
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        d = if d.abs() < tiny { 1.0 / tiny } else { 1.0 / d };
+        c = 1.0 + aa / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    h
 }
 
-pub fn theoretic_p_setup_gen(rho: f64, lambda: f64, expectation_start: f64) -> f64 {
-    (1.0 - rho) * expectation_start / (1.0 / lambda + expectation_start)
+/// The regularized incomplete beta function `I_x(a, b)`.
+pub fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// The regularized lower incomplete gamma function `P(a, x)`, with its series when `x < a + 1` and
+/// its continued fraction otherwise.
+pub fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let front = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        front * sum
+    } else {
+        // modified Lentz for the continued fraction of Q(a, x)
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        1.0 - front * h
+    }
 }
 
-/// This function gave me headache.
-/// I tried with the function moment generator instead of just using variance but with no luck.
-pub fn theoretic_stay_avg_erlang(lambda: f64, theta: f64, rho: f64, k: usize, beta: f64) -> f64 {
-    assert!(1.0 < (1.0 / beta));
-    // e_b = 𝔼[B]
-    let _e_b = 1.0 / ((1.0 - beta).powi(k as i32)); // with moment generator
-    let e_b = k as f64 * beta; // mean
+/// The cumulative distribution function of the chi-square law with `degrees_of_freedom` degrees.
+pub fn chi_square_cdf(x: f64, degrees_of_freedom: f64) -> f64 {
+    regularized_lower_gamma(degrees_of_freedom / 2.0, x / 2.0)
+}
 
-    // e_t = 𝔼[T]
-    let _e_t = 1.0 / (1.0 - 1.0 / theta); // with moment generator
-    let e_t = 1.0 / theta; // mean
+/// The cumulative distribution function of the Student law with `degrees_of_freedom` degrees.
+pub fn student_cdf(t: f64, degrees_of_freedom: f64) -> f64 {
+    let x = degrees_of_freedom / (degrees_of_freedom + t * t);
+    let tail = 0.5 * regularized_incomplete_beta(degrees_of_freedom / 2.0, 0.5, x);
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
 
-    // e_bb = 𝔼[B²]
-    let _e_bb = k as f64 / lambda / lambda; // variance
-    let e_bb = 1.0 / ((1.0 - 2.0 * beta).powi(k as i32)); // with moment generator
+/// The `p`-quantile of the Student law with `degrees_of_freedom` degrees, found by bisection.
+/// With many degrees of freedom, it tends to the quantile of the standard normal law.
+pub fn student_quantile(p: f64, degrees_of_freedom: f64) -> f64 {
+    assert!(0.0 < p && p < 1.0);
+    let (mut low, mut high) = (-1.0, 1.0);
+    while student_cdf(low, degrees_of_freedom) > p {
+        low *= 2.0;
+    }
+    while student_cdf(high, degrees_of_freedom) < p {
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if student_cdf(middle, degrees_of_freedom) < p {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
 
-    // e_tt = 𝔼[T²]
-    let _e_tt = theta * theta; // variance
-    let e_tt = 1.0 / (1.0 - 2.0 / theta); // with moment generator
+/// A confidence interval of a mean, computed from independent samples.
+///
+/// Properties:
+///
+/// * `mean`: The mean of the samples.
+/// * `standard_deviation`: The corrected standard deviation of the samples.
+/// * `n`: The number of samples.
+/// * `level`: The confidence level of the interval (e.g. `0.95`).
+/// * `lower`: The lower bound of the interval.
+/// * `upper`: The upper bound of the interval.
+#[derive(Copy, Clone, Debug)]
+pub struct ConfidenceInterval {
+    pub mean: f64,
+    pub standard_deviation: f64,
+    pub n: usize,
+    pub level: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
 
-    let e_rb = e_bb / 2.0 / e_b;
-    let e_rt = e_tt / 2.0 / e_t;
+impl ConfidenceInterval {
+    /// Build the interval of a mean from the standard deviation of the `n` samples, with the
+    /// Student quantile at `n - 1` degrees of freedom.
+    pub fn new(mean: f64, standard_deviation: f64, n: usize, level: f64) -> Self {
+        assert!(n > 1);
+        let half_width = student_quantile(1.0 - (1.0 - level) / 2.0, (n - 1) as f64)
+            * standard_deviation
+            / f64::sqrt(n as f64);
+        Self {
+            mean,
+            standard_deviation,
+            n,
+            level,
+            lower: mean - half_width,
+            upper: mean + half_width,
+        }
+    }
 
-    // e_w = 𝔼[W]
-    let e_w = rho * e_rb / (1.0 - rho)
-        + (1.0 / lambda) / (1.0 / lambda + e_t) * e_t
-        + e_t / (1.0 / lambda + e_t) * e_rt;
+    /// Half of the width of the interval, that is the precision of the mean.
+    pub fn half_width(&self) -> f64 {
+        (self.upper - self.lower) / 2.0
+    }
 
-    e_w + e_b
+    /// The same interval, at another level.
+    pub fn at_level(self, level: f64) -> Self {
+        if self.n < 2 {
+            return Self { level, ..self };
+        }
+        ConfidenceInterval::new(self.mean, self.standard_deviation, self.n, level)
+    }
+
+    /// The interval of a mean that cannot be estimated, from less than two samples: its mean and
+    /// bounds are `NaN`, so it is never precise enough for a `StoppingRule`.
+    pub fn undefined(n: usize, level: f64) -> Self {
+        Self {
+            mean: f64::NAN,
+            standard_deviation: f64::NAN,
+            n,
+            level,
+            lower: f64::NAN,
+            upper: f64::NAN,
+        }
+    }
+
+    /// Check if a value is inside the interval.
+    pub fn contains(&self, value: f64) -> bool {
+        self.lower <= value && value <= self.upper
+    }
 }
 
-pub fn corrected_standard_deviation(avg: f64, data: &[f64]) -> f64 {
-    f64::sqrt(1.0 / (data.len() - 1) as f64 * data.iter().map(|d| (d - avg).powi(2)).sum::<f64>())
+/// Compute the confidence interval of the mean of some samples, at the given level.
+pub fn confidence_interval(samples: &[f64], level: f64) -> ConfidenceInterval {
+    let mean = samples.iter().calculate_mean();
+    ConfidenceInterval::new(
+        mean,
+        corrected_standard_deviation(mean, samples),
+        samples.len(),
+        level,
+    )
 }
 
-pub fn test_statistic(avg: f64, theoretical_avg: f64, standard_deviation: f64, n: usize) -> f64 {
-    (avg - theoretical_avg) / (standard_deviation / f64::sqrt((n - 1) as f64))
+/// Estimate a transient curve by ensemble averaging: at every time of the grid, the confidence
+/// interval of the mean of a value over independent replications that all start in the same
+/// state.
+///
+/// Arguments:
+///
+/// * `simulations`: The replications.
+/// * `times`: The time grid.
+/// * `value`: The value of a replication at a given time (e.g. `N(t)`, or `1` if the server is
+///   off).
+/// * `level`: The confidence level.
+pub fn ensemble_curve<F: Fn(&Simulation, f64) -> f64>(
+    simulations: &[Simulation],
+    times: &[f64],
+    value: F,
+    level: f64,
+) -> Vec<ConfidenceInterval> {
+    times
+        .iter()
+        .map(|&t| {
+            let values = simulations
+                .iter()
+                .map(|simulation| value(simulation, t))
+                .collect::<Vec<_>>();
+            confidence_interval(&values, level)
+        })
+        .collect()
 }
 
-pub fn is_inside_interval(value: f64, threshold: f64) -> bool {
-    (-threshold) <= value && value <= threshold
+/// An estimate obtained with a variance reduction technique, compared to the plain estimate
+/// obtained from as many simulations.
+///
+/// Properties:
+///
+/// * `plain`: The confidence interval without variance reduction.
+/// * `reduced`: The confidence interval with variance reduction.
+/// * `factor`: The variance reduction factor, the variance of the plain estimator divided by the
+///   variance of the reduced one (at equal number of simulations). Above `1`, the technique helped:
+///   the plain estimator would need `factor` times more simulations to reach the same precision.
+#[derive(Copy, Clone, Debug)]
+pub struct VarianceReduction {
+    pub plain: ConfidenceInterval,
+    pub reduced: ConfidenceInterval,
+    pub factor: f64,
+}
+
+/// Estimate a mean from antithetic pairs: the two values of a pair come from simulations fed with
+/// antithetic random numbers, the mean of the pair is a single sample.
+///
+/// The plain estimator would average the `2n` values as if they were independent, its variance is
+/// estimated from the variance of all the values.
+pub fn antithetic_estimate(pairs: &[(f64, f64)], level: f64) -> VarianceReduction {
+    let values = pairs.iter().flat_map(|&(a, b)| [a, b]).collect::<Vec<_>>();
+    let plain = confidence_interval(&values, level);
+    let reduced = confidence_interval(
+        &pairs
+            .iter()
+            .map(|&(a, b)| (a + b) / 2.0)
+            .collect::<Vec<_>>(),
+        level,
+    );
+    VarianceReduction {
+        plain,
+        reduced,
+        factor: (plain.standard_deviation.powi(2) / values.len() as f64)
+            / (reduced.standard_deviation.powi(2) / pairs.len() as f64),
+    }
+}
+
+/// Estimate a mean with control variates: quantities of the simulations whose expectation is
+/// known (e.g. the average service time), and which are correlated with the samples.
+///
+/// Every sample `Y` is corrected into `Y - β (C - 𝔼[C])`, where the coefficients `β` minimise the
+/// variance of the corrected samples (the least squares regression of `Y` on the controls). The
+/// coefficients are estimated on the same samples, which adds a small bias when `n` is small.
+///
+/// Arguments:
+///
+/// * `samples`: The samples, one by simulation.
+/// * `controls`: For every control, its value for every simulation.
+/// * `means`: The known expectation of every control.
+/// * `level`: The level of the confidence intervals.
+pub fn control_variates_estimate(
+    samples: &[f64],
+    controls: &[Vec<f64>],
+    means: &[f64],
+    level: f64,
+) -> VarianceReduction {
+    assert_eq!(controls.len(), means.len());
+    let centred = |values: &[f64]| {
+        let mean = values.iter().calculate_mean();
+        values.iter().map(|v| v - mean).collect::<Vec<_>>()
+    };
+    let y = centred(samples);
+    let c = controls.iter().map(|c| centred(c)).collect::<Vec<_>>();
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+
+    // the normal equations of the regression
+    let covariances = c
+        .iter()
+        .map(|a| c.iter().map(|b| dot(a, b)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let beta = solve_linear_system(
+        &covariances,
+        &c.iter().map(|a| dot(a, &y)).collect::<Vec<_>>(),
+    );
+
+    let corrected = (0..samples.len())
+        .map(|i| {
+            samples[i]
+                - (0..controls.len())
+                    .map(|k| beta[k] * (controls[k][i] - means[k]))
+                    .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+    let plain = confidence_interval(samples, level);
+    let reduced = confidence_interval(&corrected, level);
+    VarianceReduction {
+        plain,
+        reduced,
+        factor: (plain.standard_deviation / reduced.standard_deviation).powi(2),
+    }
+}
+
+/// Estimate the difference of the means of two systems simulated with common random numbers: the
+/// `i`-th samples of both systems come from the same random numbers, their difference is a single
+/// sample.
+///
+/// The plain estimator would simulate the two systems independently, the variance of its
+/// difference being the sum of the two variances.
+pub fn common_random_numbers_estimate(
+    first: &[f64],
+    second: &[f64],
+    level: f64,
+) -> VarianceReduction {
+    assert_eq!(first.len(), second.len());
+    let (a, b) = (
+        confidence_interval(first, level),
+        confidence_interval(second, level),
+    );
+    let independent_deviation =
+        (a.standard_deviation.powi(2) + b.standard_deviation.powi(2)).sqrt();
+    let plain = ConfidenceInterval::new(a.mean - b.mean, independent_deviation, first.len(), level);
+    let reduced = confidence_interval(
+        &first
+            .iter()
+            .zip(second)
+            .map(|(a, b)| a - b)
+            .collect::<Vec<_>>(),
+        level,
+    );
+    VarianceReduction {
+        plain,
+        reduced,
+        factor: (plain.standard_deviation / reduced.standard_deviation).powi(2),
+    }
+}
+
+/// The result of a two-sided Student test.
+///
+/// Properties:
+///
+/// * `statistic`: The value of the Student statistic.
+/// * `degrees_of_freedom`: The degrees of freedom of the Student law.
+/// * `p_value`: The probability, under the hypothesis, to observe a statistic at least as far from
+///   `0`. The hypothesis is rejected at the significance level `α` when `p_value < α`.
+#[derive(Copy, Clone, Debug)]
+pub struct TTest {
+    pub statistic: f64,
+    pub degrees_of_freedom: f64,
+    pub p_value: f64,
+}
+
+impl TTest {
+    /// Check if the hypothesis holds at the significance level `alpha`.
+    pub fn is_accepted(&self, alpha: f64) -> bool {
+        self.p_value >= alpha
+    }
+}
+
+/// Test the hypothesis "the mean of the `n` samples is `theoretical_avg`" with a Student test.
+pub fn t_test(avg: f64, theoretical_avg: f64, standard_deviation: f64, n: usize) -> TTest {
+    let statistic = test_statistic(avg, theoretical_avg, standard_deviation, n);
+    let degrees_of_freedom = (n - 1) as f64;
+    TTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: 2.0 * (1.0 - student_cdf(statistic.abs(), degrees_of_freedom)),
+    }
+}
+
+/// Compute the `p`-quantile (`p` in `[0, 1]`) of some samples, with a linear interpolation between
+/// the two closest order statistics.
+pub fn quantile(samples: &[f64], p: f64) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted_quantile(&sorted, p)
+}
+
+/// Same as `quantile`, for samples that are already sorted in increasing order.
+pub fn sorted_quantile(sorted: &[f64], p: f64) -> f64 {
+    assert!(!sorted.is_empty());
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// Split the samples into `bins` bins of equal width, between the smallest and the biggest sample.
+///
+/// Returns:
+///
+/// For every bin, its lower bound and the proportion of the samples that fell inside.
+pub fn histogram(samples: &[f64], bins: usize) -> Vec<(f64, f64)> {
+    assert!(bins > 0);
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let min = samples.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = samples.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if min == max {
+        return vec![(min, 1.0)];
+    }
+
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for &s in samples {
+        let i = ((s - min) / width) as usize;
+        counts[i.min(bins - 1)] += 1;
+    }
+    counts
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (min + i as f64 * width, c as f64 / samples.len() as f64))
+        .collect()
+}
+
+/// The methods available to detect the end of the initial transient of a simulation.
+///
+/// * `Mser5`: The MSER-5 rule, applied on every series independently.
+/// * `Welch`: Welch's moving-average method, applied on the average of all the series. `window` is
+///   the half-width of the moving average, and the truncation point is the first point from which
+///   the moving average stays within the relative `tolerance` of its final level.
+#[derive(Copy, Clone)]
+pub enum TransientDeletion {
+    Mser5,
+    Welch { window: usize, tolerance: f64 },
+}
+
+/// The MSER-`batch_size` truncation point of a series (MSER-5 when `batch_size` is 5).
+///
+/// The series is first reduced to the means of batches of `batch_size` observations, then the
+/// number `d` of deleted batches is the one minimising the marginal standard error
+/// `∑_{i>d} (Y_i - Ȳ_d)² / (b - d)²`, with `d` in the first half of the `b` batches.
+///
+/// Returns:
+///
+/// The number of observations to delete.
+pub fn mser(series: &[f64], batch_size: usize) -> usize {
+    let batches = series
+        .chunks_exact(batch_size)
+        .map(|c| c.iter().calculate_mean())
+        .collect::<Vec<_>>();
+    let b = batches.len();
+    if b < 2 {
+        return 0;
+    }
+
+    let mut best = (0, f64::INFINITY);
+    for d in 0..=b / 2 {
+        let kept = &batches[d..];
+        let mean = kept.iter().calculate_mean();
+        let statistic =
+            kept.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / ((b - d) as f64).powi(2);
+        if statistic < best.1 {
+            best = (d, statistic);
+        }
+    }
+    best.0 * batch_size
+}
+
+/// Welch's moving-average truncation point of several replications of the same series.
+///
+/// The series are averaged observation by observation (over the length of the shortest one), then
+/// smoothed with a moving average of half-width `window`. The final level is the mean of the second
+/// half of the smoothed curve, and the truncation point is the first observation from which the
+/// smoothed curve never leaves the relative `tolerance` around this level.
+///
+/// Returns:
+///
+/// The number of observations to delete, at most `m - 1` (`m` being the length of the shortest
+/// series) so that one is always kept.
+pub fn welch(series: &[Vec<f64>], window: usize, tolerance: f64) -> usize {
+    let m = series.iter().map(|s| s.len()).min().unwrap_or(0);
+    if m <= 2 * window + 1 {
+        return 0;
+    }
+
+    let averages = (0..m)
+        .map(|j| series.iter().map(|s| s[j]).calculate_mean())
+        .collect::<Vec<_>>();
+    // near the beginning the window is shrunk so that it stays centered
+    let smoothed = (0..m - window)
+        .map(|j| {
+            let w = j.min(window);
+            averages[j - w..=j + w].iter().calculate_mean()
+        })
+        .collect::<Vec<_>>();
+
+    let level = smoothed[smoothed.len() / 2..].iter().calculate_mean();
+    let threshold = tolerance * level.abs();
+    smoothed
+        .iter()
+        .rposition(|y| (y - level).abs() > threshold)
+        .map_or(0, |j| (j + 1).min(m - 1))
+}
+
+/// The truncation point of every simulation, detected on the series of the stay times of the
+/// completed clients, so that the deleted jobs all left before the horizon (if any).
+pub fn truncation_points(simulations: &[Simulation], method: TransientDeletion) -> Vec<usize> {
+    match method {
+        TransientDeletion::Mser5 => simulations
+            .iter()
+            .map(|s| mser(&s.completed_stay_times(), 5))
+            .collect(),
+        TransientDeletion::Welch { window, tolerance } => {
+            let series = simulations
+                .iter()
+                .map(|s| s.completed_stay_times())
+                .collect::<Vec<_>>();
+            vec![welch(&series, window, tolerance); simulations.len()]
+        }
+    }
+}
+
+/// A point estimate together with the variance of the estimator and a confidence interval.
+///
+/// Properties:
+///
+/// * `point`: The point estimate.
+/// * `variance`: The estimated variance of the point estimator (not of the observations).
+/// * `degrees_of_freedom`: The degrees of freedom of the Student law used for the interval.
+/// * `level`: The confidence level of the interval.
+/// * `lower`: The lower bound of the confidence interval.
+/// * `upper`: The upper bound of the confidence interval.
+#[derive(Copy, Clone, Debug)]
+pub struct Estimate {
+    pub point: f64,
+    pub variance: f64,
+    pub degrees_of_freedom: f64,
+    pub level: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Estimate {
+    /// Build the estimate and its symmetric confidence interval (at the default level) from the
+    /// variance of the estimator.
+    pub fn new(point: f64, variance: f64, degrees_of_freedom: f64) -> Self {
+        Self {
+            point,
+            variance,
+            degrees_of_freedom,
+            level: DEFAULT_CONFIDENCE_LEVEL,
+            lower: point,
+            upper: point,
+        }
+        .at_level(DEFAULT_CONFIDENCE_LEVEL)
+    }
+
+    /// The same estimate, with a confidence interval at another level.
+    pub fn at_level(self, level: f64) -> Self {
+        let half_width = student_quantile(1.0 - (1.0 - level) / 2.0, self.degrees_of_freedom)
+            * self.variance.sqrt();
+        Self {
+            level,
+            lower: self.point - half_width,
+            upper: self.point + half_width,
+            ..self
+        }
+    }
+
+    /// Half of the width of the confidence interval.
+    pub fn half_width(&self) -> f64 {
+        (self.upper - self.lower) / 2.0
+    }
+}
+
+/// The non-overlapping batch means estimator of the mean of a (stationary) series.
+///
+/// The series is cut into `batches` consecutive batches of the same size (the last observations
+/// that do not fill a batch are dropped), and the batch means are considered independent. There
+/// must be at least two batches, and at least as many observations as batches.
+pub fn batch_means(series: &[f64], batches: usize) -> Estimate {
+    assert!(batches > 1);
+    let batch_size = series.len() / batches;
+    assert!(batch_size > 0);
+
+    let means = series
+        .chunks_exact(batch_size)
+        .take(batches)
+        .map(|c| c.iter().calculate_mean())
+        .collect::<Vec<_>>();
+    let point = means.iter().calculate_mean();
+    let deviation = corrected_standard_deviation(point, &means);
+    Estimate::new(
+        point,
+        deviation * deviation / batches as f64,
+        (batches - 1) as f64,
+    )
+}
+
+/// The overlapping batch means estimator of the mean of a (stationary) series.
+///
+/// Every window of `batch_size` consecutive observations is a batch, which gives a smaller
+/// variance of the variance estimator than the non-overlapping batches of the same size. The
+/// batches must be shorter than the series.
+pub fn overlapping_batch_means(series: &[f64], batch_size: usize) -> Estimate {
+    let n = series.len();
+    assert!(batch_size > 0 && batch_size < n);
+
+    let point = series.iter().calculate_mean();
+
+    // the means of all the windows are computed with a running sum
+    let mut sum = series[..batch_size].iter().sum::<f64>();
+    let mut squares = (sum / batch_size as f64 - point).powi(2);
+    for j in batch_size..n {
+        sum += series[j] - series[j - batch_size];
+        squares += (sum / batch_size as f64 - point).powi(2);
+    }
+
+    // variance parameter σ² = n m / ((n - m + 1)(n - m)) ∑ (Ȳ_j(m) - Ȳ)², and Var(Ȳ) = σ² / n
+    let m = batch_size as f64;
+    let n = n as f64;
+    let sigma_squared = n * m / ((n - m + 1.0) * (n - m)) * squares;
+    // the overlapping batches give about 1.5 times more degrees of freedom than the separate ones
+    Estimate::new(point, sigma_squared / n, 1.5 * (n / m - 1.0))
+}
+
+/// The regenerative (ratio) estimator `∑ Y_j / ∑ τ_j`, over independent and identically
+/// distributed regeneration cycles.
+///
+/// Arguments:
+///
+/// * `rewards`: The reward `Y_j` accumulated during every cycle.
+/// * `lengths`: The length `τ_j` of every cycle (a duration or a number of jobs).
+///
+/// There must be at least two cycles, to estimate the variance.
+pub fn regenerative(rewards: &[f64], lengths: &[f64]) -> Estimate {
+    assert_eq!(rewards.len(), lengths.len());
+    let k = rewards.len();
+    assert!(k > 1);
+
+    let avg_reward = rewards.iter().calculate_mean();
+    let avg_length = lengths.iter().calculate_mean();
+    let point = avg_reward / avg_length;
+
+    // s² = S_YY - 2 r S_Yτ + r² S_ττ, the variance of Y_j - r τ_j
+    let s_squared = rewards
+        .iter()
+        .zip(lengths)
+        .map(|(y, t)| (y - avg_reward - point * (t - avg_length)).powi(2))
+        .sum::<f64>()
+        / (k - 1) as f64;
+    Estimate::new(
+        point,
+        s_squared / (k as f64 * avg_length * avg_length),
+        (k - 1) as f64,
+    )
+}
+
+/// The estimation of the probability of a rare event, with the diagnostics of its precision.
+///
+/// Properties:
+///
+/// * `estimate`: The estimated probability, with its confidence interval.
+/// * `relative_error`: The standard deviation of the estimator divided by the estimated
+///   probability, infinite when the event was never observed. Above about `0.1`, the confidence
+///   interval is not to be trusted.
+/// * `hits`: The number of cycles during which the event was observed.
+/// * `cycles`: The number of cycles used by the estimation.
+#[derive(Copy, Clone, Debug)]
+pub struct RareEventEstimate {
+    pub estimate: Estimate,
+    pub relative_error: f64,
+    pub hits: usize,
+    pub cycles: usize,
+}
+
+impl RareEventEstimate {
+    fn new(estimate: Estimate, rewards: &[f64]) -> Self {
+        Self {
+            estimate,
+            relative_error: if estimate.point > 0.0 {
+                estimate.variance.sqrt() / estimate.point
+            } else {
+                f64::INFINITY
+            },
+            hits: rewards.iter().filter(|&&r| r > 0.0).count(),
+            cycles: rewards.len(),
+        }
+    }
+
+    /// The relative error multiplied by the square root of the number of cycles, that is the
+    /// relative error of a single cycle. It grows like `1 / √p` for the crude Monte Carlo, and
+    /// stays bounded (or grows slowly) for an efficient estimator when the event gets rarer.
+    pub fn normalised_relative_error(&self) -> f64 {
+        self.relative_error * (self.cycles as f64).sqrt()
+    }
+}
+
+/// Estimate the probability that a client sees a rare event, from the weighted number of clients
+/// seeing it by regeneration cycle (from importance sampling or splitting) and the number of
+/// clients by cycle of other, independent, crude simulations.
+///
+/// The probability is the ratio of the two averages, its variance is computed with the delta
+/// method.
+pub fn rare_event_estimate(rewards: &[f64], jobs_by_cycle: &[f64]) -> RareEventEstimate {
+    assert!(rewards.len() > 1 && jobs_by_cycle.len() > 1);
+    let reward = confidence_interval(rewards, DEFAULT_CONFIDENCE_LEVEL);
+    let jobs = confidence_interval(jobs_by_cycle, DEFAULT_CONFIDENCE_LEVEL);
+    let point = reward.mean / jobs.mean;
+    let relative_variance = |i: &ConfidenceInterval| {
+        if i.mean > 0.0 {
+            (i.standard_deviation / i.mean).powi(2) / i.n as f64
+        } else {
+            0.0
+        }
+    };
+    let estimate = Estimate::new(
+        point,
+        point.powi(2) * (relative_variance(&reward) + relative_variance(&jobs)),
+        (rewards.len() - 1) as f64,
+    );
+    RareEventEstimate::new(estimate, rewards)
+}
+
+/// Estimate the probability that a client sees a rare event with the crude Monte Carlo: the
+/// number of clients seeing it and the number of clients of every cycle come from the same
+/// simulations (see `regenerative`).
+pub fn crude_rare_event_estimate(rewards: &[f64], jobs_by_cycle: &[f64]) -> RareEventEstimate {
+    RareEventEstimate::new(regenerative(rewards, jobs_by_cycle), rewards)
+}
+
+/// The corrections available when many hypotheses are tested at once (one by rho in a sweep).
+///
+/// * `Bonferroni`: Every p-value is compared to `α / m`, which controls the family-wise error rate.
+/// * `Holm`: The step-down version of Bonferroni, it also controls the family-wise error rate but
+///   rejects more.
+/// * `BenjaminiHochberg`: The step-up procedure controlling the false discovery rate.
+#[derive(Copy, Clone, Debug)]
+pub enum Correction {
+    Bonferroni,
+    Holm,
+    BenjaminiHochberg,
+}
+
+/// Adjust the p-values of `m` hypotheses tested together, so that each adjusted p-value can be
+/// directly compared to the significance level `α`.
+pub fn adjusted_p_values(p_values: &[f64], correction: Correction) -> Vec<f64> {
+    let m = p_values.len();
+    let mut order = (0..m).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| p_values[i].partial_cmp(&p_values[j]).unwrap());
+
+    let mut adjusted = vec![0.0; m];
+    match correction {
+        Correction::Bonferroni => {
+            for (a, &p) in adjusted.iter_mut().zip(p_values) {
+                *a = (p * m as f64).min(1.0);
+            }
+        }
+        Correction::Holm => {
+            // the adjusted p-values are made non decreasing with the rank
+            let mut running_max: f64 = 0.0;
+            for (rank, &i) in order.iter().enumerate() {
+                running_max = running_max.max((p_values[i] * (m - rank) as f64).min(1.0));
+                adjusted[i] = running_max;
+            }
+        }
+        Correction::BenjaminiHochberg => {
+            // the adjusted p-values are made non increasing from the biggest rank down
+            let mut running_min: f64 = 1.0;
+            for (rank, &i) in order.iter().enumerate().rev() {
+                running_min = running_min.min(p_values[i] * m as f64 / (rank + 1) as f64);
+                adjusted[i] = running_min;
+            }
+        }
+    }
+    adjusted
+}
+
+/// Decide which of the hypotheses are rejected at the significance level `alpha`, once corrected.
+pub fn reject_hypotheses(p_values: &[f64], alpha: f64, correction: Correction) -> Vec<bool> {
+    adjusted_p_values(p_values, correction)
+        .iter()
+        .map(|&p| p < alpha)
+        .collect()
+}
+
+/// The result of a chi-square test.
+///
+/// Properties:
+///
+/// * `statistic`: The value of the chi-square statistic.
+/// * `degrees_of_freedom`: The degrees of freedom of the chi-square law.
+/// * `p_value`: The probability, under the hypothesis, to observe a statistic at least as big.
+#[derive(Copy, Clone, Debug)]
+pub struct ChiSquareTest {
+    pub statistic: f64,
+    pub degrees_of_freedom: f64,
+    pub p_value: f64,
+}
+
+impl ChiSquareTest {
+    /// Check if the hypothesis holds at the significance level `alpha`.
+    pub fn is_accepted(&self, alpha: f64) -> bool {
+        self.p_value >= alpha
+    }
+}
+
+/// The global goodness-of-fit test of a sweep: under the hypothesis that the model is right, the
+/// `m` (independent, and approximately normal) test statistics squared sum to a chi-square law with
+/// `m` degrees of freedom.
+pub fn global_chi_square_test(statistics: &[f64]) -> ChiSquareTest {
+    let statistic = statistics.iter().map(|t| t * t).sum::<f64>();
+    let degrees_of_freedom = statistics.len() as f64;
+    ChiSquareTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: 1.0 - chi_square_cdf(statistic, degrees_of_freedom),
+    }
+}
+
+/// The verdict on one metric of a whole sweep (e.g. the average stay for every rho).
+///
+/// Properties:
+///
+/// * `rejected`: For every point of the sweep, whether its hypothesis is rejected once corrected
+///   (at the level `α / 2`).
+/// * `global`: The global chi-square test over all the points.
+/// * `passed`: The single verdict of the experiment: no point is rejected and the global test
+///   holds. Both are run at the level `α / 2`, so that the verdict has the level `α`.
+pub struct SweepVerdict {
+    pub rejected: Vec<bool>,
+    pub global: ChiSquareTest,
+    pub passed: bool,
+}
+
+/// Test every point of a sweep against its theoretical value, with a correction for the multiple
+/// testing, and give a single pass/fail verdict. The significance level `α` of the verdict is
+/// `1 - confidence_level` of the data, split between the corrected tests of the points and the
+/// global test (Bonferroni).
+pub fn sweep_verdict(
+    values: &[Data],
+    metric: Metric,
+    theoretical: &[f64],
+    correction: Correction,
+) -> SweepVerdict {
+    let alpha = values
+        .first()
+        .map_or(1.0 - DEFAULT_CONFIDENCE_LEVEL, |d| 1.0 - d.confidence_level);
+    let tests = values
+        .iter()
+        .zip(theoretical)
+        .map(|(d, &v)| d.t_test(metric, v))
+        .collect::<Vec<_>>();
+
+    // the verdict combines two tests, each one gets half of the level
+    let rejected = reject_hypotheses(
+        &tests.iter().map(|t| t.p_value).collect::<Vec<_>>(),
+        alpha / 2.0,
+        correction,
+    );
+    let global = global_chi_square_test(&tests.iter().map(|t| t.statistic).collect::<Vec<_>>());
+    let passed = !rejected.iter().any(|&r| r) && global.is_accepted(alpha / 2.0);
+    SweepVerdict {
+        rejected,
+        global,
+        passed,
+    }
+}
+
+/// The result of a goodness-of-fit test of some samples against a theoretical distribution.
+///
+/// Properties:
+///
+/// * `statistic`: The value of the statistic of the test.
+/// * `p_value`: The probability, under the hypothesis that the samples follow the distribution, to
+///   observe a statistic at least as big.
+#[derive(Copy, Clone, Debug)]
+pub struct GoodnessOfFitTest {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+impl GoodnessOfFitTest {
+    /// Check if the hypothesis holds at the significance level `alpha`.
+    pub fn is_accepted(&self, alpha: f64) -> bool {
+        self.p_value >= alpha
+    }
+}
+
+/// The samples, sorted in increasing order.
+fn sorted(samples: &[f64]) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted
+}
+
+/// The Kolmogorov-Smirnov test of independent samples against a continuous CDF.
+///
+/// The statistic is the biggest distance between the empirical and the theoretical CDF, and the
+/// p-value comes from the (asymptotic) Kolmogorov distribution, with Stephens' correction for
+/// small samples.
+pub fn kolmogorov_smirnov_test<F: Fn(f64) -> f64>(samples: &[f64], cdf: F) -> GoodnessOfFitTest {
+    let sorted = sorted(samples);
+    let n = sorted.len() as f64;
+    let statistic = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let f = cdf(x);
+            f64::max(f - i as f64 / n, (i + 1) as f64 / n - f)
+        })
+        .fold(0.0, f64::max);
+
+    // Q_KS(λ) = 2 ∑ (-1)^(j-1) exp(-2 j² λ²)
+    let lambda = (n.sqrt() + 0.12 + 0.11 / n.sqrt()) * statistic;
+    let p_value = if lambda < 0.2 {
+        1.0
+    } else {
+        (1..=100)
+            .map(|j| {
+                let j = j as f64;
+                2.0 * (-1f64).powf(j - 1.0) * (-2.0 * j * j * lambda * lambda).exp()
+            })
+            .sum::<f64>()
+            .clamp(0.0, 1.0)
+    };
+    GoodnessOfFitTest { statistic, p_value }
+}
+
+/// The Anderson-Darling test of independent samples against a fully specified continuous CDF.
+///
+/// It gives more weight to the tails than the Kolmogorov-Smirnov test. The p-value comes from the
+/// Marsaglia & Marsaglia approximation of the asymptotic distribution of the statistic.
+pub fn anderson_darling_test<F: Fn(f64) -> f64>(samples: &[f64], cdf: F) -> GoodnessOfFitTest {
+    let sorted = sorted(samples);
+    let n = sorted.len();
+    // the CDF is kept away from 0 and 1, so that the logarithms stay finite
+    let f = sorted
+        .iter()
+        .map(|&x| cdf(x).clamp(1e-15, 1.0 - 1e-15))
+        .collect::<Vec<_>>();
+    let sum = (0..n)
+        .map(|i| (2 * i + 1) as f64 * (f[i].ln() + (1.0 - f[n - 1 - i]).ln()))
+        .sum::<f64>();
+    let statistic = -(n as f64) - sum / n as f64;
+
+    let z = statistic;
+    let asymptotic_cdf = if z <= 0.0 {
+        0.0
+    } else if z < 2.0 {
+        (-1.2337141 / z).exp() / z.sqrt()
+            * (2.00012
+                + (0.247105 - (0.0649821 - (0.0347962 - (0.011672 - 0.00168691 * z) * z) * z) * z)
+                    * z)
+    } else {
+        (-(1.0776
+            - (2.30695 - (0.43424 - (0.082433 - (0.008056 - 0.0003146 * z) * z) * z) * z) * z)
+            .exp())
+        .exp()
+    };
+    GoodnessOfFitTest {
+        statistic,
+        p_value: (1.0 - asymptotic_cdf).clamp(0.0, 1.0),
+    }
+}
+
+/// The chi-square goodness-of-fit test of independent samples against a continuous CDF.
+///
+/// The support is cut into `bins` bins of the same theoretical probability (the bounds are found
+/// by bisection on the CDF), and the observed counts are compared to the expected ones.
+///
+/// Arguments:
+///
+/// * `samples`: The independent samples.
+/// * `cdf`: The CDF of the law the samples should follow.
+/// * `support`: The interval out of which the law has no mass. Its lower bound must be finite, the
+///   upper one may be `f64::INFINITY`: the bisections then start from an upper bound found by
+///   doubling its distance to the lower one, at most 64 times.
+/// * `bins`: The number of bins.
+///
+/// Returns:
+///
+/// An error when the CDF does not reach the probability of a bound on the support (or, with an
+/// infinite support, within 2^64 of its lower bound).
+pub fn chi_square_goodness_of_fit<F: Fn(f64) -> f64>(
+    samples: &[f64],
+    cdf: F,
+    support: (f64, f64),
+    bins: usize,
+) -> Result<ChiSquareTest, Box<dyn Error>> {
+    assert!(bins > 1);
+    assert!(support.0.is_finite() && support.0 < support.1);
+    let n = samples.len() as f64;
+
+    // the bounds of the bins are the theoretical quantiles i / bins
+    let inverse = |p: f64| -> Result<f64, Box<dyn Error>> {
+        let (mut low, mut high) = support;
+        if high.is_infinite() {
+            high = low + 1.0;
+            for _ in 0..64 {
+                if cdf(high) >= p {
+                    break;
+                }
+                high = low + 2.0 * (high - low);
+            }
+        }
+        let reached = cdf(high);
+        if reached.is_nan() || reached < p {
+            return Err(format!("the CDF does not reach {} before {}", p, high).into());
+        }
+        for _ in 0..100 {
+            let middle = (low + high) / 2.0;
+            if cdf(middle) < p {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        Ok((low + high) / 2.0)
+    };
+    let bounds = (1..bins)
+        .map(|i| inverse(i as f64 / bins as f64))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut counts = vec![0usize; bins];
+    for &s in samples {
+        counts[bounds.iter().filter(|&&b| b < s).count()] += 1;
+    }
+
+    let expected = n / bins as f64;
+    let statistic = counts
+        .iter()
+        .map(|&c| (c as f64 - expected).powi(2) / expected)
+        .sum::<f64>();
+    let degrees_of_freedom = (bins - 1) as f64;
+    Ok(ChiSquareTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: 1.0 - chi_square_cdf(statistic, degrees_of_freedom),
+    })
+}
+
+/// The approximations of the average waiting time of a single server queue with setup, for the
+/// loads where the simulations are slow and noisy.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Approximation {
+    /// Kingman: `𝔼[W] ≈ ρ / (1 - ρ) (c_a² + c_s²) / 2 𝔼[B]`, exact for the M/G/1 queue. With one
+    /// server, it is also the Allen-Cunneen approximation (the M/M/c waiting time scaled by
+    /// `(c_a² + c_s²) / 2`).
+    Kingman,
+    /// Marchal: Kingman's formula corrected so that it is exact for the M/G/1 queue,
+    /// `𝔼[W] ≈ ρ² (1 + c_s²) / (1 + ρ² c_s²) (c_a² + ρ² c_s²) / (2 λ (1 - ρ))`.
+    Marchal,
+    /// The heavy-traffic (diffusion) limit: the queue is a reflected Brownian motion with the
+    /// drift `𝔼[B] - 𝔼[A]` and the variance `Var[A] + Var[B]` by client, whose stationary law is
+    /// Exponential of mean `(Var[A] + Var[B]) / (2 (𝔼[A] - 𝔼[B]))`.
+    HeavyTraffic,
+    /// Whitt (the QNA, with the correction of Kraemer and Langenbach-Belz): Kingman's formula
+    /// multiplied by `exp(-2 (1 - ρ) (1 - c_a²)² / (3 ρ (c_a² + c_s²)))` when `c_a² < 1`, and by
+    /// `exp(-(1 - ρ) (c_a² - 1) / (c_a² + 4 c_s²))` otherwise, which corrects it at a low load
+    /// for the arrivals more or less regular than Poisson ones.
+    Whitt,
+}
+
+/// The G/G/1 queue with setup, described by the moments of its laws, for the approximations.
+///
+/// Properties:
+///
+/// * `inter_arrival`: The moments of the inter-arrival time `A`.
+/// * `service`: The moments of the service time `B`.
+/// * `setup`: The moments of the setup (warmup) time `T`.
+#[derive(Copy, Clone, Debug)]
+pub struct SetupApproximations {
+    pub inter_arrival: Moments,
+    pub service: Moments,
+    pub setup: Moments,
+}
+
+impl SetupApproximations {
+    /// The approximations of the queue whose laws are given by their parameters.
+    pub fn from_parameters(
+        inter_arrival_param: &Parameter,
+        service_param: &Parameter,
+        warming_up_param: &Parameter,
+    ) -> Self {
+        SetupApproximations {
+            inter_arrival: inter_arrival_param.moments(),
+            service: service_param.moments(),
+            setup: warming_up_param.moments(),
+        }
+    }
+
+    /// The arrival rate `λ = 1 / 𝔼[A]`.
+    pub fn lambda(&self) -> f64 {
+        1.0 / self.inter_arrival.first
+    }
+
+    /// The load `ρ = 𝔼[B] / 𝔼[A]`.
+    pub fn rho(&self) -> f64 {
+        self.service.first / self.inter_arrival.first
+    }
+
+    /// The average extra waiting caused by the setup, `(𝔼[T] + λ 𝔼[T²] / 2) / (1 + λ 𝔼[T])`, exact
+    /// with Poisson arrivals (the waiting time is then the one without setup plus this independent
+    /// delay) and used as the setup correction of every approximation. With other renewal
+    /// arrivals, it is only a first-order correction: about `λ 𝔼[T]` clients still arrive during a
+    /// setup, but the fraction of the clients that find the server off changes with `c_a²`.
+    ///
+    /// It does not vanish when `ρ → 1`: the setups become rare, but each one delays a whole busy
+    /// period of clients. Its weight in `𝔼[W]` still goes to `0`, as the waiting without setup
+    /// grows like `1 / (1 - ρ)`.
+    pub fn setup_delay(&self) -> f64 {
+        let lambda = self.lambda();
+        (self.setup.first + lambda * self.setup.second / 2.0) / (1.0 + lambda * self.setup.first)
+    }
+
+    /// The approximated average waiting time without setup.
+    fn avg_waiting_without_setup(&self, approximation: Approximation) -> f64 {
+        let rho = self.rho();
+        assert!(rho < 1.0, "the queue must be stable");
+        let (ca2, cs2) = (self.inter_arrival.scv(), self.service.scv());
+        match approximation {
+            Approximation::Kingman => rho / (1.0 - rho) * (ca2 + cs2) / 2.0 * self.service.first,
+            Approximation::Marchal => {
+                let rho2 = rho * rho;
+                rho2 * (1.0 + cs2) / (1.0 + rho2 * cs2) * (ca2 + rho2 * cs2)
+                    / (2.0 * self.lambda() * (1.0 - rho))
+            }
+            Approximation::Whitt => {
+                let correction = if ca2 < 1.0 {
+                    (-2.0 * (1.0 - rho) * (1.0 - ca2).powi(2) / (3.0 * rho * (ca2 + cs2))).exp()
+                } else {
+                    (-(1.0 - rho) * (ca2 - 1.0) / (ca2 + 4.0 * cs2)).exp()
+                };
+                correction * self.avg_waiting_without_setup(Approximation::Kingman)
+            }
+            Approximation::HeavyTraffic => {
+                (self.inter_arrival.variance() + self.service.variance())
+                    / (2.0 * (self.inter_arrival.first - self.service.first))
+            }
+        }
+    }
+
+    /// The approximated average waiting time `𝔼[W]`, setup included.
+    pub fn avg_waiting(&self, approximation: Approximation) -> f64 {
+        self.avg_waiting_without_setup(approximation) + self.setup_delay()
+    }
+
+    /// The approximated average stay time `𝔼[S] = 𝔼[W] + 𝔼[B]`.
+    pub fn avg_stay(&self, approximation: Approximation) -> f64 {
+        self.avg_waiting(approximation) + self.service.first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variables::{generator_with, ExponentialParameter};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn incomplete_beta_matches_its_closed_forms() {
+        for x in [0.1, 0.37, 0.5, 0.8, 0.99] {
+            // I_x(a, 1) = x^a and I_x(1, b) = 1 - (1 - x)^b
+            assert!(close(
+                regularized_incomplete_beta(3.5, 1.0, x),
+                x.powf(3.5),
+                1e-12
+            ));
+            assert!(close(
+                regularized_incomplete_beta(1.0, 2.5, x),
+                1.0 - (1.0 - x).powf(2.5),
+                1e-12
+            ));
+        }
+        // the law of parameters (a, a) is symmetric around 1/2
+        assert!(close(
+            regularized_incomplete_beta(7.0, 7.0, 0.5),
+            0.5,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn student_quantiles_match_the_tables() {
+        for (p, degrees_of_freedom, tabulated) in [
+            (0.975, 1.0, 12.706),
+            (0.95, 5.0, 2.015),
+            (0.975, 10.0, 2.228),
+            (0.995, 30.0, 2.750),
+            (0.975, 1e7, 1.960),
+        ] {
+            let t = student_quantile(p, degrees_of_freedom);
+            assert!(close(t, tabulated, 1e-3));
+            assert!(close(student_cdf(t, degrees_of_freedom), p, 1e-12));
+            assert!(close(
+                student_quantile(1.0 - p, degrees_of_freedom),
+                -t,
+                1e-9
+            ));
+        }
+        assert!(close(student_cdf(0.0, 4.0), 0.5, 1e-15));
+    }
+
+    #[test]
+    fn confidence_interval_changes_of_level_round_trip() {
+        let interval = ConfidenceInterval::new(3.0, 2.0, 11, 0.95);
+        // t(0.975, 10) × 2 / √11
+        assert!(close(
+            interval.half_width(),
+            2.228 * 2.0 / 11f64.sqrt(),
+            1e-3
+        ));
+
+        let wider = interval.at_level(0.99);
+        assert!(wider.half_width() > interval.half_width());
+        let back = wider.at_level(0.95);
+        assert!(close(back.lower, interval.lower, 1e-12));
+        assert!(close(back.upper, interval.upper, 1e-12));
+    }
+
+    #[test]
+    fn chi_square_cdf_matches_the_tables() {
+        for (degrees_of_freedom, quantile, p) in [
+            (1.0, 3.841, 0.95),
+            (5.0, 15.086, 0.99),
+            (10.0, 18.307, 0.95),
+            (30.0, 43.773, 0.95),
+        ] {
+            assert!(close(chi_square_cdf(quantile, degrees_of_freedom), p, 1e-4));
+        }
+        // with two degrees of freedom, it is the Exponential law of rate 1/2
+        for x in [0.5, 2.0, 9.0, 40.0] {
+            assert!(close(chi_square_cdf(x, 2.0), 1.0 - (-x / 2.0).exp(), 1e-12));
+        }
+    }
+
+    #[test]
+    fn adjusted_p_values_match_the_textbook_example() {
+        let p_values = [0.01, 0.04, 0.03, 0.005];
+        for (correction, expected) in [
+            (Correction::Bonferroni, [0.04, 0.16, 0.12, 0.02]),
+            (Correction::Holm, [0.03, 0.06, 0.06, 0.02]),
+            (Correction::BenjaminiHochberg, [0.02, 0.04, 0.04, 0.02]),
+        ] {
+            let adjusted = adjusted_p_values(&p_values, correction);
+            for (a, e) in adjusted.iter().zip(expected) {
+                assert!(close(*a, e, 1e-12));
+            }
+        }
+    }
+
+    #[test]
+    fn adjusted_p_values_are_monotone_and_capped() {
+        let p_values = [0.2, 0.001, 0.6, 0.04, 0.9, 0.03, 0.35, 0.01];
+        for correction in [
+            Correction::Bonferroni,
+            Correction::Holm,
+            Correction::BenjaminiHochberg,
+        ] {
+            let adjusted = adjusted_p_values(&p_values, correction);
+            for (i, (&p, &a)) in p_values.iter().zip(&adjusted).enumerate() {
+                assert!(p <= a && a <= 1.0);
+                // the order of the p-values is kept
+                for (&q, &b) in p_values.iter().zip(&adjusted).skip(i + 1) {
+                    assert!(if p < q { a <= b } else { a >= b });
+                }
+            }
+        }
+        assert_eq!(
+            adjusted_p_values(&[0.5, 0.6, 0.9], Correction::Holm),
+            vec![1.0; 3]
+        );
+    }
+
+    /// A series with a geometric initial transient, `20 × 0.95^i`, above the level `1`, and a
+    /// deterministic noise of amplitude `0.5`. The transient is below `0.05` from `i = 117`.
+    fn transient_series(phase: f64) -> Vec<f64> {
+        (0..1000)
+            .map(|i| 1.0 + 20.0 * 0.95f64.powi(i) + 0.5 * (1.7 * i as f64 + phase).sin())
+            .collect()
+    }
+
+    #[test]
+    fn mser_deletes_the_initial_transient() {
+        let series = transient_series(0.0);
+        let d = mser(&series, 5);
+        assert!((60..=150).contains(&d));
+        assert!(close(series[d..].iter().calculate_mean(), 1.0, 0.01));
+        // a series whose batches all have the same mean is kept whole
+        assert_eq!(mser(&[1.0, 2.0].repeat(10), 2), 0);
+    }
+
+    #[test]
+    fn welch_deletes_the_initial_transient() {
+        let series = (0..10)
+            .map(|r| transient_series(r as f64))
+            .collect::<Vec<_>>();
+        assert!((100..=140).contains(&welch(&series, 5, 0.05)));
+        // a series that never settles keeps its last observation
+        let ramp = (0..100).map(|i| i as f64).collect::<Vec<_>>();
+        assert_eq!(welch(&[ramp], 0, 0.01), 99);
+    }
+
+    /// `n` independent samples of the Exponential law of mean `1`, always the same ones.
+    fn exponential_samples(n: usize, seed: u64) -> Vec<f64> {
+        let law = Parameter::Exponential(ExponentialParameter { lambda: 1.0 });
+        generator_with(&law, n, &mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn batch_means_of_independent_samples() {
+        // the variance of the mean of n samples of variance 1 is 1 / n
+        let n = 100_000;
+        let series = exponential_samples(n, 1);
+        for estimate in [
+            batch_means(&series, 50),
+            overlapping_batch_means(&series, 2000),
+        ] {
+            assert!(estimate.lower < 1.0 && 1.0 < estimate.upper);
+            assert!(close(estimate.variance * n as f64, 1.0, 0.5));
+        }
+        // the observations that do not fill a batch are dropped
+        assert!(close(
+            batch_means(&[1.0, 3.0, 2.0, 4.0, 100.0], 2).point,
+            2.5,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn regenerative_estimates_a_known_ratio() {
+        // Y = 3 τ + (U - 1) with U of mean 1 and variance 1: the ratio is 3 and the variance of
+        // Y - 3 τ is 1, so the variance of the estimator is 1 / (k 𝔼[τ]²) with 𝔼[τ] = 2
+        let k = 10_000;
+        let lengths = exponential_samples(k, 2)
+            .iter()
+            .map(|u| 1.0 + u)
+            .collect::<Vec<_>>();
+        let rewards = lengths
+            .iter()
+            .zip(exponential_samples(k, 3))
+            .map(|(t, u)| 3.0 * t + u - 1.0)
+            .collect::<Vec<_>>();
+        let estimate = regenerative(&rewards, &lengths);
+        assert!(estimate.lower < 3.0 && 3.0 < estimate.upper);
+        assert!(close(estimate.variance * 4.0 * k as f64, 1.0, 0.1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn batch_means_needs_an_observation_by_batch() {
+        batch_means(&[1.0, 2.0, 3.0], 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlapping_batch_means_needs_batches_shorter_than_the_series() {
+        overlapping_batch_means(&[1.0, 2.0, 3.0], 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn regenerative_needs_two_cycles() {
+        regenerative(&[1.0], &[2.0]);
+    }
+
+    #[test]
+    fn chi_square_goodness_of_fit_bins_any_support() {
+        // the centres of 1000 equal parts of [-2, 2] fit the uniform law on it perfectly
+        let samples = (0..1000)
+            .map(|i| -2.0 + 4.0 * (i as f64 + 0.5) / 1000.0)
+            .collect::<Vec<_>>();
+        let uniform = |x: f64| ((x + 2.0) / 4.0).clamp(0.0, 1.0);
+        let test = chi_square_goodness_of_fit(&samples, uniform, (-2.0, 2.0), 10).unwrap();
+        assert!(close(test.statistic, 0.0, 1e-9));
+        // the bins of the Exponential law are found past the first guess of the upper bound
+        let exponential = |x: f64| 1.0 - (-x).exp();
+        let shifted = samples.iter().map(|x| x + 2.0).collect::<Vec<_>>();
+        let test = chi_square_goodness_of_fit(&shifted, exponential, (0.0, f64::INFINITY), 10);
+        assert!(test.unwrap().p_value < 1e-6);
+    }
+
+    #[test]
+    fn chi_square_goodness_of_fit_rejects_a_defective_cdf() {
+        let defective = |x: f64| 0.5 * (1.0 - (-x).exp());
+        assert!(
+            chi_square_goodness_of_fit(&[1.0, 2.0], defective, (0.0, f64::INFINITY), 4).is_err()
+        );
+        assert!(chi_square_goodness_of_fit(&[1.0, 2.0], defective, (0.0, 10.0), 4).is_err());
+    }
 }
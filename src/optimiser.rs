@@ -0,0 +1,179 @@
+//! The `optimiser.rs` file contains the search of the best system among a finite set of
+//! candidates (e.g. power-down policies) whose cost can only be estimated by simulation.
+//!
+//! The search is a ranking-and-selection procedure, the fully sequential procedure of Kim and
+//! Nelson: all the candidates are simulated with common random numbers (the replication `r` of
+//! every candidate uses the seed `r`), and the candidates that are clearly worse than another one
+//! are eliminated along the way, so that the budget is spent on the close ones.
+use crate::measures::{
+    confidence_interval, corrected_standard_deviation, ConfidenceInterval, Mean,
+};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+/// The settings of the selection.
+///
+/// Properties:
+///
+/// * `initial_replications`: The number of replications of every candidate before the first
+///   elimination, used to estimate the variances of the differences (at least `2`).
+/// * `max_replications`: The budget by candidate, the best mean is taken once it is reached.
+/// * `indifference_zone`: The smallest difference of cost worth detecting: when the best candidate
+///   is better than all the others by at least this value, it is selected with probability
+///   `confidence_level`.
+/// * `confidence_level`: The probability of correct selection, also used for the confidence
+///   interval of the cost of the selected candidate.
+#[derive(Copy, Clone, Debug)]
+pub struct SelectionSettings {
+    pub initial_replications: usize,
+    pub max_replications: usize,
+    pub indifference_zone: f64,
+    pub confidence_level: f64,
+}
+
+/// The result of the selection.
+///
+/// Properties:
+///
+/// * `best`: The index of the selected candidate.
+/// * `cost`: The confidence interval of the cost of the selected candidate.
+/// * `replications`: The number of replications of every candidate, they all start with
+///   `initial_replications` and stop when they are eliminated.
+/// * `means`: The average cost of every candidate over its replications.
+/// * `gap_to_runner_up`: The confidence interval of the difference of cost between the candidate
+///   with the second best mean and the selected one, over their common replications (`None` when
+///   there is only one candidate).
+#[derive(Clone, Debug)]
+pub struct Selection {
+    pub best: usize,
+    pub cost: ConfidenceInterval,
+    pub replications: Vec<usize>,
+    pub means: Vec<f64>,
+    pub gap_to_runner_up: Option<ConfidenceInterval>,
+}
+
+/// Select the candidate with the smallest expected cost.
+///
+/// Arguments:
+///
+/// * `candidates`: The candidates.
+/// * `cost`: The cost of one replication of a candidate, given the seed of the replication. The
+///   same seed must give the same random numbers to every candidate.
+/// * `settings`: The settings of the selection.
+///
+/// Returns:
+///
+/// The selected candidate, with the confidence interval of its cost.
+pub fn select_best<C, F>(candidates: &[C], cost: F, settings: &SelectionSettings) -> Selection
+where
+    C: Sync,
+    F: Fn(&C, u64) -> f64 + Sync,
+{
+    let k = candidates.len();
+    let n0 = settings.initial_replications;
+    assert!(k > 0 && n0 > 1 && settings.max_replications >= n0);
+
+    let replicate = |survivors: &[usize], seeds: std::ops::Range<u64>| {
+        survivors
+            .par_iter()
+            .map(|&i| {
+                seeds
+                    .clone()
+                    .map(|seed| cost(&candidates[i], seed))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut costs = replicate(&(0..k).collect::<Vec<_>>(), 0..n0 as u64);
+
+    // the variance of the difference of every couple of candidates, on the first replications
+    let difference_variance = |i: usize, l: usize| {
+        let differences = costs[i][..n0]
+            .iter()
+            .zip(&costs[l][..n0])
+            .map(|(a, b)| a - b)
+            .collect::<Vec<_>>();
+        let mean = differences.iter().calculate_mean();
+        corrected_standard_deviation(mean, &differences).powi(2)
+    };
+    let variances = (0..k)
+        .map(|i| {
+            (0..k)
+                .map(|l| difference_variance(i, l))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let alpha = 1.0 - settings.confidence_level;
+    let eta = if k > 1 {
+        ((2.0 * alpha / (k - 1) as f64).powf(-2.0 / (n0 - 1) as f64) - 1.0) / 2.0
+    } else {
+        0.0
+    };
+    let h2 = 2.0 * eta * (n0 - 1) as f64;
+    let delta = settings.indifference_zone;
+
+    let mut survivors = (0..k).collect::<Vec<_>>();
+    let mut r = n0;
+    loop {
+        let means = survivors
+            .iter()
+            .map(|&i| costs[i][..r].iter().calculate_mean())
+            .collect::<Vec<_>>();
+        // `i` is eliminated when its mean is above the mean of another survivor by more than the
+        // tolerance, which shrinks as the replications are added
+        let kept = survivors
+            .iter()
+            .enumerate()
+            .filter(|&(a, &i)| {
+                survivors.iter().enumerate().all(|(b, &l)| {
+                    let w = (delta / (2.0 * r as f64)
+                        * (h2 * variances[i][l] / delta.powi(2) - r as f64))
+                        .max(0.0);
+                    a == b || means[a] <= means[b] + w
+                })
+            })
+            .map(|(_, &i)| i)
+            .collect::<Vec<_>>();
+        survivors = kept;
+
+        if survivors.len() <= 1 || r >= settings.max_replications {
+            break;
+        }
+        let new_costs = replicate(&survivors, r as u64..r as u64 + 1);
+        for (&i, c) in survivors.iter().zip(new_costs) {
+            costs[i].extend(c);
+        }
+        r += 1;
+    }
+
+    let means = costs
+        .iter()
+        .map(|c| c.iter().calculate_mean())
+        .collect::<Vec<_>>();
+    let by_mean = |candidates: &mut Vec<usize>| {
+        candidates.sort_by(|&a, &b| means[a].partial_cmp(&means[b]).unwrap());
+    };
+    by_mean(&mut survivors);
+    let best = survivors[0];
+
+    let mut others = (0..k).filter(|&i| i != best).collect::<Vec<_>>();
+    by_mean(&mut others);
+    let gap_to_runner_up = others.first().map(|&runner_up| {
+        let common = costs[best].len().min(costs[runner_up].len());
+        let differences = costs[runner_up][..common]
+            .iter()
+            .zip(&costs[best][..common])
+            .map(|(a, b)| a - b)
+            .collect::<Vec<_>>();
+        confidence_interval(&differences, settings.confidence_level)
+    });
+
+    Selection {
+        best,
+        cost: confidence_interval(&costs[best], settings.confidence_level),
+        replications: costs.iter().map(|c| c.len()).collect(),
+        means,
+        gap_to_runner_up,
+    }
+}
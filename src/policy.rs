@@ -0,0 +1,314 @@
+//! The `policy.rs` file contains the power-down policies of a farm of servers, and the event
+//! simulation of the farm. Instead of turning off as soon as it is idle (as in `queue`), a server
+//! may wait for an idle timer to expire, and the servers may only be switched on once enough
+//! clients are waiting (N-policy).
+use crate::measures::Mean;
+use crate::variables::{generator_with, Parameter};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+
+/// A power-down policy.
+///
+/// Properties:
+///
+/// * `servers`: The number of servers of the farm.
+/// * `idle_timer`: How long a server stays idle (on, without client) before turning off. With
+///   `0.0`, it turns off as soon as it is idle, with `f64::INFINITY` it never turns off once on.
+/// * `threshold`: The N-policy threshold: a server is switched on when at least `threshold`
+///   clients are waiting for every server that is already warming up (plus the new one). With
+///   `1`, every waiting client switches a server on, as in `queue`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Policy {
+    pub servers: usize,
+    pub idle_timer: f64,
+    pub threshold: usize,
+}
+
+/// Every combination of the given numbers of servers, idle timers and thresholds.
+pub fn policy_grid(servers: &[usize], idle_timers: &[f64], thresholds: &[usize]) -> Vec<Policy> {
+    servers
+        .iter()
+        .flat_map(|&servers| {
+            idle_timers.iter().flat_map(move |&idle_timer| {
+                thresholds.iter().map(move |&threshold| Policy {
+                    servers,
+                    idle_timer,
+                    threshold,
+                })
+            })
+        })
+        .collect()
+}
+
+/// The state of a server of the farm, with the time of its next event.
+#[derive(Copy, Clone, Debug)]
+enum Server {
+    Off,
+    Setup { end: f64 },
+    Busy { end: f64, job: usize },
+    Idle { until: f64 },
+}
+
+impl Server {
+    /// The index of the state in the occupation times (off, setup, idle, busy).
+    fn state_index(&self) -> usize {
+        match self {
+            Server::Off => 0,
+            Server::Setup { .. } => 1,
+            Server::Idle { .. } => 2,
+            Server::Busy { .. } => 3,
+        }
+    }
+
+    /// The time of the next event of the server, if it has one.
+    fn next_event(&self) -> f64 {
+        match self {
+            Server::Off => f64::INFINITY,
+            Server::Setup { end } => *end,
+            Server::Busy { end, .. } => *end,
+            Server::Idle { until } => *until,
+        }
+    }
+}
+
+/// The servers of the farm, with the time they spent in every state.
+struct Farm {
+    servers: Vec<Server>,
+    since: Vec<f64>, // when every server entered its current state
+    occupation: [f64; 4],
+}
+
+impl Farm {
+    /// Change the state of a server at time `t`, accounting for the time spent in the previous one.
+    fn set(&mut self, i: usize, state: Server, t: f64) {
+        self.occupation[self.servers[i].state_index()] += t - self.since[i];
+        self.since[i] = t;
+        self.servers[i] = state;
+    }
+
+    /// The servers that are in a given state.
+    fn in_state(&self, state_index: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.servers.len()).filter(move |&i| self.servers[i].state_index() == state_index)
+    }
+}
+
+/// The policy simulation struct groups the information of a run of the farm.
+///
+/// Properties:
+///
+/// * `arrivals`: List of the clients arrivals, the exact time at which they entered the system.
+/// * `departures`: List of the clients departures, the exact moments at which they left the system.
+/// * `occupation`: The time spent by the servers in every state (off, setup, idle and busy),
+///   summed over the servers.
+/// * `switch_ons`: The number of setups that were started (cancelled ones included).
+/// * `total_time`: The total time, from the beginning to the last departure.
+pub struct PolicySimulation {
+    arrivals: Vec<f64>,
+    departures: Vec<f64>,
+    occupation: [f64; 4],
+    switch_ons: usize,
+    total_time: f64,
+}
+
+/// Methods of the PolicySimulation struct
+impl PolicySimulation {
+    /// The time spent in the system by every client, from its arrival to its departure.
+    pub fn stay_times(&self) -> Vec<f64> {
+        self.arrivals
+            .iter()
+            .zip(&self.departures)
+            .map(|(&a, &d)| d - a)
+            .collect()
+    }
+
+    /// The average time spent in the system by the clients.
+    pub fn avg_stay(&self) -> f64 {
+        self.stay_times().iter().calculate_mean()
+    }
+
+    /// The average number of servers that are off.
+    pub fn avg_servers_off(&self) -> f64 {
+        self.occupation[0] / self.total_time
+    }
+
+    /// The average number of servers that are warming up.
+    pub fn avg_servers_setup(&self) -> f64 {
+        self.occupation[1] / self.total_time
+    }
+
+    /// The average number of servers that are on without client.
+    pub fn avg_servers_idle(&self) -> f64 {
+        self.occupation[2] / self.total_time
+    }
+
+    /// The average number of servers that are serving a client.
+    pub fn avg_servers_busy(&self) -> f64 {
+        self.occupation[3] / self.total_time
+    }
+
+    /// The number of setups started by unit of time.
+    pub fn switch_on_rate(&self) -> f64 {
+        self.switch_ons as f64 / self.total_time
+    }
+}
+
+/// The `policy_queue` function simulates `n` clients going through a farm of servers managed by
+/// a power-down policy.
+///
+/// The clients are served in their order of arrival by the first available server: an idle server
+/// if there is one (the one that became idle last, so that the timers of the others may expire),
+/// or the first server whose service or setup ends. When a server takes a client from the queue,
+/// the setups that are not needed anymore are cancelled. Once every client has arrived, the
+/// threshold is ignored so that the last clients are not left waiting forever.
+///
+/// The random numbers are drawn from three generators seeded from `seed` (one for the arrivals,
+/// one for the services and one for the setups), so that several policies can be compared with
+/// common random numbers: with the same seed, they see the same clients.
+///
+/// Arguments:
+///
+/// * `n`: The number of clients that will enter the system.
+/// * `policy`: The power-down policy of the farm.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+/// * `seed`: The seed of the random number generators.
+///
+/// Returns:
+///
+/// A `PolicySimulation` containing all the useful information for further calculations.
+pub fn policy_queue(
+    n: usize,
+    policy: &Policy,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+    seed: u64,
+) -> PolicySimulation {
+    assert!(policy.servers > 0 && policy.threshold > 0 && n > 0);
+    let mut arrivals_rng = StdRng::seed_from_u64(3 * seed);
+    let mut services_rng = StdRng::seed_from_u64(3 * seed + 1);
+    let mut setups_rng = StdRng::seed_from_u64(3 * seed + 2);
+
+    let mut accumulator = 0.0;
+    let arrivals: Vec<f64> = generator_with(inter_arrival_param, n, &mut arrivals_rng)
+        .iter()
+        .map(|x| {
+            accumulator += x;
+            accumulator
+        })
+        .collect();
+    let services = generator_with(service_param, n, &mut services_rng);
+    let mut draw_setup = || generator_with(warming_up_param, 1, &mut setups_rng)[0];
+
+    let mut farm = Farm {
+        servers: vec![Server::Off; policy.servers],
+        since: vec![0.0; policy.servers],
+        occupation: [0.0; 4],
+    };
+    let mut switch_ons = 0;
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    let mut departures = vec![0.0; n];
+    let mut next_arrival = 0;
+
+    // the simulation ends with the last departure, the timers of the idle servers are ignored
+    while next_arrival < n || !queue.is_empty() || farm.in_state(3).next().is_some() {
+        let (server, server_time) = farm
+            .servers
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.next_event()))
+            .fold((0, f64::INFINITY), |a, b| if b.1 < a.1 { b } else { a });
+        let arrival_time = arrivals.get(next_arrival).copied().unwrap_or(f64::INFINITY);
+
+        let t = arrival_time.min(server_time);
+        if arrival_time <= server_time {
+            let job = next_arrival;
+            next_arrival += 1;
+            // the idle server that became idle last takes the client
+            let idle = farm
+                .in_state(2)
+                .max_by(|&i, &j| farm.since[i].partial_cmp(&farm.since[j]).unwrap());
+            match idle {
+                Some(i) => farm.set(
+                    i,
+                    Server::Busy {
+                        end: t + services[job],
+                        job,
+                    },
+                    t,
+                ),
+                None => queue.push_back(job),
+            }
+        } else {
+            if let Server::Busy { job, .. } = farm.servers[server] {
+                departures[job] = t;
+            }
+            let state = match farm.servers[server] {
+                Server::Idle { .. } => Server::Off,
+                _ => match queue.pop_front() {
+                    Some(job) => Server::Busy {
+                        end: t + services[job],
+                        job,
+                    },
+                    None if policy.idle_timer > 0.0 => Server::Idle {
+                        until: t + policy.idle_timer,
+                    },
+                    None => Server::Off,
+                },
+            };
+            farm.set(server, state, t);
+        }
+
+        // the servers are switched on (or their setups cancelled) to match the waiting clients
+        let threshold = if next_arrival == n {
+            1
+        } else {
+            policy.threshold
+        };
+        let mut setting_up = farm.in_state(1).count();
+        while queue.len() >= threshold * (setting_up + 1) {
+            let off = farm.in_state(0).next();
+            match off {
+                Some(i) => {
+                    farm.set(
+                        i,
+                        Server::Setup {
+                            end: t + draw_setup(),
+                        },
+                        t,
+                    );
+                    switch_ons += 1;
+                    setting_up += 1;
+                }
+                None => break,
+            }
+        }
+        while setting_up > 0 && queue.len() < threshold * setting_up {
+            // the setup that would end last is the least useful
+            let i = farm
+                .in_state(1)
+                .max_by(|&i, &j| {
+                    let (a, b) = (farm.servers[i].next_event(), farm.servers[j].next_event());
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap();
+            farm.set(i, Server::Off, t);
+            setting_up -= 1;
+        }
+    }
+
+    let total_time = departures.iter().cloned().fold(0.0, f64::max);
+    for i in 0..policy.servers {
+        farm.set(i, Server::Off, total_time);
+    }
+
+    PolicySimulation {
+        arrivals,
+        departures,
+        occupation: farm.occupation,
+        switch_ons,
+        total_time,
+    }
+}
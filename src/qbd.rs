@@ -0,0 +1,805 @@
+//! The `qbd.rs` file contains the solver of the quasi-birth-death processes (QBD): CTMCs whose
+//! states are grouped by levels (the number of clients), where the transitions only go to the
+//! same level or to the next ones, and are the same for every level above a boundary.
+//!
+//! Their stationary distribution is matrix-geometric, `π_(n+1) = π_n R` above the boundary, so it
+//! is found without truncating the state space, even at a load close to one. The matrix `R` is
+//! computed with the logarithmic reduction of Latouche and Ramaswami.
+//!
+//! Two models with setup are built this way: the M/PH/1 queue (phase-type services and setups),
+//! and the M/M/c farm whose servers turn off as soon as they are idle, with Erlang-ordered
+//! phase-type setups.
+use crate::ctmc::solve_linear_system;
+use crate::theory::Moments;
+use crate::variables::Parameter;
+
+type Matrix = Vec<Vec<f64>>;
+
+fn zeros(rows: usize, columns: usize) -> Matrix {
+    vec![vec![0.0; columns]; rows]
+}
+
+fn identity(n: usize) -> Matrix {
+    let mut m = zeros(n, n);
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    m
+}
+
+fn product(a: &[Vec<f64>], b: &[Vec<f64>]) -> Matrix {
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(b).map(|(x, b)| x * b[j]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+fn sum(a: &[Vec<f64>], b: &[Vec<f64>], factor: f64) -> Matrix {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.iter().zip(b).map(|(x, y)| x + factor * y).collect())
+        .collect()
+}
+
+fn inverse(a: &[Vec<f64>]) -> Matrix {
+    let n = a.len();
+    let columns = (0..n)
+        .map(|j| {
+            let mut e = vec![0.0; n];
+            e[j] = 1.0;
+            solve_linear_system(a, &e)
+        })
+        .collect::<Vec<_>>();
+    (0..n)
+        .map(|i| (0..n).map(|j| columns[j][i]).collect())
+        .collect()
+}
+
+/// The row vector `x` multiplied by the matrix `m`.
+fn row_product(x: &[f64], m: &[Vec<f64>]) -> Vec<f64> {
+    (0..m.first().map_or(0, |r| r.len()))
+        .map(|j| x.iter().zip(m).map(|(x, row)| x * row[j]).sum())
+        .collect()
+}
+
+/// A phase-type law: the time until absorption of a CTMC with transient phases.
+///
+/// Properties:
+///
+/// * `alpha`: The probability to start in every phase.
+/// * `t`: The rates between the transient phases (the sub-generator), the rate of absorption from
+///   a phase being what is missing for its row to sum to `0`.
+#[derive(Clone, Debug)]
+pub struct PhaseType {
+    pub alpha: Vec<f64>,
+    pub t: Vec<Vec<f64>>,
+}
+
+impl PhaseType {
+    /// The Exponential law of rate `lambda`.
+    pub fn exponential(lambda: f64) -> Self {
+        PhaseType {
+            alpha: vec![1.0],
+            t: vec![vec![-lambda]],
+        }
+    }
+
+    /// The Erlang law of `k` phases of rate `lambda` each.
+    pub fn erlang(k: usize, lambda: f64) -> Self {
+        let mut t = zeros(k, k);
+        for (i, row) in t.iter_mut().enumerate() {
+            row[i] = -lambda;
+            if i + 1 < k {
+                row[i + 1] = lambda;
+            }
+        }
+        let mut alpha = vec![0.0; k];
+        alpha[0] = 1.0;
+        PhaseType { alpha, t }
+    }
+
+    /// The hyper-exponential law: the Exponential law of rate `rates[i]` with probability
+    /// `probabilities[i]`. Its coefficient of variation is above one.
+    pub fn hyperexponential(probabilities: &[f64], rates: &[f64]) -> Self {
+        let mut t = zeros(rates.len(), rates.len());
+        for (i, &rate) in rates.iter().enumerate() {
+            t[i][i] = -rate;
+        }
+        PhaseType {
+            alpha: probabilities.to_vec(),
+            t,
+        }
+    }
+
+    /// The phase-type form of an Exponential, Erlang or hyper-exponential law.
+    pub fn from_parameter(parameter: &Parameter) -> Self {
+        match parameter {
+            Parameter::Exponential(p) => PhaseType::exponential(p.lambda),
+            Parameter::Erlang(p) => PhaseType::erlang(p.k, 1.0 / p.beta),
+            Parameter::HyperExponential(p) => {
+                PhaseType::hyperexponential(&[p.p, 1.0 - p.p], &[p.lambda_1, p.lambda_2])
+            }
+            Parameter::Poisson(_) => panic!("the Poisson law is discrete, not phase-type"),
+        }
+    }
+
+    /// The number of phases.
+    pub fn phases(&self) -> usize {
+        self.alpha.len()
+    }
+
+    /// The rate of absorption from every phase.
+    pub fn exit_rates(&self) -> Vec<f64> {
+        self.t.iter().map(|row| -row.iter().sum::<f64>()).collect()
+    }
+
+    /// The first three moments, `𝔼[X^k] = k! α (-T)^(-k) 1`.
+    pub fn moments(&self) -> Moments {
+        let n = self.phases();
+        let minus_t_inverse = inverse(&sum(&zeros(n, n), &self.t, -1.0));
+        let mut x = self.alpha.clone();
+        let mut moments = [0.0; 3];
+        let mut factorial = 1.0;
+        for (k, moment) in moments.iter_mut().enumerate() {
+            x = row_product(&x, &minus_t_inverse);
+            factorial *= (k + 1) as f64;
+            *moment = factorial * x.iter().sum::<f64>();
+        }
+        Moments {
+            first: moments[0],
+            second: moments[1],
+            third: Some(moments[2]),
+        }
+    }
+}
+
+/// A QBD described level by level: the solver builds the blocks of the generator from it.
+///
+/// The levels from `repeating_level()` on must have the same states and the same transitions
+/// (only shifted by one level).
+pub trait QbdModel {
+    type State: Clone + PartialEq;
+
+    /// The first level `L` of the repeating part, the levels below are the boundary.
+    fn repeating_level(&self) -> usize;
+
+    /// The states of a level.
+    fn states(&self, level: usize) -> Vec<Self::State>;
+
+    /// The transitions out of a state: the next level (the same one, or one more or one less), the
+    /// next state and the rate.
+    fn transitions(&self, level: usize, state: &Self::State) -> Vec<(usize, Self::State, f64)>;
+}
+
+/// The stationary distribution of a QBD.
+///
+/// Properties:
+///
+/// * `states`: The states of the levels `0` to `L` (the last one is shared by every level above).
+/// * `boundary`: The probabilities of the states of the levels below `L`.
+/// * `first_level`: The probabilities `π_L` of the states of the level `L`.
+/// * `r`: The rate matrix, `π_(n+1) = π_n R` for `n ≥ L`.
+pub struct QbdSolution<S> {
+    pub states: Vec<Vec<S>>,
+    pub boundary: Vec<Vec<f64>>,
+    pub first_level: Vec<f64>,
+    pub r: Vec<Vec<f64>>,
+    // (I - R)^(-1), used by all the sums over the levels
+    fundamental: Vec<Vec<f64>>,
+}
+
+impl<S> QbdSolution<S> {
+    /// The probabilities of the states of a level.
+    pub fn level(&self, n: usize) -> Vec<f64> {
+        if n < self.boundary.len() {
+            return self.boundary[n].clone();
+        }
+        let mut pi = self.first_level.clone();
+        for _ in self.boundary.len()..n {
+            pi = row_product(&pi, &self.r);
+        }
+        pi
+    }
+
+    /// The probability of every level from `0` to `max_level`.
+    pub fn level_distribution(&self, max_level: usize) -> Vec<f64> {
+        (0..=max_level)
+            .map(|n| self.level(n).iter().sum())
+            .collect()
+    }
+
+    /// The expectation of a reward that depends on the level and on the state. Above the boundary,
+    /// the reward must not depend on the level anymore: it is only asked for the level `L`.
+    pub fn expectation<F: Fn(usize, &S) -> f64>(&self, reward: F) -> f64 {
+        let boundary = self
+            .boundary
+            .iter()
+            .enumerate()
+            .map(|(n, pi)| {
+                pi.iter()
+                    .zip(&self.states[n])
+                    .map(|(p, s)| p * reward(n, s))
+                    .sum::<f64>()
+            })
+            .sum::<f64>();
+        let l = self.boundary.len();
+        // ∑_(n ≥ L) π_n = π_L (I - R)^(-1)
+        let repeating = row_product(&self.first_level, &self.fundamental)
+            .iter()
+            .zip(&self.states[l])
+            .map(|(p, s)| p * reward(l, s))
+            .sum::<f64>();
+        boundary + repeating
+    }
+
+    /// The average level, that is the average number of clients.
+    pub fn mean_level(&self) -> f64 {
+        let l = self.boundary.len();
+        let boundary = self
+            .boundary
+            .iter()
+            .enumerate()
+            .map(|(n, pi)| n as f64 * pi.iter().sum::<f64>())
+            .sum::<f64>();
+        // ∑_(n ≥ L) n π_n = π_L (L (I - R)^(-1) + R (I - R)^(-2)) 1
+        let fundamental_squared = product(&self.fundamental, &self.fundamental);
+        let weights = sum(
+            &self
+                .fundamental
+                .iter()
+                .map(|row| row.iter().map(|x| l as f64 * x).collect())
+                .collect::<Matrix>(),
+            &product(&self.r, &fundamental_squared),
+            1.0,
+        );
+        boundary + row_product(&self.first_level, &weights).iter().sum::<f64>()
+    }
+}
+
+/// Compute the matrix `G` of a QBD, the minimal solution of `A2 + A1 G + A0 G² = 0` (the
+/// probability to first reach the level below in every state), with the logarithmic reduction.
+///
+/// Arguments:
+///
+/// * `a0`: The rates to the level above.
+/// * `a1`: The rates inside a level.
+/// * `a2`: The rates to the level below.
+fn logarithmic_reduction(a0: &[Vec<f64>], a1: &[Vec<f64>], a2: &[Vec<f64>]) -> Matrix {
+    let m = a1.len();
+    let minus_a1_inverse = inverse(&sum(&zeros(m, m), a1, -1.0));
+    let mut up = product(&minus_a1_inverse, a0);
+    let mut down = product(&minus_a1_inverse, a2);
+    let mut g = down.clone();
+    let mut t = up.clone();
+
+    for _ in 0..100 {
+        let u = sum(&product(&up, &down), &product(&down, &up), 1.0);
+        let n = inverse(&sum(&identity(m), &u, -1.0));
+        up = product(&n, &product(&up, &up));
+        down = product(&n, &product(&down, &down));
+        g = sum(&g, &product(&t, &down), 1.0);
+        t = product(&t, &up);
+
+        // G is stochastic when the QBD is positive recurrent, and is not updated anymore once `T`
+        // vanishes (the rounding errors may then keep it a bit away from stochastic)
+        let gap = g
+            .iter()
+            .map(|row| (1.0 - row.iter().sum::<f64>()).abs())
+            .fold(0.0, f64::max);
+        let remainder = t.iter().flatten().fold(0.0, |m: f64, x| m.max(x.abs()));
+        if gap < 1e-14 || remainder < 1e-16 {
+            return g;
+        }
+    }
+    panic!("the logarithmic reduction did not converge, the QBD may be unstable");
+}
+
+/// Solve a QBD: its stationary distribution, without truncation.
+///
+/// The matrix `R = A0 (-A1 - A0 G)^(-1)` gives the repeating levels from the level `L`, and the
+/// boundary levels together with `π_L` solve the remaining balance equations with the
+/// normalisation `∑ π_boundary + π_L (I - R)^(-1) 1 = 1`.
+pub fn solve_qbd<M: QbdModel>(model: &M) -> QbdSolution<M::State> {
+    let l = model.repeating_level();
+    let states = (0..=l + 1).map(|n| model.states(n)).collect::<Vec<_>>();
+    assert!(states[l] == states[l + 1], "the levels must repeat from L");
+    let m = states[l].len();
+
+    // the boundary states are numbered level by level
+    let offsets = states[..l]
+        .iter()
+        .scan(0, |offset, s| {
+            let o = *offset;
+            *offset += s.len();
+            Some(o)
+        })
+        .collect::<Vec<_>>();
+    let b = states[..l].iter().map(|s| s.len()).sum::<usize>();
+    let position =
+        |level: usize, state: &M::State| states[level].iter().position(|s| s == state).unwrap();
+
+    // the blocks of the generator (the diagonal is the opposite of all the outgoing rates)
+    let mut boundary = zeros(b, b);
+    let mut boundary_up = zeros(b, m);
+    let mut boundary_down = zeros(m, b);
+    let (mut a0, mut a1, mut a2) = (zeros(m, m), zeros(m, m), zeros(m, m));
+    for (n, level_states) in states[..l].iter().enumerate() {
+        for (i, state) in level_states.iter().enumerate() {
+            let from = offsets[n] + i;
+            for (next, next_state, rate) in model.transitions(n, state) {
+                boundary[from][from] -= rate;
+                if next == l {
+                    boundary_up[from][position(l, &next_state)] += rate;
+                } else {
+                    boundary[from][offsets[next] + position(next, &next_state)] += rate;
+                }
+            }
+        }
+    }
+    for (i, state) in states[l].iter().enumerate() {
+        for (next, next_state, rate) in model.transitions(l, state) {
+            a1[i][i] -= rate;
+            match next {
+                _ if next == l + 1 => a0[i][position(l + 1, &next_state)] += rate,
+                _ if next == l => a1[i][position(l, &next_state)] += rate,
+                _ => boundary_down[i][offsets[next] + position(next, &next_state)] += rate,
+            }
+        }
+    }
+    for (i, state) in states[l + 1].iter().enumerate() {
+        for (next, next_state, rate) in model.transitions(l + 1, state) {
+            if next == l {
+                a2[i][position(l, &next_state)] += rate;
+            }
+        }
+    }
+
+    let g = logarithmic_reduction(&a0, &a1, &a2);
+    let r = product(
+        &a0,
+        &inverse(&sum(&sum(&zeros(m, m), &a1, -1.0), &product(&a0, &g), -1.0)),
+    );
+    let fundamental = inverse(&sum(&identity(m), &r, -1.0));
+    let level_l = sum(&a1, &product(&r, &a2), 1.0);
+
+    // the balance equations of the boundary and of the level L, as `x M = 0` for x = (π_b, π_L),
+    // solved as `Mᵀ xᵀ = 0` where the last equation is replaced by the normalisation
+    let size = b + m;
+    let mut a = zeros(size, size);
+    for i in 0..b {
+        for j in 0..b {
+            a[j][i] = boundary[i][j];
+        }
+        for j in 0..m {
+            a[b + j][i] = boundary_up[i][j];
+        }
+    }
+    for k in 0..m {
+        for j in 0..b {
+            a[j][b + k] = boundary_down[k][j];
+        }
+        for j in 0..m {
+            a[b + j][b + k] = level_l[k][j];
+        }
+    }
+    let mut normalisation = vec![1.0; b];
+    normalisation.extend(fundamental.iter().map(|row| row.iter().sum::<f64>()));
+    a[size - 1] = normalisation;
+    let mut right = vec![0.0; size];
+    right[size - 1] = 1.0;
+    let x = solve_linear_system(&a, &right);
+
+    QbdSolution {
+        boundary: (0..l)
+            .map(|n| x[offsets[n]..offsets[n] + states[n].len()].to_vec())
+            .collect(),
+        first_level: x[b..].to_vec(),
+        states: states[..=l].to_vec(),
+        r,
+        fundamental,
+    }
+}
+
+/// The state of the server of the M/PH/1 queue with setup, with the phase of the ongoing setup or
+/// service.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ServerPhase {
+    Off,
+    Setup(usize),
+    Busy(usize),
+}
+
+/// The M/PH/1 queue with setup: Poisson arrivals, phase-type services and setups. The level is
+/// the number of clients, the server is off on the level `0` only.
+///
+/// Properties:
+///
+/// * `lambda`: The arrival rate.
+/// * `service`: The law of the service times.
+/// * `setup`: The law of the setup times.
+pub struct PhaseTypeSetupQueue {
+    pub lambda: f64,
+    pub service: PhaseType,
+    pub setup: PhaseType,
+}
+
+impl QbdModel for PhaseTypeSetupQueue {
+    type State = ServerPhase;
+
+    fn repeating_level(&self) -> usize {
+        1
+    }
+
+    fn states(&self, level: usize) -> Vec<ServerPhase> {
+        if level == 0 {
+            return vec![ServerPhase::Off];
+        }
+        (0..self.setup.phases())
+            .map(ServerPhase::Setup)
+            .chain((0..self.service.phases()).map(ServerPhase::Busy))
+            .collect()
+    }
+
+    fn transitions(&self, level: usize, state: &ServerPhase) -> Vec<(usize, ServerPhase, f64)> {
+        let mut transitions = Vec::new();
+        // a phase-type law moves between its phases, or ends and starts the next period
+        let mut moves = |law: &PhaseType, phase: usize, to: fn(usize) -> ServerPhase| {
+            for (j, &rate) in law.t[phase].iter().enumerate() {
+                if j != phase && rate > 0.0 {
+                    transitions.push((level, to(j), rate));
+                }
+            }
+            law.exit_rates()[phase]
+        };
+        match *state {
+            ServerPhase::Off => {
+                for (j, &p) in self.setup.alpha.iter().enumerate() {
+                    transitions.push((1, ServerPhase::Setup(j), self.lambda * p));
+                }
+                return transitions;
+            }
+            ServerPhase::Setup(i) => {
+                let exit = moves(&self.setup, i, ServerPhase::Setup);
+                for (j, &p) in self.service.alpha.iter().enumerate() {
+                    transitions.push((level, ServerPhase::Busy(j), exit * p));
+                }
+            }
+            ServerPhase::Busy(i) => {
+                let exit = moves(&self.service, i, ServerPhase::Busy);
+                if level == 1 {
+                    transitions.push((0, ServerPhase::Off, exit));
+                } else {
+                    for (j, &p) in self.service.alpha.iter().enumerate() {
+                        transitions.push((level - 1, ServerPhase::Busy(j), exit * p));
+                    }
+                }
+            }
+        }
+        transitions.push((level + 1, *state, self.lambda));
+        transitions
+    }
+}
+
+/// The exact metrics of a QBD model with setup.
+///
+/// Properties:
+///
+/// * `avg_servers_off`: The average number of servers that are off (with one server, the
+///   probability that it is off).
+/// * `avg_servers_setup`: The average number of servers that are warming up.
+/// * `avg_servers_busy`: The average number of servers that are serving a client.
+/// * `avg_jobs_in_system`: The average number of clients in the system.
+/// * `avg_jobs_in_queue`: The average number of clients waiting for a server.
+/// * `avg_stay`: The average time spent in the system by a client (by Little's law).
+/// * `avg_waiting`: The average time spent waiting for a server by a client.
+/// * `switch_on_rate`: The number of setups started by unit of time (cancelled ones included).
+#[derive(Copy, Clone, Debug)]
+pub struct QbdMetrics {
+    pub avg_servers_off: f64,
+    pub avg_servers_setup: f64,
+    pub avg_servers_busy: f64,
+    pub avg_jobs_in_system: f64,
+    pub avg_jobs_in_queue: f64,
+    pub avg_stay: f64,
+    pub avg_waiting: f64,
+    pub switch_on_rate: f64,
+}
+
+impl PhaseTypeSetupQueue {
+    /// Solve the queue, and derive its metrics.
+    pub fn solve(&self) -> (QbdSolution<ServerPhase>, QbdMetrics) {
+        let solution = solve_qbd(self);
+        let p_off = solution.expectation(|_, s| (*s == ServerPhase::Off) as usize as f64);
+        let p_busy = solution.expectation(|_, s| matches!(s, ServerPhase::Busy(_)) as usize as f64);
+        let avg_jobs_in_system = solution.mean_level();
+        let metrics = QbdMetrics {
+            avg_servers_off: p_off,
+            avg_servers_setup: 1.0 - p_off - p_busy,
+            avg_servers_busy: p_busy,
+            avg_jobs_in_system,
+            avg_jobs_in_queue: avg_jobs_in_system - p_busy,
+            avg_stay: avg_jobs_in_system / self.lambda,
+            avg_waiting: (avg_jobs_in_system - p_busy) / self.lambda,
+            switch_on_rate: self.lambda * p_off,
+        };
+        (solution, metrics)
+    }
+}
+
+/// The state of the M/M/c farm with setup inside a level: the number of servers that are on, and
+/// the number of servers warming up in every phase of the setup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FarmPhases {
+    pub on: usize,
+    pub setups: Vec<usize>,
+}
+
+/// The M/M/c farm with phase-type setups: Poisson arrivals, Exponential services, and servers that
+/// turn off as soon as they are idle. As in `policy_queue` (with a threshold of `1`), a server is
+/// switched on for every waiting client as long as some are off, and a setup is cancelled when a
+/// client leaves the queue for a server that was already on (the least advanced setup).
+///
+/// The cancelled setup is the one in the lowest phase, so the setup law must be Erlang-ordered: it
+/// starts in the first phase and only moves forward, like the Exponential and Erlang laws.
+///
+/// The levels repeat from `c` clients on: all the servers are then either on or warming up.
+///
+/// Properties:
+///
+/// * `lambda`: The arrival rate.
+/// * `mu`: The rate of the Exponential service times.
+/// * `servers`: The number `c` of servers.
+/// * `setup`: The law of the setup times.
+pub struct PhaseTypeSetupFarm {
+    pub lambda: f64,
+    pub mu: f64,
+    pub servers: usize,
+    pub setup: PhaseType,
+}
+
+impl PhaseTypeSetupFarm {
+    /// The number of servers warming up with `n` clients and `on` servers on.
+    fn setups_needed(&self, n: usize, on: usize) -> usize {
+        (n - n.min(on)).min(self.servers - on)
+    }
+
+    /// Every way to spread `count` setups over the phases.
+    fn spreads(&self, count: usize) -> Vec<Vec<usize>> {
+        let phases = self.setup.phases();
+        let mut spreads = vec![vec![]];
+        for phase in 0..phases {
+            spreads = spreads
+                .into_iter()
+                .flat_map(|spread: Vec<usize>| {
+                    let used = spread.iter().sum::<usize>();
+                    let range = if phase + 1 == phases {
+                        count - used..=count - used
+                    } else {
+                        0..=count - used
+                    };
+                    range.map(move |k| {
+                        let mut spread = spread.clone();
+                        spread.push(k);
+                        spread
+                    })
+                })
+                .collect();
+        }
+        spreads
+    }
+
+    /// Solve the farm, and derive its metrics.
+    pub fn solve(&self) -> (QbdSolution<FarmPhases>, QbdMetrics) {
+        assert!(
+            self.setup.alpha[0] == 1.0
+                && (0..self.setup.phases()).all(|i| (0..i).all(|j| self.setup.t[i][j] == 0.0)),
+            "the setup law must be Erlang-ordered"
+        );
+        let solution = solve_qbd(self);
+        let busy = |n: usize, s: &FarmPhases| n.min(s.on) as f64;
+        let setup = |s: &FarmPhases| s.setups.iter().sum::<usize>() as f64;
+        let avg_servers_busy = solution.expectation(busy);
+        let avg_jobs_in_system = solution.mean_level();
+        let avg_jobs_in_queue = avg_jobs_in_system - avg_servers_busy;
+        let throughput = self.lambda;
+        // the setups are only started by the arrivals
+        let switch_on_rate = self.lambda
+            * solution.expectation(|n, s| {
+                (self.setups_needed(n + 1, s.on) > self.setups_needed(n, s.on)) as usize as f64
+            });
+        let metrics = QbdMetrics {
+            avg_servers_off: solution
+                .expectation(|_, s| self.servers as f64 - s.on as f64 - setup(s)),
+            avg_servers_setup: solution.expectation(|_, s| setup(s)),
+            avg_servers_busy,
+            avg_jobs_in_system,
+            avg_jobs_in_queue,
+            avg_stay: avg_jobs_in_system / throughput,
+            avg_waiting: avg_jobs_in_queue / throughput,
+            switch_on_rate,
+        };
+        (solution, metrics)
+    }
+}
+
+impl QbdModel for PhaseTypeSetupFarm {
+    type State = FarmPhases;
+
+    fn repeating_level(&self) -> usize {
+        self.servers
+    }
+
+    fn states(&self, level: usize) -> Vec<FarmPhases> {
+        // the servers that are on are all busy
+        (0..=level.min(self.servers))
+            .flat_map(|on| {
+                self.spreads(self.setups_needed(level, on))
+                    .into_iter()
+                    .map(move |setups| FarmPhases { on, setups })
+            })
+            .collect()
+    }
+
+    fn transitions(&self, level: usize, state: &FarmPhases) -> Vec<(usize, FarmPhases, f64)> {
+        let mut transitions = Vec::new();
+        let on = state.on;
+
+        // an arrival may switch a server on
+        if self.setups_needed(level + 1, on) > self.setups_needed(level, on) {
+            for (j, &p) in self.setup.alpha.iter().enumerate() {
+                let mut next = state.clone();
+                next.setups[j] += 1;
+                transitions.push((level + 1, next, self.lambda * p));
+            }
+        } else {
+            transitions.push((level + 1, state.clone(), self.lambda));
+        }
+
+        // the setups move between their phases, or end and the server takes a waiting client
+        let exit_rates = self.setup.exit_rates();
+        for (i, &count) in state.setups.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            for (j, &rate) in self.setup.t[i].iter().enumerate() {
+                if j != i && rate > 0.0 {
+                    let mut next = state.clone();
+                    next.setups[i] -= 1;
+                    next.setups[j] += 1;
+                    transitions.push((level, next, count as f64 * rate));
+                }
+            }
+            let mut next = state.clone();
+            next.setups[i] -= 1;
+            next.on += 1;
+            transitions.push((level, next, count as f64 * exit_rates[i]));
+        }
+
+        // a departure: the server takes a waiting client (a setup may not be needed anymore), or
+        // turns off
+        let busy = level.min(on);
+        if busy > 0 {
+            let mut next = state.clone();
+            if level > on {
+                let needed = self.setups_needed(level - 1, on);
+                if needed < next.setups.iter().sum::<usize>() {
+                    let least_advanced = next.setups.iter().position(|&k| k > 0).unwrap();
+                    next.setups[least_advanced] -= 1;
+                }
+            } else {
+                next.on -= 1;
+            }
+            transitions.push((level - 1, next, busy as f64 * self.mu));
+        }
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ctmc::MarkovianFarm;
+    use crate::policy::Policy;
+    use crate::theory::SetupQueue;
+
+    /// The metrics of the QBD must be the closed forms of the M/G/1 queue with setup, which only
+    /// depend on the moments of the laws.
+    fn assert_matches_setup_queue(lambda: f64, service: PhaseType, setup: PhaseType) {
+        let exact = SetupQueue {
+            lambda,
+            service: service.moments(),
+            setup: setup.moments(),
+        };
+        let (_, metrics) = PhaseTypeSetupQueue {
+            lambda,
+            service,
+            setup,
+        }
+        .solve();
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9 * b.abs().max(1.0);
+        assert!(close(metrics.avg_jobs_in_system, exact.avg_in_system()));
+        assert!(close(metrics.avg_stay, exact.avg_stay()));
+        assert!(close(metrics.avg_servers_off, exact.probability_p_off()));
+        assert!(close(
+            metrics.avg_servers_setup,
+            exact.probability_p_setup()
+        ));
+    }
+
+    #[test]
+    fn exponential_queue_matches_the_closed_form() {
+        for rho in [0.3, 0.7, 0.99] {
+            assert_matches_setup_queue(
+                1.0,
+                PhaseType::exponential(1.0 / rho),
+                PhaseType::exponential(0.6),
+            );
+        }
+    }
+
+    #[test]
+    fn phase_type_queue_matches_the_closed_form() {
+        assert_matches_setup_queue(
+            1.0,
+            PhaseType::erlang(3, 3.0 / 0.8),
+            PhaseType::hyperexponential(&[0.2, 0.8], &[0.5, 2.0]),
+        );
+    }
+
+    #[test]
+    fn exponential_setup_farm_matches_the_markovian_farm() {
+        let (lambda, mu, theta, servers) = (2.0, 1.0, 0.6, 3);
+        let (_, metrics) = PhaseTypeSetupFarm {
+            lambda,
+            mu,
+            servers,
+            setup: PhaseType::exponential(theta),
+        }
+        .solve();
+        let truncated = MarkovianFarm {
+            lambda,
+            mu,
+            theta,
+            policy: Policy {
+                servers,
+                idle_timer: 0.0,
+                threshold: 1,
+            },
+            capacity: 150,
+        }
+        .solve();
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9 * b.abs().max(1.0);
+        assert!(close(
+            metrics.avg_jobs_in_system,
+            truncated.avg_jobs_in_system
+        ));
+        assert!(close(
+            metrics.avg_jobs_in_queue,
+            truncated.avg_jobs_in_queue
+        ));
+        assert!(close(metrics.avg_servers_off, truncated.avg_servers_off));
+        assert!(close(
+            metrics.avg_servers_setup,
+            truncated.avg_servers_setup
+        ));
+        assert!(close(metrics.avg_servers_busy, truncated.avg_servers_busy));
+        assert!(close(metrics.switch_on_rate, truncated.switch_on_rate));
+    }
+
+    #[test]
+    #[should_panic]
+    fn farm_rejects_the_setups_that_are_not_erlang_ordered() {
+        PhaseTypeSetupFarm {
+            lambda: 1.0,
+            mu: 1.0,
+            servers: 2,
+            setup: PhaseType::hyperexponential(&[0.2, 0.8], &[0.5, 2.0]),
+        }
+        .solve();
+    }
+}
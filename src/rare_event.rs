@@ -0,0 +1,308 @@
+//! The `rare_event.rs` file contains the simulations needed to estimate the probability of very long
+//! stays, like `P(S > 100 𝔼[S])`, that the crude Monte Carlo never observes.
+//!
+//! Two techniques are available, both working on the regeneration cycles of the queue (from a
+//! client that finds the server off to the next one):
+//!
+//! * importance sampling: the inter-arrival and service laws are exponentially tilted so that the
+//!   queue becomes unstable and the long stays frequent, and every client is weighted by its
+//!   likelihood ratio;
+//! * splitting (RESTART): the simulation is cloned every time the stay of a client crosses a level
+//!   upwards, and the clones are killed when they fall back below it.
+use crate::simulation::{single_server_with_warmups, Simulation};
+use crate::variables::{generator_with, Parameter};
+use rand::Rng;
+
+/// The exponent `γ > 0` of the tail of the stay, the Cramér root of
+/// `κ_B(γ) + κ_A(-γ) = 0` (`κ` being the cumulants of the service and inter-arrival laws): the
+/// probability of a stay longer than `x` decreases like `e^(-γx)`.
+///
+/// Tilting the service law by `γ` and the inter-arrival law by `-γ` is the asymptotically optimal
+/// importance sampling of the long stays.
+pub fn cramer_root(inter_arrival_param: &Parameter, service_param: &Parameter) -> f64 {
+    let balance = |gamma: f64| service_param.cumulant(gamma) + inter_arrival_param.cumulant(-gamma);
+    assert!(
+        service_param.moments().first < inter_arrival_param.moments().first,
+        "the queue must be stable"
+    );
+
+    // the balance is convex, negative between 0 and the root, and explodes at the rate of the
+    // service law (at its smallest rate for the hyper-exponential law)
+    let explosion = match service_param {
+        Parameter::HyperExponential(p) => p.lambda_1.min(p.lambda_2),
+        _ => service_param.rate(),
+    };
+    let (mut low, mut high) = (0.0, explosion);
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if balance(middle) < 0.0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// The `tilted_queue` function simulates `n` clients going through the queue with the
+/// inter-arrival law tilted by `-gamma` and the service law tilted by `gamma` (the warmups are not
+/// tilted), and weights every client by its likelihood ratio.
+///
+/// The tilt is applied from the beginning of every regeneration cycle until a client stays longer
+/// than `level`, then the original laws are used again so that the cycle ends (with `gamma` equal
+/// to the Cramér root, the tilted queue is unstable). The weight of a client is the likelihood
+/// ratio of the values drawn since the beginning of its cycle.
+///
+/// Only the weighted methods of the returned simulation (like `weighted_stay_tail_by_cycle`) are
+/// meaningful.
+///
+/// Arguments:
+///
+/// * `n`: The number of clients that will enter the system.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+/// * `gamma`: The tilt, usually the Cramér root.
+/// * `level`: The stay after which the tilt is stopped, usually the `x` of the targeted `P(S > x)`.
+///
+/// Returns:
+///
+/// A weighted `Simulation`.
+pub fn tilted_queue(
+    n: usize,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+    gamma: f64,
+    level: f64,
+) -> Simulation {
+    let rng = &mut rand::thread_rng();
+    let tilted_inter_arrival_param = inter_arrival_param.tilted(-gamma);
+    let tilted_service_param = service_param.tilted(gamma);
+    // the logarithm of the likelihood ratio of a value drawn from the tilted law
+    let log_ratio = |law: &Parameter, theta: f64, x: f64| law.cumulant(theta) - theta * x;
+
+    let mut arrivals = Vec::with_capacity(n);
+    let mut services = Vec::with_capacity(n);
+    let mut warmups = Vec::with_capacity(n);
+    let mut weights = Vec::with_capacity(n);
+
+    let mut arrival = 0.0;
+    let mut previous_departure = 0.0;
+    let mut tilting = true;
+    let mut log_weight = 0.0;
+    for _ in 0..n {
+        let (gap, gap_log_ratio) = if tilting {
+            let gap = generator_with(&tilted_inter_arrival_param, 1, rng)[0];
+            (gap, log_ratio(inter_arrival_param, -gamma, gap))
+        } else {
+            (generator_with(inter_arrival_param, 1, rng)[0], 0.0)
+        };
+        arrival += gap;
+
+        // a client that finds the server off opens a new cycle, that does not depend on the past
+        // (the gap before it included)
+        let warmup = if arrival > previous_departure {
+            tilting = true;
+            log_weight = 0.0;
+            generator_with(warming_up_param, 1, rng)[0]
+        } else {
+            log_weight += gap_log_ratio;
+            0.0
+        };
+
+        let service = if tilting {
+            let service = generator_with(&tilted_service_param, 1, rng)[0];
+            log_weight += log_ratio(service_param, gamma, service);
+            service
+        } else {
+            generator_with(service_param, 1, rng)[0]
+        };
+
+        let departure = arrival.max(previous_departure) + warmup + service;
+        if departure - arrival > level {
+            tilting = false;
+        }
+
+        arrivals.push(arrival);
+        services.push(service);
+        warmups.push(warmup);
+        weights.push(f64::exp(log_weight));
+        previous_departure = departure;
+    }
+
+    single_server_with_warmups(arrivals, services, &warmups).with_weights(weights)
+}
+
+/// The levels of the splitting and the number of clones made at each of them.
+///
+/// Properties:
+///
+/// * `thresholds`: The increasing stays `T_1 < ... < T_m` that trigger the splitting.
+/// * `retrials`: The number `R_k` of copies of a trial that crosses `T_k` upwards (itself included).
+pub struct Splitting {
+    pub thresholds: Vec<f64>,
+    pub retrials: Vec<usize>,
+}
+
+impl Splitting {
+    /// `m` levels evenly spaced up to `x` (included), each one with `retrials` copies.
+    pub fn evenly_spaced(x: f64, m: usize, retrials: usize) -> Self {
+        Splitting {
+            thresholds: (1..=m).map(|k| k as f64 * x / m as f64).collect(),
+            retrials: vec![retrials; m],
+        }
+    }
+
+    /// The number of thresholds below a stay, that is its region.
+    fn region(&self, stay: f64) -> usize {
+        self.thresholds.iter().filter(|&&t| stay >= t).count()
+    }
+
+    /// The weight of a client whose stay is in a given region: the inverse of the number of copies
+    /// made to reach it.
+    fn weight(&self, region: usize) -> f64 {
+        1.0 / self.retrials[..region]
+            .iter()
+            .map(|&r| r as f64)
+            .product::<f64>()
+    }
+}
+
+/// The trials of the RESTART splitting, with everything they share.
+///
+/// Properties:
+///
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `splitting`: The levels and the numbers of copies.
+/// * `x`: The stay whose tail is estimated.
+struct Trials<'a> {
+    inter_arrival_param: &'a Parameter,
+    service_param: &'a Parameter,
+    splitting: &'a Splitting,
+    x: f64,
+}
+
+impl Trials<'_> {
+    /// Run one trial from the departure of a client, until the end of the regeneration cycle or
+    /// until it is killed.
+    ///
+    /// Arguments:
+    ///
+    /// * `arrival`, `departure`: The arrival and departure of the last client.
+    /// * `birth`: The level at which the trial was born, it is killed when a stay falls below its
+    ///   threshold (`0` for the main trial, which is never killed).
+    /// * `region`: The region of the last client.
+    ///
+    /// Returns:
+    ///
+    /// The weighted number of clients staying longer than `x`, over this trial and its copies.
+    fn run<R: Rng + ?Sized>(
+        &self,
+        mut arrival: f64,
+        mut departure: f64,
+        birth: usize,
+        mut region: usize,
+        rng: &mut R,
+    ) -> f64 {
+        let mut reward = 0.0;
+        loop {
+            arrival += generator_with(self.inter_arrival_param, 1, rng)[0];
+            if arrival > departure {
+                return reward; // the cycle is over
+            }
+            departure += generator_with(self.service_param, 1, rng)[0];
+            reward += self.visit(arrival, departure, birth, &mut region, rng);
+            if region < birth {
+                return reward;
+            }
+        }
+    }
+
+    /// Handle the departure of a client during a trial: the copies when its stay crossed some
+    /// thresholds upwards, and its contribution to the reward. The region is updated.
+    fn visit<R: Rng + ?Sized>(
+        &self,
+        arrival: f64,
+        departure: f64,
+        birth: usize,
+        region: &mut usize,
+        rng: &mut R,
+    ) -> f64 {
+        let stay = departure - arrival;
+        let new_region = self.splitting.region(stay);
+        let mut reward = 0.0;
+        // every threshold crossed at once is handled in turn: the copies made at a level are
+        // themselves split at the next ones
+        while *region < new_region {
+            let level = *region + 1;
+            for _ in 1..self.splitting.retrials[level - 1] {
+                let mut copy_region = level;
+                reward += self.visit(arrival, departure, level, &mut copy_region, rng);
+                reward += self.run(arrival, departure, level, copy_region, rng);
+            }
+            *region = level;
+        }
+        *region = new_region;
+
+        if new_region >= birth && stay > self.x {
+            reward += self.splitting.weight(new_region);
+        }
+        reward
+    }
+}
+
+/// The `restart_queue` function estimates the expected number of clients staying longer than `x`
+/// by regeneration cycle, with the RESTART splitting.
+///
+/// Every cycle starts with a client finding the server off. Its main trial runs until the cycle is
+/// over; when the stay of a client crosses the threshold `T_k` upwards, `R_k - 1` copies of the
+/// trial are started from the departure of this client, each one with its own future, and are
+/// killed as soon as a stay falls below `T_k`. A client whose stay is in the region `k` (above
+/// `T_k`, below `T_(k+1)`) counts for `1 / (R_1 ... R_k)`.
+///
+/// The levels must be at most `x`. They are best spaced so that a trial starting from one of them
+/// reaches the next one with a probability of about `1 / R`.
+///
+/// Arguments:
+///
+/// * `cycles`: The number of regeneration cycles.
+/// * `inter_arrival_param`: The parameter of the law that will describe the inter-arrival rate.
+/// * `service_param`: The parameter of the law that will describe the services times.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+/// * `splitting`: The levels and the numbers of copies.
+/// * `x`: The stay whose tail is estimated.
+///
+/// Returns:
+///
+/// The weighted number of clients staying longer than `x`, for every cycle.
+pub fn restart_queue(
+    cycles: usize,
+    inter_arrival_param: &Parameter,
+    service_param: &Parameter,
+    warming_up_param: &Parameter,
+    splitting: &Splitting,
+    x: f64,
+) -> Vec<f64> {
+    assert_eq!(splitting.thresholds.len(), splitting.retrials.len());
+    assert!(splitting.thresholds.last().is_none_or(|&t| t <= x));
+    let rng = &mut rand::thread_rng();
+    let trials = Trials {
+        inter_arrival_param,
+        service_param,
+        splitting,
+        x,
+    };
+
+    (0..cycles)
+        .map(|_| {
+            // the first client of the cycle finds the server off
+            let departure = generator_with(warming_up_param, 1, rng)[0]
+                + generator_with(service_param, 1, rng)[0];
+            let mut region = 0;
+            trials.visit(0.0, departure, 0, &mut region, rng)
+                + trials.run(0.0, departure, 0, region, rng)
+        })
+        .collect()
+}
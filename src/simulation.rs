@@ -1,6 +1,11 @@
 //! The `simulation.rs` contains the struct `Simulation` used to store values of a simulation and
 //! some function associated, to compute more metrics.
-use crate::measures::Mean;
+use crate::measures::{
+    batch_means, overlapping_batch_means, regenerative, sorted_quantile, Estimate, Mean,
+};
+use crate::variables::{generator, Parameter};
+use std::ops::Range;
+use std::sync::OnceLock;
 
 /// The simulation struct groups valuable information needed for further processing.
 ///
@@ -8,16 +13,24 @@ use crate::measures::Mean;
 ///
 /// * `arrivals`: List of the clients arrivals, the exact time at which they entered the system.
 /// * `delays`: List of delays encountered, for every client entered in the system. If a client did
-/// not encounter delay, the value for its index will be `0.0`.
+///   not encounter delay, the value for its index will be `0.0`.
 /// * `warmups`: List of the durations spent by clients waiting for the server to warmup. If a
-/// client dit not encounter this kind of duration, the value for its index will be `0.0`. It is
-/// impossible for a client of index `i` to have both a value in `delays[i]` and `warmups[i]`.
+///   client dit not encounter this kind of duration, the value for its index will be `0.0`. It is
+///   impossible for a client of index `i` to have both a value in `delays[i]` and `warmups[i]`.
 /// * `services`: List of the durations spent by clients during their service time.
 /// * `nap_times`: List of duration that the server spent off. These duration are not really linked
-/// to any client, because it is a measure of time spent while they were not in the system. Still,
-/// this information is useful to calculate the total time that the server spent off line.
+///   to any client, because it is a measure of time spent while they were not in the system. Still,
+///   this information is useful to calculate the total time that the server spent off line.
 /// * `departures`: List of the clients departure, the exact moments at which they left the system.
 /// * `total_time`: The total time, from the beginning to the end.
+/// * `start_time`: The beginning of the observation window, `0.0` unless the first jobs were
+///   deleted to remove the initial transient.
+/// * `horizon`: When the simulation is stopped at a fixed end time instead of after a fixed number
+///   of arrivals, this end time. Everything that happens after it is ignored.
+/// * `weights`: The likelihood ratio of every client, when the inputs were drawn from other laws
+///   than the simulated ones (importance sampling), `1.0` otherwise.
+/// * `sorted_stays`, `sorted_waitings`: The stay and waiting times of the completed clients, sorted
+///   the first time a percentile is asked for, and kept for the next ones.
 pub struct Simulation {
     arrivals: Vec<f64>,
     delays: Vec<f64>,
@@ -27,6 +40,38 @@ pub struct Simulation {
     // computed
     departures: Vec<f64>,
     total_time: Option<f64>,
+    start_time: f64,
+    horizon: Option<f64>,
+    weights: Vec<f64>,
+    // cached
+    sorted_stays: OnceLock<Vec<f64>>,
+    sorted_waitings: OnceLock<Vec<f64>>,
+}
+
+/// One cycle of the server: it naps (off) until a client arrives, warms up, then serves clients
+/// until the system is empty again.
+///
+/// Properties:
+///
+/// * `nap`: The duration the server spent off, before the arrival that woke it up.
+/// * `setup`: The duration of the warmup.
+/// * `busy`: The duration of the busy period, from the end of the warmup to the departure of the
+///   last client of the cycle.
+/// * `jobs`: The number of clients served during the busy period.
+#[derive(Copy, Clone, Debug)]
+pub struct Cycle {
+    pub nap: f64,
+    pub setup: f64,
+    pub busy: f64,
+    pub jobs: usize,
+}
+
+/// The random inputs of a simulation, whose laws may be perturbed to compute sensitivities.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Input {
+    InterArrival,
+    Service,
+    Warmup,
 }
 
 /// Constructor & static functions
@@ -37,14 +82,14 @@ impl Simulation {
     /// Arguments:
     /// * `arrivals`: List of the clients arrivals, the exact time at which they entered the system.
     /// * `delays`: List of delays encountered, for every client entered in the system. If a client did
-    /// not encounter delay, the value for its index will be `0.0`.
+    ///   not encounter delay, the value for its index will be `0.0`.
     /// * `warmups`: List of the durations spent by clients waiting for the server to warmup. If a
-    /// client dit not encounter this kind of duration, the value for its index will be `0.0`. It is
-    /// impossible for a client of index `i` to have both a value in `delays[i]` and `warmups[i]`.
+    ///   client dit not encounter this kind of duration, the value for its index will be `0.0`. It is
+    ///   impossible for a client of index `i` to have both a value in `delays[i]` and `warmups[i]`.
     /// * `services`: List of the durations spent by clients during their service time.
     /// * `nap_times`: List of duration that the server spent off. These duration are not really linked
-    /// to any client, because it is a measure of time spent while they were not in the system. Still,
-    /// this information is useful to calculate the total time that the server spent off line.
+    ///   to any client, because it is a measure of time spent while they were not in the system. Still,
+    ///   this information is useful to calculate the total time that the server spent off line.
     ///
     /// Returns:
     ///
@@ -62,7 +107,8 @@ impl Simulation {
         assert_eq!(arrivals.len(), services.len());
 
         let departures = Self::departures(&arrivals, &delays, &warmups, &services);
-        let total_time = departures.last().map(|v| v.clone());
+        let total_time = departures.last().copied();
+        let weights = vec![1.0; arrivals.len()];
         Self {
             arrivals,
             delays,
@@ -71,6 +117,68 @@ impl Simulation {
             nap_times,
             departures,
             total_time,
+            start_time: 0.0,
+            horizon: None,
+            weights,
+            sorted_stays: OnceLock::new(),
+            sorted_waitings: OnceLock::new(),
+        }
+    }
+
+    /// Attach the likelihood ratio of every client, for a simulation whose inputs were drawn from
+    /// other laws than the simulated ones (importance sampling).
+    ///
+    /// The weight of a client must be the likelihood ratio of everything that was drawn since the
+    /// beginning of its regeneration cycle, up to its departure. Only the weighted methods (like
+    /// `weighted_stay_tail_by_cycle`) take the weights into account.
+    pub fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        assert_eq!(self.arrivals.len(), weights.len());
+        self.weights = weights;
+        self
+    }
+
+    /// Cut the simulation at a fixed end time `horizon`.
+    ///
+    /// The busy, setup and off periods that are still in progress at the horizon are cut, the
+    /// server is considered off between the last departure and the horizon, and the jobs that are
+    /// still in the system at the end are not taken into account for the stay times.
+    /// No client may have arrived after the horizon.
+    pub fn with_horizon(mut self, horizon: f64) -> Self {
+        assert!(self.arrivals.iter().all(|&a| a <= horizon));
+        self.total_time = Some(horizon);
+        self.horizon = Some(horizon);
+        // the clients still in the system at the horizon are not completed anymore
+        self.sorted_stays = OnceLock::new();
+        self.sorted_waitings = OnceLock::new();
+        self
+    }
+
+    /// Delete the `d` first jobs of the simulation, to get rid of the initial transient.
+    ///
+    /// The observation window of the truncated simulation starts at the departure of the last
+    /// deleted job: from this moment, everything that happens to the server is linked to the kept
+    /// jobs (the nap before the first kept job included). With a horizon, the last deleted job
+    /// must have left before it, otherwise nothing would be left to observe.
+    pub fn truncated(&self, d: usize) -> Self {
+        assert!(d < self.arrivals.len());
+        assert!(d == 0 || self.departures[d - 1] < self.total_time.unwrap());
+        Self {
+            arrivals: self.arrivals[d..].to_vec(),
+            delays: self.delays[d..].to_vec(),
+            warmups: self.warmups[d..].to_vec(),
+            services: self.services[d..].to_vec(),
+            nap_times: self.nap_times[d..].to_vec(),
+            departures: self.departures[d..].to_vec(),
+            weights: self.weights[d..].to_vec(),
+            total_time: self.total_time,
+            start_time: if d == 0 {
+                self.start_time
+            } else {
+                self.departures[d - 1]
+            },
+            horizon: self.horizon,
+            sorted_stays: OnceLock::new(),
+            sorted_waitings: OnceLock::new(),
         }
     }
 
@@ -101,40 +209,573 @@ impl Simulation {
 
 /// Methods of the Simulation struct
 impl Simulation {
+    /// The exact times at which the clients entered the system.
+    pub fn arrival_times(&self) -> &[f64] {
+        &self.arrivals
+    }
+
+    /// The exact times at which the clients left the system.
+    pub fn departure_times(&self) -> &[f64] {
+        &self.departures
+    }
+
+    /// The time spent in the node by every client, from its arrival to its departure.
+    pub fn stay_times(&self) -> Vec<f64> {
+        self.arrivals
+            .iter()
+            .zip(&self.departures)
+            .map(|(&a, &d)| d - a)
+            .collect()
+    }
+
+    /// The stay times of the clients that left the system before the end of the simulation (all of
+    /// them, unless the simulation has a horizon), in their order of arrival.
+    pub fn completed_stay_times(&self) -> Vec<f64> {
+        self.completed_jobs()
+            .map(|i| self.departures[i] - self.arrivals[i])
+            .collect()
+    }
+
+    /// The length of the observation window.
+    fn duration(&self) -> f64 {
+        self.total_time.unwrap() - self.start_time
+    }
+
     /// Calculate the mean service time.
     pub fn avg_service(&self) -> f64 {
         self.services.iter().calculate_mean()
     }
 
+    /// Calculate the mean inter-arrival time (from the start of the simulation to the first
+    /// arrival, then between two arrivals).
+    pub fn avg_inter_arrival(&self) -> f64 {
+        self.inputs(Input::InterArrival).iter().calculate_mean()
+    }
+
     /// The stay time has sometimes the confusing name "waiting time".
     ///
     /// It's the average time that a job spends in the node (delay or startup + service time),
     /// from its arrival to its departure.
+    ///
+    /// Only the clients that left the system before the end of the simulation are taken into
+    /// account.
     pub fn avg_stay(&self) -> f64 {
+        let end = self.total_time.unwrap();
         self.arrivals
             .iter()
             .zip(&self.departures)
+            .filter(|(_, &d)| d <= end)
             .map(|(&a, &d)| d - a)
             .calculate_mean()
     }
 
+    /// The throughput is the number of clients served by unit of time.
+    pub fn throughput(&self) -> f64 {
+        let end = self.total_time.unwrap();
+        self.departures.iter().filter(|&&d| d <= end).count() as f64 / self.duration()
+    }
+
+    /// The number of times the server was switched on by unit of time, that is the number of
+    /// warmups that started during the observation window.
+    pub fn switch_on_rate(&self) -> f64 {
+        let end = self.total_time.unwrap();
+        self.arrivals
+            .iter()
+            .zip(&self.warmups)
+            .filter(|(&a, &w)| w > 0.0 && a >= self.start_time && a <= end)
+            .count() as f64
+            / self.duration()
+    }
+
+    /// The number of clients that are still in the system at the end of the simulation.
+    /// Without a horizon, every client has left so it is always `0`.
+    pub fn jobs_in_system_at_end(&self) -> usize {
+        let end = self.total_time.unwrap();
+        self.departures.iter().filter(|&&d| d > end).count()
+    }
+
+    /// The part of the interval `[start, end]` that happened before the end of the simulation.
+    fn clipped_duration(&self, start: f64, end: f64) -> f64 {
+        let total_time = self.total_time.unwrap();
+        (end.min(total_time) - start.min(total_time)).max(0.0)
+    }
+
     /// Calculate the probability of arriving and finding the server in an active state.
     /// The idea is to return the ratio of the time when the server was on by the total time spent.
     pub fn probability_server_on(&self) -> f64 {
-        self.services.iter().sum::<f64>() / self.total_time.unwrap()
+        self.services
+            .iter()
+            .zip(&self.departures)
+            .map(|(&s, &d)| self.clipped_duration(d - s, d))
+            .sum::<f64>()
+            / self.duration()
     }
 
     /// Calculate the probability of arriving and finding the server in an warming state.
     /// The idea is to return the ratio of the time when the server was warming up by the total
     /// time spent.
     pub fn probability_server_setup(&self) -> f64 {
-        self.warmups.iter().sum::<f64>() / self.total_time.unwrap()
+        self.warmups
+            .iter()
+            .zip(&self.arrivals)
+            .map(|(&w, &a)| self.clipped_duration(a, a + w))
+            .sum::<f64>()
+            / self.duration()
     }
 
     /// Calculate the probability of arriving and finding the server in an off state.
     /// The idea is to return the ratio of the time when the server was off by the total time spent.
+    ///
+    /// With a horizon, the server is also off from the last departure to the horizon.
     pub fn probability_server_off(&self) -> f64 {
-        self.nap_times.iter().sum::<f64>() / self.total_time.unwrap()
+        let last_nap = match (self.horizon, self.departures.last()) {
+            (Some(horizon), Some(&last_departure)) => (horizon - last_departure).max(0.0),
+            (Some(horizon), None) => horizon - self.start_time,
+            _ => 0.0,
+        };
+        (self.nap_times.iter().sum::<f64>() + last_nap) / self.duration()
+    }
+
+    /// Build a piecewise-constant sample path counting the clients that entered and did not exit
+    /// yet, over the observation window.
+    ///
+    /// Returns:
+    ///
+    /// The list of the jumps of the path: from each time, the count keeps the given value until
+    /// the next jump (or the end of the simulation). The first jump is at the start of the window.
+    fn sample_path(&self, entries: &[f64], exits: &[f64]) -> Vec<(f64, usize)> {
+        let end = self.total_time.unwrap();
+        let mut events = entries
+            .iter()
+            .map(|&t| (t, 1))
+            .chain(exits.iter().map(|&t| (t, -1)))
+            .collect::<Vec<(f64, i64)>>();
+        // at the same time, entries come first so that the count never goes below zero
+        events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then(b.1.cmp(&a.1)));
+
+        let mut count: i64 = 0;
+        let mut path = vec![(self.start_time, 0)];
+        for (t, step) in events {
+            if t > end {
+                break;
+            }
+            count += step;
+            let last = path.last_mut().unwrap();
+            if t <= last.0 {
+                last.1 = count as usize;
+            } else {
+                path.push((t, count as usize));
+            }
+        }
+        path
+    }
+
+    /// The sample path of `N(t)`, the number of clients in the system (waiting, or in service).
+    pub fn jobs_in_system_path(&self) -> Vec<(f64, usize)> {
+        self.sample_path(&self.arrivals, &self.departures)
+    }
+
+    /// The sample path of `N_q(t)`, the number of clients waiting in the queue (for the clients
+    /// before them, or for the warmup of the server).
+    pub fn jobs_in_queue_path(&self) -> Vec<(f64, usize)> {
+        let service_starts = self
+            .departures
+            .iter()
+            .zip(&self.services)
+            .map(|(d, s)| d - s)
+            .collect::<Vec<_>>();
+        self.sample_path(&self.arrivals, &service_starts)
+    }
+
+    /// The time spent in every state of a sample path, until the end of the simulation.
+    fn time_in_states(&self, path: &[(f64, usize)]) -> Vec<(usize, f64)> {
+        let end = self.total_time.unwrap();
+        path.iter()
+            .zip(path.iter().skip(1).map(|&(t, _)| t).chain(Some(end)))
+            .map(|(&(t, n), next)| (n, next - t))
+            .collect()
+    }
+
+    /// The time average of `N(t)`, the average number of clients in the system.
+    pub fn avg_jobs_in_system(&self) -> f64 {
+        self.time_in_states(&self.jobs_in_system_path())
+            .iter()
+            .map(|&(n, d)| n as f64 * d)
+            .sum::<f64>()
+            / self.duration()
+    }
+
+    /// The time average of `N_q(t)`, the average number of clients waiting in the queue.
+    pub fn avg_jobs_in_queue(&self) -> f64 {
+        self.time_in_states(&self.jobs_in_queue_path())
+            .iter()
+            .map(|&(n, d)| n as f64 * d)
+            .sum::<f64>()
+            / self.duration()
+    }
+
+    /// The time-weighted distribution of `N(t)`: the element `n` is the fraction of the time
+    /// spent with `n` clients in the system.
+    pub fn jobs_in_system_distribution(&self) -> Vec<f64> {
+        let mut distribution: Vec<f64> = Vec::new();
+        for (n, d) in self.time_in_states(&self.jobs_in_system_path()) {
+            if distribution.len() <= n {
+                distribution.resize(n + 1, 0.0);
+            }
+            distribution[n] += d / self.duration();
+        }
+        distribution
+    }
+
+    /// The number of clients in the system at time `t`, `N(t)`.
+    pub fn jobs_in_system_at(&self, t: f64) -> usize {
+        // the clients leave in their order of arrival, so both lists are sorted
+        self.arrivals.partition_point(|&a| a <= t) - self.departures.partition_point(|&d| d <= t)
+    }
+
+    /// Whether the server is off at time `t`, that is whether the system is empty, as the server
+    /// turns off as soon as it has no client.
+    pub fn is_server_off_at(&self, t: f64) -> bool {
+        self.jobs_in_system_at(t) == 0
+    }
+
+    /// The stay times of the clients arriving in the interval `[start, end)`.
+    pub fn stays_of_arrivals_between(&self, start: f64, end: f64) -> Vec<f64> {
+        let clients = self.arrivals.partition_point(|&a| a < start)
+            ..self.arrivals.partition_point(|&a| a < end);
+        clients
+            .map(|i| self.departures[i] - self.arrivals[i])
+            .collect()
+    }
+
+    /// How far the gap between the two sides of Little's law, `L - λ W`, falls outside of the
+    /// bounds given by the clients at the edges of the observation window, in clients. `L` is the
+    /// time average of the sample path of `N(t)` over the window of duration `T`, `λ` the number
+    /// of arrivals in the window divided by `T`, and `W` the average stay of the clients that
+    /// arrived and left during the window.
+    ///
+    /// On any sample path, `L T` is the sum of the stays of these clients plus the time spent in
+    /// the window by the clients at its edges: the ones present at its start, and the ones that
+    /// arrived during it but are still there at its end. The latter are also counted by `λ`, each
+    /// bringing `W / T` to the right side. So `L - λ W` lies between `-(A - C) W / T` and
+    /// `(E - (A - C) W) / T`, where `A - C` is the number of clients that arrived but did not
+    /// leave during the window and `E` the time spent in it by the clients at its edges.
+    ///
+    /// Returns:
+    ///
+    /// `0` when the gap is within the bounds, up to rounding errors (see `LITTLES_LAW_TOLERANCE`),
+    /// otherwise the distance to the nearest bound: the sample path and the stay times of the
+    /// simulation are then inconsistent.
+    pub fn littles_law_discrepancy(&self) -> f64 {
+        let (start, end) = (self.start_time, self.total_time.unwrap());
+        let clients = self.arrivals.iter().zip(&self.departures);
+        let in_window = clients
+            .clone()
+            .filter(|(&a, _)| a >= start && a <= end)
+            .collect::<Vec<_>>();
+        let completed = in_window
+            .iter()
+            .filter(|(_, &d)| d <= end)
+            .map(|(&a, &d)| d - a)
+            .collect::<Vec<_>>();
+        let arrival_rate = in_window.len() as f64 / self.duration();
+        let avg_stay = if completed.is_empty() {
+            0.0
+        } else {
+            completed.iter().calculate_mean()
+        };
+        let edge_time = clients
+            .filter(|(&a, &d)| (a < start && d > start) || (a >= start && a <= end && d > end))
+            .map(|(&a, &d)| d.min(end) - a.max(start))
+            .sum::<f64>();
+
+        let gap = self.avg_jobs_in_system() - arrival_rate * avg_stay;
+        let lower = -((in_window.len() - completed.len()) as f64) * avg_stay / self.duration();
+        let upper = lower + edge_time / self.duration();
+        (gap - upper).max(0.0) + (lower - gap).max(0.0)
+    }
+
+    /// Split the simulation into its regeneration cycles. A cycle starts every time a client
+    /// arrives in an empty system, with the server off: from then on, the future does not depend
+    /// on the past anymore. The simulation starts empty and off, so the first client also opens a
+    /// cycle (unless the beginning of the simulation was truncated).
+    ///
+    /// Returns:
+    ///
+    /// The range of the indexes of the clients served during every cycle.
+    pub fn regeneration_cycles(&self) -> Vec<Range<usize>> {
+        let mut starts = self
+            .nap_times
+            .iter()
+            .enumerate()
+            .filter(|(i, &nap)| nap > 0.0 || (*i == 0 && self.start_time == 0.0))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        starts.push(self.arrivals.len());
+        starts.windows(2).map(|w| w[0]..w[1]).collect()
+    }
+
+    /// The off → setup → busy cycles of the server.
+    ///
+    /// The first cycle is dropped when it started before the observation window, and so are the
+    /// cycles that are not over at the end of the simulation. With a horizon, these are the ones
+    /// still busy at the horizon. Without one, it is always the last cycle: no client arrives
+    /// after the last one, so its busy period is cut short.
+    pub fn cycles(&self) -> Vec<Cycle> {
+        let end = self.total_time.unwrap();
+        let mut cycles = self.regeneration_cycles();
+        if self.horizon.is_none() {
+            cycles.pop();
+        }
+        cycles
+            .into_iter()
+            .filter(|c| self.departures[c.end - 1] <= end)
+            .map(|c| {
+                let busy_start = self.arrivals[c.start] + self.warmups[c.start];
+                Cycle {
+                    nap: self.nap_times[c.start],
+                    setup: self.warmups[c.start],
+                    busy: self.departures[c.end - 1] - busy_start,
+                    jobs: c.len(),
+                }
+            })
+            .collect()
+    }
+
+    /// The weighted number of clients whose stay is longer than `x`, for every regeneration
+    /// cycle: `∑ w 1{S > x}` over the clients of the cycle.
+    ///
+    /// With importance sampling, this is an unbiased estimation of the expected number of such
+    /// clients by cycle under the simulated laws. The last cycle is dropped, as it may not be over.
+    pub fn weighted_stay_tail_by_cycle(&self, x: f64) -> Vec<f64> {
+        let cycles = self.regeneration_cycles();
+        cycles[..cycles.len().saturating_sub(1)]
+            .iter()
+            .map(|c| {
+                c.clone()
+                    .filter(|&i| self.departures[i] - self.arrivals[i] > x)
+                    .map(|i| self.weights[i])
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The batch means estimation of the average stay, over `batches` non-overlapping batches of
+    /// consecutive clients.
+    pub fn batch_means_avg_stay(&self, batches: usize) -> Estimate {
+        batch_means(&self.stay_times(), batches)
+    }
+
+    /// The overlapping batch means estimation of the average stay, with batches of `batch_size`
+    /// consecutive clients.
+    pub fn overlapping_batch_means_avg_stay(&self, batch_size: usize) -> Estimate {
+        overlapping_batch_means(&self.stay_times(), batch_size)
+    }
+
+    /// The regenerative estimation of the average stay: the total stay of the clients of a cycle
+    /// divided by the number of clients of the cycle.
+    pub fn regenerative_avg_stay(&self) -> Estimate {
+        let stays = self.stay_times();
+        let cycles = self.regeneration_cycles();
+        let rewards = cycles
+            .iter()
+            .map(|c| stays[c.clone()].iter().sum::<f64>())
+            .collect::<Vec<_>>();
+        let lengths = cycles.iter().map(|c| c.len() as f64).collect::<Vec<_>>();
+        regenerative(&rewards, &lengths)
+    }
+
+    /// The regenerative estimation of the probability of finding the server off: the nap that
+    /// opens a cycle divided by the duration of the cycle (from the departure of the last client
+    /// of the previous cycle to the departure of its own last client).
+    pub fn regenerative_probability_server_off(&self) -> Estimate {
+        let cycles = self.regeneration_cycles();
+        let rewards = cycles
+            .iter()
+            .map(|c| self.nap_times[c.start])
+            .collect::<Vec<_>>();
+        let lengths = cycles
+            .iter()
+            .map(|c| {
+                let beginning = if c.start == 0 {
+                    self.start_time
+                } else {
+                    self.departures[c.start - 1]
+                };
+                self.departures[c.end - 1] - beginning
+            })
+            .collect::<Vec<_>>();
+        regenerative(&rewards, &lengths)
+    }
+
+    /// The indexes of the clients that left the system before the end of the simulation.
+    fn completed_jobs(&self) -> impl Iterator<Item = usize> + '_ {
+        let end = self.total_time.unwrap();
+        (0..self.departures.len()).filter(move |&i| self.departures[i] <= end)
+    }
+
+    /// The time waited by every client before its service, because of the clients served before
+    /// him (delay) or because of the warmup of the server.
+    pub fn waiting_times(&self) -> Vec<f64> {
+        self.delays
+            .iter()
+            .zip(&self.warmups)
+            .map(|(d, w)| d + w)
+            .collect()
+    }
+
+    /// The `p`-quantile of the stay times of the clients that left the system before the end of the
+    /// simulation, or `None` when no client did.
+    pub fn stay_percentile(&self, p: f64) -> Option<f64> {
+        let sorted = self.sorted_stays.get_or_init(|| {
+            Self::sorted(
+                self.completed_jobs()
+                    .map(|i| self.departures[i] - self.arrivals[i]),
+            )
+        });
+        (!sorted.is_empty()).then(|| sorted_quantile(sorted, p))
+    }
+
+    /// The `p`-quantile of the waiting times of the clients that left the system before the end of
+    /// the simulation, or `None` when no client did.
+    pub fn waiting_percentile(&self, p: f64) -> Option<f64> {
+        let sorted = self.sorted_waitings.get_or_init(|| {
+            Self::sorted(
+                self.completed_jobs()
+                    .map(|i| self.delays[i] + self.warmups[i]),
+            )
+        });
+        (!sorted.is_empty()).then(|| sorted_quantile(sorted, p))
+    }
+
+    /// Collect some values, sorted in increasing order.
+    fn sorted(values: impl Iterator<Item = f64>) -> Vec<f64> {
+        let mut sorted = values.collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted
+    }
+
+    /// The fraction of the clients (among those that left the system) that found the server off,
+    /// and thus paid for its warmup.
+    pub fn fraction_with_warmup(&self) -> f64 {
+        self.completed_jobs()
+            .map(|i| if self.warmups[i] > 0.0 { 1.0 } else { 0.0 })
+            .calculate_mean()
+    }
+
+    /// The average stay time of the clients that paid for the warmup of the server, or `None` when
+    /// no client (among those that left the system) did.
+    pub fn avg_stay_with_warmup(&self) -> Option<f64> {
+        let stays = self
+            .completed_jobs()
+            .filter(|&i| self.warmups[i] > 0.0)
+            .map(|i| self.departures[i] - self.arrivals[i])
+            .collect::<Vec<_>>();
+        if stays.is_empty() {
+            None
+        } else {
+            Some(stays.iter().calculate_mean())
+        }
+    }
+
+    /// The values drawn for an input, in the order of the clients: the inter-arrival times (the
+    /// first one from the start of the simulation, unless it was truncated), the service times, or
+    /// the warmups that happened.
+    fn inputs(&self, input: Input) -> Vec<f64> {
+        match input {
+            Input::InterArrival => {
+                let mut previous = 0.0;
+                let gaps = self.arrivals.iter().map(|&a| {
+                    let gap = a - previous;
+                    previous = a;
+                    gap
+                });
+                // when truncated, the arrival before the first kept client is unknown
+                let unknown = if self.start_time > 0.0 { 1 } else { 0 };
+                gaps.skip(unknown).collect()
+            }
+            Input::Service => self.services.clone(),
+            Input::Warmup => self.warmups.iter().cloned().filter(|&w| w > 0.0).collect(),
+        }
+    }
+
+    /// The infinitesimal perturbation analysis (IPA) of the average stay and of the probability
+    /// of finding the server off, with respect to the rate of the law of an input.
+    ///
+    /// The law must be a scale family: when its rate `r` changes, every value `x` drawn from it
+    /// moves by `dx/dr = -x / r`. Those perturbations are propagated along the Lindley recursion:
+    /// a client that finds the server off leaves at `A + T + B`, another one at `D_prev + B`, and
+    /// the naps are `A - D_prev`.
+    ///
+    /// IPA only sees the smooth part of the sample path. The setups make it jump (a client that
+    /// arrives just after a departure pays a whole setup, one that arrives just before does not),
+    /// so the derivatives are biased unless the setups are short; the likelihood ratio (`score`)
+    /// has no such bias, but a much bigger variance.
+    ///
+    /// When the beginning of the simulation was truncated, the departure of the last deleted
+    /// client is only moved with the time axis; its true perturbation is forgotten at the first
+    /// nap anyway.
+    ///
+    /// Arguments:
+    ///
+    /// * `input`: The input whose law is perturbed.
+    /// * `rate`: The rate of this law.
+    ///
+    /// Returns:
+    ///
+    /// The derivatives of `avg_stay()` and of `probability_server_off()`.
+    pub fn ipa_derivatives(&self, input: Input, rate: f64) -> (f64, f64) {
+        let end = self.total_time.unwrap();
+        let scale = |x: f64, perturbed: bool| if perturbed { -x / rate } else { 0.0 };
+        let d_start = scale(self.start_time, input == Input::InterArrival);
+
+        let mut d_departure = d_start;
+        let mut d_stays = Vec::new();
+        let mut naps = 0.0;
+        let mut d_naps = 0.0;
+        for i in 0..self.arrivals.len() {
+            let d_arrival = scale(self.arrivals[i], input == Input::InterArrival);
+            let d_service = scale(self.services[i], input == Input::Service);
+            if self.nap_times[i] > 0.0 || self.warmups[i] > 0.0 {
+                naps += self.nap_times[i];
+                d_naps += d_arrival - d_departure;
+                let d_warmup = scale(self.warmups[i], input == Input::Warmup);
+                d_departure = d_arrival + d_warmup + d_service;
+            } else {
+                d_departure += d_service;
+            }
+            if self.departures[i] <= end {
+                d_stays.push(d_departure - d_arrival);
+            }
+        }
+
+        // without a horizon the simulation ends with the last departure, with one the server is
+        // off from the last departure to the horizon
+        let d_end = match self.horizon {
+            None => d_departure,
+            Some(horizon) => {
+                if self.departures.last().is_some_and(|&d| d < horizon) {
+                    naps += horizon - self.departures.last().unwrap();
+                    d_naps -= d_departure;
+                }
+                0.0
+            }
+        };
+        let duration = self.duration();
+        let d_duration = d_end - d_start;
+        let d_p_off = (d_naps * duration - naps * d_duration) / duration.powi(2);
+
+        (d_stays.iter().calculate_mean(), d_p_off)
+    }
+
+    /// The score of the simulation with respect to the rate of the law of an input: the sum of
+    /// the scores of all the values drawn for this input. The likelihood ratio (score function)
+    /// estimator of the derivative of `𝔼[f]` is then `f × score`.
+    ///
+    /// When the beginning of the simulation was truncated, the values drawn for the deleted
+    /// clients are ignored.
+    pub fn score(&self, input: Input, law: &Parameter) -> f64 {
+        self.inputs(input).iter().map(|&x| law.score(x)).sum()
     }
 
     /// Used to calculate 𝔼\[W²]
@@ -142,3 +783,110 @@ impl Simulation {
         self.delays.iter().map(|d| d * d).calculate_mean()
     }
 }
+
+/// Run a single server, that follows the off/setup/busy lifecycle, over a given list of arrivals.
+///
+/// This is the heart of the `queue` function, extracted so that other models (like the fork-join
+/// one) can reuse the exact same server behaviour over arrivals they generated themselves.
+///
+/// Arguments:
+///
+/// * `incoming_clients`: The exact times at which the clients enter the node.
+/// * `service_times`: The service time of each client, there must be one by client.
+/// * `warming_up_param`: The parameter of the law that will describe the warmup time.
+///
+/// Returns:
+///
+/// A `Simulation` "object" (it is a struct really) containing all the useful information for
+/// further calculations.
+pub fn single_server(
+    incoming_clients: Vec<f64>,
+    service_times: Vec<f64>,
+    warming_up_param: &Parameter,
+) -> Simulation {
+    let warmup_times = generator(warming_up_param, incoming_clients.len());
+    single_server_with_warmups(incoming_clients, service_times, &warmup_times)
+}
+
+/// Same as `single_server`, but the warmups are given instead of being drawn: the client `i` pays
+/// `warmup_times[i]` if it finds the server off, and the value is ignored otherwise.
+///
+/// Attaching the warmups to the clients keeps them synchronised between simulations fed with the
+/// same (or antithetic) random numbers, whatever clients find the server off.
+pub fn single_server_with_warmups(
+    incoming_clients: Vec<f64>,
+    service_times: Vec<f64>,
+    warmup_times: &[f64],
+) -> Simulation {
+    assert_eq!(incoming_clients.len(), warmup_times.len());
+    let mut warmups: Vec<f64> = Vec::new();
+    let mut delays: Vec<f64> = Vec::new();
+    let mut nap_times: Vec<f64> = Vec::new();
+
+    let mut previous_client_departure = 0.0;
+    for ((&client_arrival, &service_time), &warmup_time) in incoming_clients
+        .iter()
+        .zip(&service_times)
+        .zip(warmup_times)
+    {
+        let delay: f64;
+        let nap_time: f64;
+        let warmup: f64;
+
+        // If the new client arrive *after* the departure of the last one present in the node, the
+        // server has taken a break and needs to be warmed up.
+        // If the new client arrive *before* the departure of the last one present in the node, this
+        // client has to wait for the server. The server will not take a break.
+        if client_arrival > previous_client_departure {
+            delay = 0.0;
+            nap_time = client_arrival - previous_client_departure; // the nap time is the time that the server spent OFF
+            warmup = warmup_time;
+        } else {
+            delay = previous_client_departure - client_arrival;
+            nap_time = 0.0;
+            warmup = 0.0;
+        }
+        warmups.push(warmup);
+        delays.push(delay); // warning, the real time waited by clients are `delays.iter().zip(warmups).map(|(d, w)| d+w).collect()`
+        nap_times.push(nap_time);
+
+        // the nap time is only relevant to the server, so it's not counted in the sojourn time
+        previous_client_departure = client_arrival + delay + warmup + service_time;
+    }
+
+    Simulation::new(incoming_clients, delays, warmups, service_times, nap_times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measures::LITTLES_LAW_TOLERANCE;
+
+    /// A short queue with an idle period, whose last client is still there at `7.2`.
+    fn small_queue(first_service: f64) -> Simulation {
+        single_server_with_warmups(
+            vec![0.5, 1.0, 1.2, 4.0, 4.5, 4.6, 7.0],
+            vec![first_service, 0.3, 0.6, 0.5, 0.9, 0.2, 0.4],
+            &[0.5; 7],
+        )
+    }
+
+    #[test]
+    fn consistent_paths_satisfy_littles_law() {
+        for simulation in [
+            small_queue(0.8),
+            small_queue(0.8).truncated(2),
+            small_queue(0.8).with_horizon(7.2),
+            small_queue(0.8).with_horizon(7.2).truncated(4),
+        ] {
+            assert!(simulation.littles_law_discrepancy() < LITTLES_LAW_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn corrupted_path_fails_littles_law() {
+        // the first client leaves before it arrives
+        let simulation = small_queue(-1.5);
+        assert!(simulation.littles_law_discrepancy() > LITTLES_LAW_TOLERANCE);
+    }
+}
@@ -0,0 +1,393 @@
+//! The `theory.rs` file contains the theoretical values of the M/G/1 queue with setup (or with an
+//! exceptional first service): when a client finds the system empty, the server is off and must
+//! warm up before serving him, the clients arriving meanwhile wait in the queue.
+//!
+//! The averages only depend on the arrival rate and on the first moments of the service and setup
+//! laws, so any law can be studied as long as its moments are known. The distributions need the
+//! whole laws, through their Laplace-Stieltjes transforms, which are inverted numerically.
+
+use crate::transform::{euler_inversion, Complex};
+use crate::variables::Parameter;
+
+/// The first moments `𝔼[X]`, `𝔼[X²]` and `𝔼[X³]` of a random variable.
+/// The third one is only needed for the second moment of the waiting time.
+#[derive(Copy, Clone, Debug)]
+pub struct Moments {
+    pub first: f64,
+    pub second: f64,
+    pub third: Option<f64>,
+}
+
+impl Moments {
+    /// The variance `𝔼[X²] - 𝔼[X]²`.
+    pub fn variance(&self) -> f64 {
+        self.second - self.first * self.first
+    }
+
+    /// The squared coefficient of variation `Var[X] / 𝔼[X]²`.
+    pub fn scv(&self) -> f64 {
+        self.variance() / (self.first * self.first)
+    }
+
+    /// The mean residual time `𝔼[X²] / (2 𝔼[X])`, the average remaining time when arriving at a
+    /// random moment of an ongoing `X`.
+    pub fn residual(&self) -> f64 {
+        self.second / (2.0 * self.first)
+    }
+}
+
+/// The M/G/1 queue with setup.
+///
+/// Properties:
+///
+/// * `lambda`: The arrival rate (Poisson process).
+/// * `service`: The moments of the service time `B`.
+/// * `setup`: The moments of the setup (warmup) time `T`.
+#[derive(Copy, Clone, Debug)]
+pub struct SetupQueue {
+    pub lambda: f64,
+    pub service: Moments,
+    pub setup: Moments,
+}
+
+impl SetupQueue {
+    /// The load `ρ = λ 𝔼[B]`, which must be less than one for the queue to be stable.
+    pub fn rho(&self) -> f64 {
+        self.lambda * self.service.first
+    }
+
+    /// The probability that the server is serving a client.
+    pub fn probability_p_busy(&self) -> f64 {
+        self.rho()
+    }
+
+    /// The probability that the server is off. The idle time `1 - ρ` is shared between the naps,
+    /// of mean `1 / λ`, and the setups, of mean `𝔼[T]`.
+    pub fn probability_p_off(&self) -> f64 {
+        (1.0 - self.rho()) / (1.0 + self.lambda * self.setup.first)
+    }
+
+    /// The probability that the server is warming up.
+    pub fn probability_p_setup(&self) -> f64 {
+        (1.0 - self.rho()) * self.lambda * self.setup.first / (1.0 + self.lambda * self.setup.first)
+    }
+
+    /// The average waiting time of the M/G/1 queue without setup (Pollaczek-Khinchine).
+    fn avg_waiting_without_setup(&self) -> f64 {
+        self.lambda * self.service.second / (2.0 * (1.0 - self.rho()))
+    }
+
+    /// The fraction of the clients that find the server off and pay for its warmup, which is the
+    /// probability that the server is off, as Poisson arrivals see time averages (PASTA).
+    pub fn probability_warmup(&self) -> f64 {
+        self.probability_p_off()
+    }
+
+    /// The average stay time of a client that found the server off: the whole setup, then its
+    /// own service.
+    pub fn avg_stay_with_warmup(&self) -> f64 {
+        self.setup.first + self.service.first
+    }
+
+    /// The average extra delay `Y` caused by the setup. The waiting time decomposes into the
+    /// waiting time of the M/G/1 queue plus this independent extra delay, whose transform is
+    /// `(T*(s) + λ (1 - T*(s)) / s) / (1 + λ 𝔼[T])`.
+    fn avg_setup_delay(&self) -> f64 {
+        (self.setup.first + self.lambda * self.setup.second / 2.0)
+            / (1.0 + self.lambda * self.setup.first)
+    }
+
+    /// The average waiting time `𝔼[W]`, from the arrival to the beginning of the service.
+    pub fn avg_waiting(&self) -> f64 {
+        self.avg_waiting_without_setup() + self.avg_setup_delay()
+    }
+
+    /// The average stay time `𝔼[S] = 𝔼[W] + 𝔼[B]`.
+    pub fn avg_stay(&self) -> f64 {
+        self.avg_waiting() + self.service.first
+    }
+
+    /// The second moment of the waiting time `𝔼[W²]`. It needs the third moments of both the
+    /// service and the setup laws, so it is `None` when one of them is unknown.
+    pub fn second_moment_waiting(&self) -> Option<f64> {
+        let rho = self.rho();
+        let w = self.avg_waiting_without_setup();
+        let ww = 2.0 * w * w + self.lambda * self.service.third? / (3.0 * (1.0 - rho));
+
+        let y = self.avg_setup_delay();
+        let yy = (self.setup.second + self.lambda * self.setup.third? / 3.0)
+            / (1.0 + self.lambda * self.setup.first);
+
+        Some(ww + 2.0 * w * y + yy)
+    }
+
+    /// The nap of the server, from the departure that empties the system to the next arrival:
+    /// Exponential of rate `λ`.
+    pub fn nap(&self) -> Moments {
+        Moments {
+            first: 1.0 / self.lambda,
+            second: 2.0 / self.lambda.powi(2),
+            third: Some(6.0 / self.lambda.powi(3)),
+        }
+    }
+
+    /// The number `K = 1 + N_T` of clients waiting at the end of the setup: the one that woke the
+    /// server up, plus the `N_T` arrivals during the setup.
+    fn clients_after_setup(&self) -> Moments {
+        let mean = 1.0 + self.lambda * self.setup.first;
+        let variance = self.lambda * self.setup.first + self.lambda.powi(2) * self.setup.variance();
+        Moments {
+            first: mean,
+            second: variance + mean * mean,
+            third: None,
+        }
+    }
+
+    /// The moments of a sum of `K` independent copies of `X`, `K` being independent of them
+    /// (the variance is `𝔼[K] Var[X] + Var[K] 𝔼[X]²`).
+    fn random_sum(k: Moments, x: Moments) -> Moments {
+        let mean = k.first * x.first;
+        let variance = k.first * x.variance() + k.variance() * x.first.powi(2);
+        Moments {
+            first: mean,
+            second: variance + mean * mean,
+            third: None,
+        }
+    }
+
+    /// The busy period of the server, from the end of the setup to the next time the system is
+    /// empty.
+    ///
+    /// Each of the `K` clients waiting at the end of the setup opens an ordinary M/G/1 busy
+    /// period `Θ`, with `𝔼[Θ] = 𝔼[B] / (1 - ρ)` and `𝔼[Θ²] = 𝔼[B²] / (1 - ρ)³`, so the busy period
+    /// is the sum of `K` independent `Θ`.
+    pub fn busy_period(&self) -> Moments {
+        let rho = self.rho();
+        let ordinary = Moments {
+            first: self.service.first / (1.0 - rho),
+            second: self.service.second / (1.0 - rho).powi(3),
+            third: None,
+        };
+        Self::random_sum(self.clients_after_setup(), ordinary)
+    }
+
+    /// The number of clients served during a busy period.
+    ///
+    /// An ordinary M/G/1 busy period serves `Γ` clients, with `𝔼[Γ] = 1 / (1 - ρ)` and
+    /// `Var[Γ] = (ρ (1 - ρ) + λ² 𝔼[B²]) / (1 - ρ)³`, and the busy period is made of `K` of them.
+    pub fn jobs_per_busy_period(&self) -> Moments {
+        let rho = self.rho();
+        let mean = 1.0 / (1.0 - rho);
+        let variance =
+            (rho * (1.0 - rho) + self.lambda.powi(2) * self.service.second) / (1.0 - rho).powi(3);
+        let ordinary = Moments {
+            first: mean,
+            second: variance + mean * mean,
+            third: None,
+        };
+        Self::random_sum(self.clients_after_setup(), ordinary)
+    }
+
+    /// The average length of a whole cycle nap → setup → busy period, `(1 + λ 𝔼[T]) / (λ (1 - ρ))`.
+    pub fn avg_cycle(&self) -> f64 {
+        self.nap().first + self.setup.first + self.busy_period().first
+    }
+
+    /// The average number of clients in the system `𝔼[N] = λ 𝔼[S]` (Little's law).
+    pub fn avg_in_system(&self) -> f64 {
+        self.lambda * self.avg_stay()
+    }
+
+    /// The average number of clients waiting in the queue `𝔼[N_q] = λ 𝔼[W]`.
+    pub fn avg_in_queue(&self) -> f64 {
+        self.lambda * self.avg_waiting()
+    }
+}
+
+/// The M/G/1 queue with setup, described by the laws themselves rather than by their moments, to
+/// get the distributions of the waiting and stay times.
+///
+/// Properties:
+///
+/// * `lambda`: The arrival rate (Poisson process).
+/// * `service`: The law of the service time `B`.
+/// * `setup`: The law of the setup (warmup) time `T`.
+pub struct SetupQueueLaws<'a> {
+    pub lambda: f64,
+    pub service: &'a Parameter,
+    pub setup: &'a Parameter,
+}
+
+impl<'a> SetupQueueLaws<'a> {
+    /// The same queue, only described by the moments of its laws.
+    pub fn moments(&self) -> SetupQueue {
+        SetupQueue {
+            lambda: self.lambda,
+            service: self.service.moments(),
+            setup: self.setup.moments(),
+        }
+    }
+
+    /// The Laplace-Stieltjes transform of the waiting time `W`.
+    ///
+    /// `W` is the sum of the waiting time of the M/G/1 queue without setup, whose transform is
+    /// given by Pollaczek-Khinchine `(1 - ρ) s / (s - λ (1 - B*(s)))`, and of the independent
+    /// extra delay `Y` caused by the setup, of transform `(T*(s) + λ (1 - T*(s)) / s) / (1 + λ 𝔼[T])`.
+    pub fn waiting_lst(&self, s: Complex) -> Complex {
+        let one = Complex::from(1.0);
+        let lambda = Complex::from(self.lambda);
+        let rho = self.moments().rho();
+
+        let b = self.service.lst(s);
+        let without_setup = Complex::from(1.0 - rho) * s / (s - lambda * (one - b));
+
+        let t = self.setup.lst(s);
+        let setup_delay = (t + lambda * (one - t) / s)
+            / Complex::from(1.0 + self.lambda * self.setup.moments().first);
+
+        without_setup * setup_delay
+    }
+
+    /// The Laplace-Stieltjes transform of the stay time `S = W + B`, where the service `B` of the
+    /// client is independent of its waiting time.
+    pub fn stay_lst(&self, s: Complex) -> Complex {
+        self.waiting_lst(s) * self.service.lst(s)
+    }
+
+    /// The probability `P(W > t)` that a client waits more than `t`.
+    pub fn waiting_tail(&self, t: f64) -> f64 {
+        tail(|s| self.waiting_lst(s), t)
+    }
+
+    /// The probability `P(S > t)` that a client stays more than `t`.
+    pub fn stay_tail(&self, t: f64) -> f64 {
+        tail(|s| self.stay_lst(s), t)
+    }
+
+    /// The CDF `P(S ≤ t)` of the stay time.
+    pub fn stay_cdf(&self, t: f64) -> f64 {
+        1.0 - self.stay_tail(t)
+    }
+
+    /// The `p`-quantile of the stay time, the smallest `t` such that `P(S ≤ t) ≥ p`.
+    pub fn stay_quantile(&self, p: f64) -> f64 {
+        assert!(0.0 < p && p < 1.0);
+        // the quantile is bracketed by doubling the mean, then found by bisection
+        let mut upper = self.moments().avg_stay();
+        while self.stay_cdf(upper) < p {
+            upper *= 2.0;
+        }
+        let mut lower = 0.0;
+        while upper - lower > 1e-6 * upper {
+            let middle = (lower + upper) / 2.0;
+            if self.stay_cdf(middle) < p {
+                lower = middle;
+            } else {
+                upper = middle;
+            }
+        }
+        upper
+    }
+
+    /// The distribution of the number of clients left behind by a departure, from `0` to
+    /// `max_jobs`, given by the Markov chain embedded at the departures.
+    ///
+    /// A departure that leaves `i ≥ 1` clients is followed by one leaving `i - 1 + A`, `A` being
+    /// the arrivals during a service (`a_j = P(A = j)`); one that leaves the system empty is
+    /// followed by one leaving `A'`, the arrivals during the exceptional first service, that is the
+    /// setup plus the service (`b_j = P(A' = j)`). The chain crosses each level down only through
+    /// `A = 0`, hence `π_j a_0 = π_0 b̄_j + ∑_(1 ≤ i < j) π_i ā_(j - i + 1)` (`ā` and `b̄` being the
+    /// tails), a recursion without subtraction, started from `π_0 = 1 / 𝔼[clients by busy period]`.
+    pub fn departure_jobs_distribution(&self, max_jobs: usize) -> Vec<f64> {
+        let service_counts = self.service.arrival_counts(self.lambda, max_jobs + 1);
+        let setup_counts = self.setup.arrival_counts(self.lambda, max_jobs + 1);
+        // the arrivals during the setup and during the service are independent
+        let first_service_counts = (0..=max_jobs + 1)
+            .map(|j| {
+                (0..=j)
+                    .map(|i| setup_counts[i] * service_counts[j - i])
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+        let tails = |counts: &[f64]| {
+            let mut cumulative: f64 = 0.0;
+            counts
+                .iter()
+                .map(|c| {
+                    let tail = (1.0 - cumulative).max(0.0);
+                    cumulative += c;
+                    tail
+                })
+                .collect::<Vec<_>>()
+        };
+        let (a, a_tails, b_tails) = (
+            &service_counts,
+            tails(&service_counts),
+            tails(&first_service_counts),
+        );
+
+        let mut pi = vec![1.0 / self.moments().jobs_per_busy_period().first];
+        for j in 1..=max_jobs {
+            let up = pi[0] * b_tails[j] + (1..j).map(|i| pi[i] * a_tails[j - i + 1]).sum::<f64>();
+            pi.push(up / a[0]);
+        }
+        pi
+    }
+
+    /// The time-average distribution of the number of clients in the system `N`, from `0` to
+    /// `max_jobs`.
+    ///
+    /// `N` moves by one client at a time, so the departures leave behind the same distribution
+    /// as the one seen by the arrivals, which is the time-average one as the arrivals are Poisson
+    /// (PASTA): it is the distribution embedded at the departures.
+    pub fn jobs_in_system_distribution(&self, max_jobs: usize) -> Vec<f64> {
+        self.departure_jobs_distribution(max_jobs)
+    }
+}
+
+/// The tail `P(X > t)` of a positive random variable given by its Laplace-Stieltjes transform.
+/// The transform of the tail is `(1 - X*(s)) / s`, which is inverted rather than the CDF to keep
+/// the precision on the small probabilities.
+fn tail<F>(lst: F, t: f64) -> f64
+where
+    F: Fn(Complex) -> Complex,
+{
+    if t <= 0.0 {
+        return 1.0;
+    }
+    euler_inversion(|s| (Complex::from(1.0) - lst(s)) / s, t).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variables::{ErlangParameter, ExponentialParameter};
+
+    #[test]
+    fn embedded_chain_mean_matches_the_closed_form() {
+        let lambda = 1.0;
+        let setup = Parameter::Exponential(ExponentialParameter { lambda: 0.6 });
+        for service in [
+            Parameter::Exponential(ExponentialParameter { lambda: 1.0 / 0.8 }),
+            Parameter::Erlang(ErlangParameter {
+                k: 3,
+                beta: 0.8 / 3.0,
+            }),
+        ] {
+            let laws = SetupQueueLaws {
+                lambda,
+                service: &service,
+                setup: &setup,
+            };
+            let distribution = laws.jobs_in_system_distribution(500);
+            let mean = distribution
+                .iter()
+                .enumerate()
+                .map(|(j, p)| j as f64 * p)
+                .sum::<f64>();
+
+            assert!((distribution.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert!((mean - laws.moments().avg_in_system()).abs() < 1e-9);
+        }
+    }
+}
@@ -1,33 +1,90 @@
 //! The `variable.rs` file contains the boilerplate needed to generate values according to
 //! a specific distribution.
 
-use rand::distributions::Distribution;
+use crate::theory::Moments;
+use crate::transform::Complex;
+use rand::distributions::{Distribution, Open01};
+use rand::Rng;
 use rand_distr::{Exp, Gamma, Poisson};
 
 /// The generator will generate values according to a specific distribution.
 /// The distribution is specified through the `parameter` variable, which can only be certain type.
 pub fn generator(parameter: &Parameter, n: usize) -> Vec<f64> {
+    generator_with(parameter, n, &mut rand::thread_rng())
+}
+
+/// Same as `generator`, but the values are drawn from the given random number generator.
+///
+/// With a seeded generator (e.g. `StdRng::seed_from_u64(seed)`), the same values are drawn again
+/// for the same seed, which allows to compare several systems with common random numbers.
+pub fn generator_with<R: Rng + ?Sized>(parameter: &Parameter, n: usize, rng: &mut R) -> Vec<f64> {
     match parameter {
         Parameter::Poisson(p) => {
             Poisson::new(p.lambda) // instantiate a Poisson distribution generator
                 .unwrap()
-                .sample_iter(&mut rand::thread_rng()) // generator following a poisson distribution
+                .sample_iter(rng) // generator following a poisson distribution
                 .take(n) // iter n times (generate n values)
                 .collect() // collect values into a nice `Vec`tor
         }
         Parameter::Exponential(p) => Exp::new(p.lambda)
             .unwrap()
-            .sample_iter(&mut rand::thread_rng())
+            .sample_iter(rng)
             .take(n)
             .collect(),
         Parameter::Erlang(p) => Gamma::new(p.k as f64, p.beta)
             .unwrap()
-            .sample_iter(&mut rand::thread_rng())
+            .sample_iter(rng)
             .take(n)
             .collect(),
+        Parameter::HyperExponential(p) => (0..n)
+            .map(|_| {
+                let u: f64 = rng.sample(Open01);
+                let rate = if u < p.p { p.lambda_1 } else { p.lambda_2 };
+                Exp::new(rate).unwrap().sample(rng)
+            })
+            .collect(),
     }
 }
 
+/// Generate `n` antithetic pairs: both values of a pair are drawn by inverse transform from the
+/// same uniform numbers, `u` for the first one and `1 - u` for the second one.
+///
+/// Each list follows the law described by `parameter`, but the two values of a pair are negatively
+/// correlated, so that the average of two simulations fed with the two lists usually varies less
+/// than the average of two independent ones.
+pub fn antithetic_generator(parameter: &Parameter, n: usize) -> (Vec<f64>, Vec<f64>) {
+    antithetic_generator_with(parameter, n, &mut rand::thread_rng())
+}
+
+/// Same as `antithetic_generator`, but the uniform numbers are drawn from the given random number
+/// generator.
+pub fn antithetic_generator_with<R: Rng + ?Sized>(
+    parameter: &Parameter,
+    n: usize,
+    rng: &mut R,
+) -> (Vec<f64>, Vec<f64>) {
+    // an Erlang value is the sum of `k` Exponential ones, each one drawn from its own uniform, and
+    // a hyper-exponential value needs one more uniform to choose its phase
+    let uniforms_by_value = match parameter {
+        Parameter::Erlang(p) => p.k,
+        Parameter::HyperExponential(_) => 2,
+        _ => 1,
+    };
+    (0..n)
+        .map(|_| {
+            let u = rng
+                .sample_iter(Open01)
+                .take(uniforms_by_value)
+                .collect::<Vec<f64>>();
+            let v = u.iter().map(|u| 1.0 - u).collect::<Vec<_>>();
+            (
+                parameter.inverse_transform(&u),
+                parameter.inverse_transform(&v),
+            )
+        })
+        .unzip()
+}
+
 /// Container for a Poisson random variable parameter
 pub struct PoissonParameter {
     /// Shape parameter for a Poisson distribution
@@ -47,10 +104,312 @@ pub struct ErlangParameter {
     pub beta: f64,
 }
 
+/// Container for a hyper-exponential random variable parameter: an Exponential value of rate
+/// `lambda_1` with probability `p`, of rate `lambda_2` otherwise. Its squared coefficient of
+/// variation is at least `1`.
+pub struct HyperExponentialParameter {
+    /// Probability of the first phase
+    pub p: f64,
+    /// Rate of the first phase
+    pub lambda_1: f64,
+    /// Rate of the second phase
+    pub lambda_2: f64,
+}
+
+impl HyperExponentialParameter {
+    /// The hyper-exponential law with balanced means (`p / lambda_1 = (1 - p) / lambda_2`) having
+    /// the given mean and squared coefficient of variation (at least `1`).
+    pub fn balanced(mean: f64, scv: f64) -> Self {
+        assert!(
+            scv >= 1.0,
+            "a hyper-exponential law varies at least as an Exponential one"
+        );
+        let p = (1.0 + ((scv - 1.0) / (scv + 1.0)).sqrt()) / 2.0;
+        HyperExponentialParameter {
+            p,
+            lambda_1: 2.0 * p / mean,
+            lambda_2: 2.0 * (1.0 - p) / mean,
+        }
+    }
+
+    /// The probabilities and the rates of both phases.
+    fn phases(&self) -> [(f64, f64); 2] {
+        [(self.p, self.lambda_1), (1.0 - self.p, self.lambda_2)]
+    }
+}
+
 /// Enumeration of available parameter types
 /// This allows us to use rust strong typing checking system
 pub enum Parameter {
     Poisson(PoissonParameter),
     Exponential(ExponentialParameter),
     Erlang(ErlangParameter),
+    HyperExponential(HyperExponentialParameter),
+}
+
+impl Parameter {
+    /// A law of the given mean and squared coefficient of variation: Erlang below `1`, Exponential
+    /// at `1`, and hyper-exponential with balanced means above. An Erlang law only reaches the
+    /// SCVs `1 / k`, so any other SCV below `1` is rejected.
+    pub fn with_scv(mean: f64, scv: f64) -> Self {
+        assert!(mean > 0.0 && scv > 0.0);
+        if scv < 1.0 {
+            let k = 1.0 / scv;
+            assert!(
+                (k - k.round()).abs() < 1e-9,
+                "an Erlang law cannot have a SCV of {}",
+                scv
+            );
+        }
+        if scv > 1.0 {
+            Parameter::HyperExponential(HyperExponentialParameter::balanced(mean, scv))
+        } else if scv == 1.0 {
+            Parameter::Exponential(ExponentialParameter { lambda: 1.0 / mean })
+        } else {
+            let k = (1.0 / scv).round() as usize;
+            Parameter::Erlang(ErlangParameter {
+                k,
+                beta: mean / k as f64,
+            })
+        }
+    }
+
+    /// The first three moments of the law described by this parameter.
+    pub fn moments(&self) -> Moments {
+        match self {
+            Parameter::Poisson(p) => Moments {
+                first: p.lambda,
+                second: p.lambda * (1.0 + p.lambda),
+                third: Some(p.lambda * (1.0 + 3.0 * p.lambda + p.lambda * p.lambda)),
+            },
+            Parameter::Exponential(p) => Moments {
+                first: 1.0 / p.lambda,
+                second: 2.0 / p.lambda.powi(2),
+                third: Some(6.0 / p.lambda.powi(3)),
+            },
+            Parameter::Erlang(p) => {
+                let k = p.k as f64;
+                Moments {
+                    first: k * p.beta,
+                    second: k * (k + 1.0) * p.beta.powi(2),
+                    third: Some(k * (k + 1.0) * (k + 2.0) * p.beta.powi(3)),
+                }
+            }
+            Parameter::HyperExponential(p) => {
+                let moment = |order: i32, factorial: f64| {
+                    p.phases()
+                        .iter()
+                        .map(|&(probability, rate)| probability * factorial / rate.powi(order))
+                        .sum::<f64>()
+                };
+                Moments {
+                    first: moment(1, 1.0),
+                    second: moment(2, 2.0),
+                    third: Some(moment(3, 6.0)),
+                }
+            }
+        }
+    }
+
+    /// The Laplace-Stieltjes transform `𝔼[e^(-sX)]` of the law described by this parameter.
+    pub fn lst(&self, s: Complex) -> Complex {
+        match self {
+            Parameter::Poisson(p) => {
+                (Complex::from(p.lambda) * ((-s).exp() - Complex::from(1.0))).exp()
+            }
+            Parameter::Exponential(p) => Complex::from(p.lambda) / (Complex::from(p.lambda) + s),
+            Parameter::Erlang(p) => (Complex::from(1.0)
+                / (Complex::from(1.0) + Complex::from(p.beta) * s))
+                .powi(p.k as u32),
+            Parameter::HyperExponential(p) => p
+                .phases()
+                .iter()
+                .map(|&(probability, rate)| {
+                    Complex::from(probability * rate) / (Complex::from(rate) + s)
+                })
+                .fold(Complex::from(0.0), |a, b| a + b),
+        }
+    }
+
+    /// The probabilities `a_j = P(A = j)` for `j` from `0` to `max_count`, `A` being the number of
+    /// arrivals of a Poisson process of rate `lambda` during a time `X` of this law:
+    /// `a_j = 𝔼[e^(-λX) (λX)^j / j!]`.
+    ///
+    /// The count is Geometric for the Exponential law and Negative Binomial for the Erlang law.
+    pub fn arrival_counts(&self, lambda: f64, max_count: usize) -> Vec<f64> {
+        // the counts are computed in logarithms so that `(λX)^j / j!` does not overflow
+        let poisson = |mean: f64, j: usize| {
+            if mean == 0.0 {
+                return (j == 0) as usize as f64;
+            }
+            let log_factorial = (1..=j).map(|i| (i as f64).ln()).sum::<f64>();
+            (j as f64 * mean.ln() - mean - log_factorial).exp()
+        };
+        match self {
+            Parameter::Poisson(p) => {
+                // the value `m` is drawn with the probability `e^(-Λ) Λ^m / m!`, far enough in the
+                // tail for the missing mass to be negligible
+                let values = (p.lambda + 10.0 * p.lambda.sqrt() + 20.0).ceil() as usize;
+                (0..=max_count)
+                    .map(|j| {
+                        (0..=values)
+                            .map(|m| poisson(p.lambda, m) * poisson(lambda * m as f64, j))
+                            .sum()
+                    })
+                    .collect()
+            }
+            Parameter::Exponential(p) => {
+                let success = p.lambda / (p.lambda + lambda);
+                (0..=max_count)
+                    .map(|j| success * (1.0 - success).powi(j as i32))
+                    .collect()
+            }
+            Parameter::Erlang(p) => {
+                let success = 1.0 / (1.0 + lambda * p.beta);
+                let mut count = success.powi(p.k as i32);
+                (0..=max_count)
+                    .map(|j| {
+                        if j > 0 {
+                            count *= (j + p.k - 1) as f64 / j as f64 * (1.0 - success);
+                        }
+                        count
+                    })
+                    .collect()
+            }
+            Parameter::HyperExponential(p) => (0..=max_count)
+                .map(|j| {
+                    p.phases()
+                        .iter()
+                        .map(|&(probability, rate)| {
+                            let success = rate / (rate + lambda);
+                            probability * success * (1.0 - success).powi(j as i32)
+                        })
+                        .sum()
+                })
+                .collect(),
+        }
+    }
+
+    /// The value of the law drawn from some uniform numbers of `(0, 1)` by inverse transform (one
+    /// uniform number, `k` of them for the Erlang law, and two for the hyper-exponential law: one
+    /// for the phase, one for the value). The value grows with every uniform number.
+    fn inverse_transform(&self, uniforms: &[f64]) -> f64 {
+        match self {
+            Parameter::Poisson(p) => {
+                // the smallest `k` whose cumulative probability reaches `u`
+                let u = uniforms[0];
+                let mut k = 0;
+                let mut probability = (-p.lambda).exp();
+                let mut cumulative = probability;
+                while cumulative < u && probability > 0.0 {
+                    k += 1;
+                    probability *= p.lambda / k as f64;
+                    cumulative += probability;
+                }
+                k as f64
+            }
+            Parameter::Exponential(p) => -(1.0 - uniforms[0]).ln() / p.lambda,
+            Parameter::Erlang(p) => -p.beta * uniforms.iter().map(|u| (1.0 - u).ln()).sum::<f64>(),
+            Parameter::HyperExponential(p) => {
+                // the small uniform numbers choose the fastest phase
+                let [(p_1, lambda_1), (p_2, lambda_2)] = p.phases();
+                let (p_fast, fast, slow) = if lambda_1 >= lambda_2 {
+                    (p_1, lambda_1, lambda_2)
+                } else {
+                    (p_2, lambda_2, lambda_1)
+                };
+                let rate = if uniforms[0] < p_fast { fast } else { slow };
+                -(1.0 - uniforms[1]).ln() / rate
+            }
+        }
+    }
+
+    /// The cumulant generating function `ln 𝔼[e^(θX)]` of the law, finite for `θ` below the rate of
+    /// the Exponential and Erlang laws (below the smallest rate of the hyper-exponential law).
+    pub fn cumulant(&self, theta: f64) -> f64 {
+        match self {
+            Parameter::Poisson(p) => p.lambda * (theta.exp() - 1.0),
+            Parameter::Exponential(p) => (p.lambda / (p.lambda - theta)).ln(),
+            Parameter::Erlang(p) => -(p.k as f64) * (1.0 - theta * p.beta).ln(),
+            Parameter::HyperExponential(p) => p
+                .phases()
+                .iter()
+                .map(|&(probability, rate)| probability * rate / (rate - theta))
+                .sum::<f64>()
+                .ln(),
+        }
+    }
+
+    /// The exponentially tilted law, whose density is `f(x) e^(θx - κ(θ))` (`κ` being the
+    /// cumulant): a law of the same family, with bigger values when `θ > 0`.
+    ///
+    /// A value `x` drawn from the tilted law has the likelihood ratio `e^(κ(θ) - θx)` with respect
+    /// to the original law.
+    pub fn tilted(&self, theta: f64) -> Parameter {
+        match self {
+            Parameter::Poisson(p) => Parameter::Poisson(PoissonParameter {
+                lambda: p.lambda * theta.exp(),
+            }),
+            Parameter::Exponential(p) => Parameter::Exponential(ExponentialParameter {
+                lambda: p.lambda - theta,
+            }),
+            Parameter::Erlang(p) => Parameter::Erlang(ErlangParameter {
+                k: p.k,
+                beta: p.beta / (1.0 - theta * p.beta),
+            }),
+            Parameter::HyperExponential(p) => {
+                // every phase is tilted, and weighted by its own moment generating function
+                let first = p.p * p.lambda_1 / (p.lambda_1 - theta);
+                Parameter::HyperExponential(HyperExponentialParameter {
+                    p: first / self.cumulant(theta).exp(),
+                    lambda_1: p.lambda_1 - theta,
+                    lambda_2: p.lambda_2 - theta,
+                })
+            }
+        }
+    }
+
+    /// The rate of the law: `lambda` for the Exponential law, `1 / beta` for the Erlang law, and the
+    /// inverse of the mean for the hyper-exponential law (whose two rates are proportional to it).
+    /// The sensitivities are computed with respect to this rate, which is the arrival rate when
+    /// the law describes the inter-arrival times. The Poisson law is the exception: its `lambda`
+    /// is its mean, not a rate.
+    pub fn rate(&self) -> f64 {
+        match self {
+            Parameter::Poisson(p) => p.lambda,
+            Parameter::Exponential(p) => p.lambda,
+            Parameter::Erlang(p) => 1.0 / p.beta,
+            Parameter::HyperExponential(_) => 1.0 / self.moments().first,
+        }
+    }
+
+    /// Whether the rate is a scale parameter (a value `x` drawn with the rate `r` is `x' r / r'`
+    /// with the rate `r'`), which is needed by the perturbation analysis. It is not the case of
+    /// the Poisson law, which is discrete.
+    pub fn is_scale_family(&self) -> bool {
+        !matches!(self, Parameter::Poisson(_))
+    }
+
+    /// The score `∂ ln f(x) / ∂r` of a value `x`, `f` being the density (or the probability mass)
+    /// of the law and `r` its rate. Its expectation is `0`.
+    pub fn score(&self, x: f64) -> f64 {
+        match self {
+            Parameter::Poisson(p) => x / p.lambda - 1.0,
+            Parameter::Exponential(p) => 1.0 / p.lambda - x,
+            Parameter::Erlang(p) => p.k as f64 * p.beta - x,
+            Parameter::HyperExponential(p) => {
+                // with the rates `r c_i`, `∂f/∂r = ∑ p_i c_i e^(-r c_i x) (1 - r c_i x)`
+                let density = |derivative: bool| {
+                    p.phases()
+                        .iter()
+                        .map(|&(probability, rate)| {
+                            let factor = if derivative { 1.0 - rate * x } else { 1.0 };
+                            probability * rate * (-rate * x).exp() * factor
+                        })
+                        .sum::<f64>()
+                };
+                density(true) / (self.rate() * density(false))
+            }
+        }
+    }
 }